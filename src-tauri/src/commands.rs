@@ -1,29 +1,203 @@
-use crate::persistence;
-use crate::pty::{AppState, SessionGroup, SessionInfo, SharedPtyManager};
+use crate::ipc::IpcState;
+use crate::persistence::{self, LayoutPreview, PersistenceHealth, StateBackup, Workspace};
+use crate::pty::color;
+use crate::pty::dependency;
+use crate::pty::dotenv::EnvFile;
+use crate::pty::html_export::HtmlTheme;
+use crate::pty::input_pacer::InputPacingStats;
+use crate::pty::manager::{ForegroundProcess, OutputEncoding, ResourceUsage, SessionMetrics, TapInfo, TapTarget};
+use crate::pty::manager::{PtyManager, DEFAULT_TERM};
+use crate::pty::process::{self, ProcessNode, Signal};
+use crate::pty::resource_limits::ResourceLimits;
+use crate::pty::scrollback::SessionRead;
+use crate::pty::search::{self, SearchHit, SessionMatches};
+use crate::pty::self_test::{self, SelfTestReport};
+use crate::pty::termios::EchoState;
+use crate::pty::{ActiveSessionFallback, AppState, CloseBehavior, PathMode, Profile, SessionCommand, SessionGroup, SessionInfo, SessionKind, SessionOrigin, SessionStatus, Settings, SharedPtyManager, SortKey, STATE_VERSION};
 use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use tauri::State;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// How long a `Logout` close waits for the shell to exit before killing it
+const LOGOUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `ping_session` waits for its sentinel to come back
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// State for storing groups (sessions are in PtyManager)
 pub struct GroupState {
     pub groups: Mutex<Vec<SessionGroup>>,
+    pub profiles: Mutex<Vec<Profile>>,
     pub active_session_id: Mutex<Option<String>>,
+    /// The state as last written to (or read from) disk
+    pub last_saved: Mutex<Option<SavedState>>,
+    /// Outcome of the startup restore, once it has finished
+    pub restore_report: Mutex<Option<RestoreReport>>,
 }
 
 impl Default for GroupState {
     fn default() -> Self {
         Self {
             groups: Mutex::new(Vec::new()),
+            profiles: Mutex::new(Vec::new()),
             active_session_id: Mutex::new(None),
+            last_saved: Mutex::new(None),
+            restore_report: Mutex::new(None),
+        }
+    }
+}
+
+/// A persisted state and its hash
+pub struct SavedState {
+    pub hash: u64,
+    pub state: AppState,
+}
+
+impl SavedState {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            hash: persistence::state_hash(&state),
+            state,
         }
     }
 }
 
+/// Payload of `restore-complete`, emitted once startup restore has finished
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RestoreReport {
+    pub sessions_restored: usize,
+    pub errors: Vec<RestoreError>,
+    /// How long the restore took
+    pub elapsed_ms: u64,
+}
+
+/// Something that went wrong during restore (or a group restart); `session_id`
+/// is unset for whole-state failures
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RestoreError {
+    pub session_id: Option<String>,
+    pub error: String,
+}
+
+/// Payload of `session-restarting`, emitted as `restart_group` gets to each session
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionRestarting {
+    pub id: String,
+}
+
+/// What `restart_group` did, in tab order
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GroupRestartReport {
+    pub restarted: Vec<SessionInfo>,
+    pub errors: Vec<RestoreError>,
+}
+
+/// Which machine the loaded state came from, for `get_state_host`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StateHost {
+    /// Host recorded in the saved state (unset for states saved before hosts were recorded)
+    pub saved_on: Option<String>,
+    pub current: Option<String>,
+    /// False when the state was saved on a different machine
+    pub same_host: bool,
+}
+
+/// Round-trip time measured by `ping_session`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PingResult {
+    pub latency_ms: f64,
+}
+
+/// Whether the in-memory state differs from the last save
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StateDirtyReport {
+    pub dirty: bool,
+    /// Short human-readable descriptions of what changed
+    pub changes: Vec<String>,
+}
+
+/// Result of a `delete_session` call
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum DeleteOutcome {
+    /// The session was killed and removed
+    Deleted,
+    /// `exit` was sent; the session is removed once the shell logs out
+    LoggingOut,
+    /// The session's close behavior is `Confirm`; call again with `force`
+    NeedsConfirmation,
+}
+
+/// Outcome for one id passed to `delete_sessions`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionDeleteResult {
+    pub id: String,
+    /// `None` if the session was killed and removed
+    pub error: Option<String>,
+}
+
+/// Payload of `persistence-error`, emitted when saving the state fails
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PersistenceError {
+    pub error: String,
+}
+
+/// Assemble the in-memory sessions, groups, active id and settings into an `AppState`
+fn collect_state(pty_manager: &SharedPtyManager, group_state: &GroupState) -> AppState {
+    // Hold every lock at once so the snapshot is consistent
+    let groups = group_state.groups.lock();
+    let profiles = group_state.profiles.lock();
+    let manager = pty_manager.lock();
+    let active_session_id = group_state.active_session_id.lock();
+
+    // Scratch sessions are never persisted
+    let mut sessions: Vec<SessionInfo> = manager
+        .get_layout_sessions()
+        .into_iter()
+        .filter(|s| !s.ephemeral)
+        .collect();
+    // Stable order so identical state always serializes (and hashes) identically
+    sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+    let active_session_id = active_session_id
+        .clone()
+        .filter(|id| sessions.iter().any(|s| &s.id == id));
+
+    AppState {
+        version: STATE_VERSION,
+        sessions,
+        groups: groups.clone(),
+        profiles: profiles.clone(),
+        active_session_id,
+        settings: manager.settings().clone(),
+        hostname: persistence::current_hostname(),
+    }
+}
+
+/// Write a state to disk and remember it as the last saved state
+///
+/// A failed save also emits `persistence-error`, so the UI can warn even if the
+/// command's caller ignores the error.
+fn persist_state(app: &AppHandle, state: AppState) -> Result<(), String> {
+    if let Err(error) = persistence::save_state(&state) {
+        let _ = app.emit("persistence-error", PersistenceError {
+            error: error.clone(),
+        });
+        return Err(error);
+    }
+    *app.state::<GroupState>().last_saved.lock() = Some(SavedState::new(state));
+    Ok(())
+}
+
 // ============ Session Commands ============
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn create_session(
     pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
     name: String,
     shell: Option<String>,
     cwd: Option<String>,
@@ -31,46 +205,462 @@ pub fn create_session(
     startup_command: Option<String>,
     rows: Option<u16>,
     cols: Option<u16>,
+    cpu_limit: Option<f64>,
+    memory_limit: Option<u64>,
+    command: Option<String>,
+    then_shell: Option<bool>,
+    term: Option<String>,
+    env_file: Option<PathBuf>,
+    env_file_required: Option<bool>,
+    lazy: Option<bool>,
+    ephemeral: Option<bool>,
+    args: Option<Vec<String>>,
+    env: Option<HashMap<String, String>>,
+    startup_commands: Option<Vec<String>>,
+    startup_delay_ms: Option<u64>,
+    kind: Option<SessionKind>,
 ) -> Result<SessionInfo, String> {
+    let kind = kind.unwrap_or_default();
+    if let SessionKind::Ssh { ref host, .. } = kind {
+        if host.trim().is_empty() {
+            return Err("SSH host cannot be empty".to_string());
+        }
+    }
     let id = uuid::Uuid::new_v4().to_string();
-    let cwd_path = cwd.map(PathBuf::from);
     let rows = rows.unwrap_or(24);
     let cols = cols.unwrap_or(80);
+    let resource_limits = ResourceLimits::from_params(cpu_limit, memory_limit)?;
+
+    // Explicit startup commands win over the group's default; the singular
+    // `startup_command` is the older form of a one-command list
+    let startup_commands = startup_commands
+        .or_else(|| startup_command.map(|command| vec![command]))
+        .or_else(|| {
+            let group_id = group_id.as_ref()?;
+            group_state
+                .groups
+                .lock()
+                .iter()
+                .find(|g| &g.id == group_id)
+                .and_then(|g| g.default_startup_command.clone())
+                .map(|command| vec![command])
+        })
+        .unwrap_or_default();
+
+    let mut info = SessionInfo::new(
+        id.clone(),
+        name,
+        shell.unwrap_or_else(PtyManager::default_shell),
+        cwd.map(PathBuf::from).unwrap_or_else(PtyManager::default_cwd),
+    );
+    info.group_id = group_id;
+    info.kind = kind;
+    info.shell_args = args;
+    info.env = env.unwrap_or_default().into_iter().collect();
+    info.startup_commands = startup_commands;
+    info.startup_delay_ms = startup_delay_ms;
+    info.resource_limits = resource_limits;
+    info.command = command
+        .filter(|c| !c.trim().is_empty())
+        .map(|command| SessionCommand {
+            command,
+            then_shell: then_shell.unwrap_or(false),
+        });
+    info.term_override = term.filter(|t| !t.trim().is_empty());
+    info.ephemeral = ephemeral.unwrap_or(false);
+    info.env_file = env_file.map(|path| EnvFile {
+        path,
+        required: env_file_required.unwrap_or(true),
+    });
+
+    let manager = pty_manager.lock();
+    info.order = manager.next_session_order(info.group_id.as_deref());
+    if lazy.unwrap_or(false) {
+        // Spawned (and its startup command run) by spawn_pending_session
+        return Ok(manager.add_pending_session(info));
+    }
+    let info = manager.spawn_session(info, rows, cols)?;
+    run_startup_commands(pty_manager.inner().clone(), &info);
+
+    Ok(info)
+}
 
+/// Open a new session like `id`: same shell, arguments, directory, environment and
+/// group, placed right after it. Its startup commands only run if `rerun_startup_command` is set.
+#[tauri::command]
+pub fn duplicate_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    rows: Option<u16>,
+    cols: Option<u16>,
+    rerun_startup_command: Option<bool>,
+) -> Result<SessionInfo, String> {
     let manager = pty_manager.lock();
-    let mut info = manager.spawn_session(id.clone(), name, shell, cwd_path, rows, cols)?;
+    let source = manager
+        .get_session_info(&id)
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+
+    let new_id = uuid::Uuid::new_v4().to_string();
+    let mut info = SessionInfo::new(new_id.clone(), source.name.clone(), source.shell.clone(), source.cwd.clone());
+    info.group_id = source.group_id.clone();
+    info.order = manager.next_session_order(info.group_id.as_deref());
+    info.kind = source.kind.clone();
+    info.shell_args = source.shell_args.clone();
+    info.env = source.env.clone();
+    info.env_file = source.env_file.clone();
+    info.term_override = source.term_override.clone();
+    info.resource_limits = source.resource_limits.clone();
+    info.input_rate_limit = source.input_rate_limit;
+    info.color = source.color.clone();
+    info.tags = source.tags.clone();
+    info.origin = SessionOrigin::Duplicate(id.clone());
+    if rerun_startup_command.unwrap_or(false) {
+        info.startup_commands = source.startup_commands.clone();
+        info.startup_delay_ms = source.startup_delay_ms;
+    }
+
+    let info = manager.spawn_session(info, rows.unwrap_or(source.rows), cols.unwrap_or(source.cols))?;
+    manager.move_session_after(&new_id, &id)?;
+    run_startup_commands(pty_manager.inner().clone(), &info);
+
+    manager
+        .get_session_info(&new_id)
+        .ok_or_else(|| format!("Session not found: {}", new_id))
+}
 
-    if group_id.is_some() {
-        info.group_id = group_id;
+/// Replace a session's shell with a fresh one, keeping the session itself
+#[tauri::command]
+pub fn restart_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<SessionInfo, String> {
+    restart(&pty_manager, &id)
+}
+
+/// Restart a session without holding the manager lock while its old shell exits
+fn restart(pty_manager: &SharedPtyManager, id: &str) -> Result<SessionInfo, String> {
+    let mut restart = pty_manager.lock().begin_restart(id)?;
+    restart.stop_old();
+    pty_manager.lock().finish_restart(restart)
+}
+
+/// Restart every session in a group one at a time, in tab order, and type in
+/// their startup commands again. `session-restarting` is emitted as each one
+/// begins and `session-restarted` once its new shell is up.
+#[tauri::command]
+pub async fn restart_group(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+) -> Result<GroupRestartReport, String> {
+    if !group_state.groups.lock().iter().any(|g| g.id == id) {
+        return Err(format!("Group not found: {}", id));
     }
+    let pty_manager = pty_manager.inner().clone();
+    // Each restart waits for the old shell to exit
+    tokio::task::spawn_blocking(move || restart_group_members(&app, &pty_manager, &id))
+        .await
+        .map_err(|e| format!("Restart failed to run: {}", e))
+}
 
-    // Set and run startup command if provided
-    if let Some(ref cmd) = startup_command {
-        info.startup_command = startup_command.clone();
-        manager.set_startup_command(&id, Some(cmd.clone()))?;
-        // Small delay to let shell initialize, then run the command
-        std::thread::spawn({
-            let pty_manager = pty_manager.inner().clone();
-            let id = id.clone();
-            let cmd = cmd.clone();
-            move || {
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                let manager = pty_manager.lock();
-                let _ = manager.run_command(&id, &cmd);
+fn restart_group_members(app: &AppHandle, pty_manager: &SharedPtyManager, group_id: &str) -> GroupRestartReport {
+    let ids = pty_manager.lock().find_sessions_in_group(group_id);
+    let mut report = GroupRestartReport {
+        restarted: Vec::new(),
+        errors: Vec::new(),
+    };
+    for id in ids {
+        let _ = app.emit("session-restarting", SessionRestarting { id: id.clone() });
+        let result = restart(pty_manager, &id);
+        match result {
+            Ok(info) => {
+                run_startup_commands(pty_manager.clone(), &info);
+                report.restarted.push(info);
             }
-        });
+            Err(error) => report.errors.push(RestoreError {
+                session_id: Some(id),
+                error,
+            }),
+        }
     }
+    report
+}
+
+/// Spawn a session created with `lazy`, e.g. when the frontend first shows it
+#[tauri::command]
+pub fn spawn_pending_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    rows: Option<u16>,
+    cols: Option<u16>,
+) -> Result<SessionInfo, String> {
+    let manager = pty_manager.lock();
+    let info = manager.spawn_pending_session(&id, rows.unwrap_or(24), cols.unwrap_or(80))?;
+    run_startup_commands(pty_manager.inner().clone(), &info);
 
     Ok(info)
 }
 
+/// Respawn the saved sessions under their saved ids, skipping any that are already live.
+///
+/// Sessions that came back with a note (e.g. their directory was gone) count as
+/// restored and are also listed in the report's errors. Emits `restore-complete`
+/// with the report once they're all back.
+#[tauri::command]
+pub async fn restore_sessions(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+) -> Result<RestoreReport, String> {
+    let state = persistence::load_state()?;
+    let report = respawn_sessions(pty_manager.inner().clone(), state.sessions).await?;
+    restore_active_session(&app, state.active_session_id);
+    *group_state.restore_report.lock() = Some(report.clone());
+    let _ = app.emit("restore-complete", report.clone());
+    Ok(report)
+}
+
+/// Pause after starting sessions before starting the ones that depend on them
+const DEPENDENCY_START_DELAY: Duration = Duration::from_secs(1);
+
+/// Most sessions of one restore wave spawned at the same time
+const RESTORE_PARALLELISM: usize = 4;
+
+/// Respawn saved sessions, skipping any that are already live, and type in their startup commands.
+///
+/// Sessions start after the ones they depend on, with `DEPENDENCY_START_DELAY`
+/// between each wave; sessions on a dependency cycle aren't started. Within a
+/// wave up to `RESTORE_PARALLELISM` sessions spawn at once. Runs off the main
+/// thread since it may wait.
+async fn respawn_sessions(pty_manager: SharedPtyManager, sessions: Vec<SessionInfo>) -> Result<RestoreReport, String> {
+    tokio::task::spawn_blocking(move || respawn_in_order(&pty_manager, sessions))
+        .await
+        .map_err(|e| format!("Restore failed to run: {}", e))
+}
+
+fn respawn_in_order(pty_manager: &SharedPtyManager, sessions: Vec<SessionInfo>) -> RestoreReport {
+    let started = Instant::now();
+    let (waves, cycles) = dependency::start_waves(sessions);
+    let mut report = RestoreReport {
+        sessions_restored: 0,
+        errors: cycles
+            .into_iter()
+            .map(|(id, error)| RestoreError {
+                session_id: Some(id),
+                error,
+            })
+            .collect(),
+        elapsed_ms: 0,
+    };
+
+    for (index, wave) in waves.into_iter().enumerate() {
+        if index > 0 {
+            std::thread::sleep(DEPENDENCY_START_DELAY);
+        }
+        respawn_wave(pty_manager, wave, &mut report);
+    }
+    report.elapsed_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+fn respawn_wave(pty_manager: &SharedPtyManager, sessions: Vec<SessionInfo>, report: &mut RestoreReport) {
+    // Locked only to pick the sessions; the spawns themselves go through a spawner
+    let (spawner, sessions) = {
+        let manager = pty_manager.lock();
+        let mut seen = HashSet::new();
+        let sessions: Vec<SessionInfo> = sessions
+            .into_iter()
+            .filter(|info| seen.insert(info.id.clone()) && manager.get_session_info(&info.id).is_none())
+            .collect();
+        (manager.spawner(), sessions)
+    };
+    let results = spawn_in_parallel(&spawner, sessions);
+
+    for (id, result) in results {
+        match result {
+            Ok(info) => {
+                report.sessions_restored += 1;
+                if let SessionStatus::Error(error) = &info.status {
+                    report.errors.push(RestoreError {
+                        session_id: Some(id.clone()),
+                        error: error.clone(),
+                    });
+                }
+                run_startup_commands(pty_manager.clone(), &info);
+            }
+            Err(error) => report.errors.push(RestoreError {
+                session_id: Some(id),
+                error,
+            }),
+        }
+    }
+}
+
+/// Spawn sessions on up to `RESTORE_PARALLELISM` threads, returning each
+/// session's result in the order given.
+///
+/// Only as many as `Settings::max_sessions` leaves room for spawn at once, so
+/// the restore can't overrun the cap by racing its check; the rest are tried
+/// one at a time afterwards and fail with the usual limit error. A session the
+/// user opens while the parallel spawns run isn't counted until they finish.
+fn spawn_in_parallel(
+    manager: &PtyManager,
+    mut sessions: Vec<SessionInfo>,
+) -> Vec<(String, Result<SessionInfo, String>)> {
+    let parallel = manager.free_session_slots().unwrap_or(usize::MAX).min(sessions.len());
+    let rest = sessions.split_off(parallel);
+    let results: Vec<Mutex<Option<Result<SessionInfo, String>>>> = sessions.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..RESTORE_PARALLELISM.min(parallel) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(info) = sessions.get(index) else {
+                    break;
+                };
+                *results[index].lock() = Some(manager.respawn_from_info(info.clone()));
+            });
+        }
+    });
+
+    let spawned = sessions
+        .into_iter()
+        .zip(results)
+        .map(|(info, result)| (info.id, result.into_inner().expect("every session is spawned")));
+    let rest = rest.into_iter().map(|info| (info.id.clone(), manager.respawn_from_info(info)));
+    spawned.chain(rest).collect()
+}
+
+/// Wait before the first startup command when the session doesn't say how long
+const DEFAULT_STARTUP_DELAY_MS: u64 = 500;
+
+/// Pause between consecutive startup commands
+const STARTUP_COMMAND_GAP_MS: u64 = 100;
+
+/// Type a session's startup commands into it, in order, once its shell has had
+/// a moment to initialize
+fn run_startup_commands(pty_manager: SharedPtyManager, info: &SessionInfo) {
+    if info.startup_commands.is_empty() {
+        return;
+    }
+    let id = info.id.clone();
+    let commands = info.startup_commands.clone();
+    let delay = info.startup_delay_ms.unwrap_or(DEFAULT_STARTUP_DELAY_MS);
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(delay));
+        for (index, cmd) in commands.iter().enumerate() {
+            if index > 0 {
+                std::thread::sleep(std::time::Duration::from_millis(STARTUP_COMMAND_GAP_MS));
+            }
+            // Stop once the shell is gone rather than erroring through the rest;
+            // the write itself happens after the manager lock is released
+            let input = pty_manager.lock().session_input(&id);
+            if input.and_then(|input| input.run_command(cmd)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Close a session according to its `close_behavior`; `force` always kills immediately
 #[tauri::command]
 pub fn delete_session(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    force: Option<bool>,
+) -> Result<DeleteOutcome, String> {
+    let info = {
+        let manager = pty_manager.lock();
+        let Some(info) = manager.get_session_info(&id) else {
+            // Already gone; deleting is idempotent
+            return Ok(DeleteOutcome::Deleted);
+        };
+
+        let behavior = if force.unwrap_or(false) {
+            CloseBehavior::Kill
+        } else {
+            info.close_behavior
+        };
+        let logout = match behavior {
+            CloseBehavior::Confirm => return Ok(DeleteOutcome::NeedsConfirmation),
+            CloseBehavior::Logout => manager.session_input(&id).ok(),
+            CloseBehavior::Kill => None,
+        };
+        drop(manager);
+
+        if logout.is_some_and(|input| input.run_command("exit").is_ok()) {
+            spawn_logout_watch(app, pty_manager.inner().clone(), info);
+            return Ok(DeleteOutcome::LoggingOut);
+        }
+        // Kill, or a Logout whose `exit` couldn't be written
+        pty_manager.lock().kill_session(&id)?;
+        info
+    };
+
+    reconcile_active_session(&app, &info);
+    Ok(DeleteOutcome::Deleted)
+}
+
+/// Kill and remove several sessions at once, ignoring their close behavior.
+///
+/// Every id gets a result; unknown ids and failures are reported without
+/// stopping the rest of the batch.
+#[tauri::command]
+pub fn delete_sessions(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    ids: Vec<String>,
+) -> Vec<SessionDeleteResult> {
+    let mut killed = Vec::new();
+    let results = {
+        let manager = pty_manager.lock();
+        ids.into_iter()
+            .map(|id| {
+                let result = manager
+                    .get_session_info(&id)
+                    .ok_or_else(|| format!("Session not found: {}", id))
+                    .and_then(|info| {
+                        manager.kill_session(&id)?;
+                        killed.push(info);
+                        Ok(())
+                    });
+                SessionDeleteResult {
+                    id,
+                    error: result.err(),
+                }
+            })
+            .collect()
+    };
+
+    for info in &killed {
+        reconcile_active_session(&app, info);
+    }
+    results
+}
+
+/// Wait for a shell we sent `exit` to, then remove it (killing it if it hangs)
+fn spawn_logout_watch(app: AppHandle, pty_manager: SharedPtyManager, info: SessionInfo) {
+    std::thread::spawn(move || {
+        let deadline = Instant::now() + LOGOUT_TIMEOUT;
+        while Instant::now() < deadline && !pty_manager.lock().has_child_exited(&info.id) {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let _ = pty_manager.lock().kill_session(&info.id);
+        reconcile_active_session(&app, &info);
+    });
+}
+
+#[tauri::command]
+pub fn set_close_behavior(
     pty_manager: State<'_, SharedPtyManager>,
     id: String,
+    behavior: CloseBehavior,
 ) -> Result<(), String> {
     let manager = pty_manager.lock();
-    manager.kill_session(&id)
+    manager.set_close_behavior(&id, behavior)
 }
 
 #[tauri::command]
@@ -89,8 +679,35 @@ pub fn write_to_session(
     id: String,
     data: Vec<u8>,
 ) -> Result<(), String> {
-    let manager = pty_manager.lock();
-    manager.write_to_session(&id, &data)
+    // Only the lookup holds the manager; the write waits on nothing but this session
+    let input = pty_manager.lock().session_input(&id)?;
+    input.write(&[&data])
+}
+
+/// Write several chunks to a session with a single flush, for large pastes;
+/// `write_to_session` stays the path for keystrokes
+#[tauri::command]
+pub fn write_to_session_bulk(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    chunks: Vec<Vec<u8>>,
+) -> Result<(), String> {
+    let chunks: Vec<&[u8]> = chunks.iter().map(Vec::as_slice).collect();
+    let input = pty_manager.lock().session_input(&id)?;
+    input.write(&chunks)
+}
+
+/// Paste text into a session. While the program has bracketed paste on, the
+/// text is wrapped in paste markers so its newlines don't run anything; otherwise
+/// it's written as-is. Returns whether it was wrapped.
+#[tauri::command]
+pub fn paste_to_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    data: Vec<u8>,
+) -> Result<bool, String> {
+    let input = pty_manager.lock().session_input(&id)?;
+    input.paste(&data)
 }
 
 #[tauri::command]
@@ -105,143 +722,1706 @@ pub fn resize_session(
 }
 
 #[tauri::command]
-pub fn get_session(
+pub fn get_foreground_process(
     pty_manager: State<'_, SharedPtyManager>,
     id: String,
-) -> Option<SessionInfo> {
+) -> Result<Option<ForegroundProcess>, String> {
     let manager = pty_manager.lock();
-    manager.get_session_info(&id)
+    manager.get_foreground_process(&id)
 }
 
+/// Every process running under a session's shell
 #[tauri::command]
-pub fn get_all_sessions(
+pub fn get_session_process_tree(
     pty_manager: State<'_, SharedPtyManager>,
-) -> Vec<SessionInfo> {
+    id: String,
+) -> Result<Vec<ProcessNode>, String> {
     let manager = pty_manager.lock();
-    manager.get_all_sessions()
+    manager.get_session_process_tree(&id)
 }
 
+/// CPU percent and resident memory of a session's process tree, briefly cached
 #[tauri::command]
-pub fn set_session_group(
+pub fn get_session_resource_usage(
     pty_manager: State<'_, SharedPtyManager>,
     id: String,
-    group_id: Option<String>,
-) -> Result<(), String> {
+) -> Result<Option<ResourceUsage>, String> {
     let manager = pty_manager.lock();
-    manager.set_session_group(&id, group_id)
+    manager.get_session_resource_usage(&id)
 }
 
+/// Bytes of output a session has produced, its current output rate and last activity time
 #[tauri::command]
-pub fn set_startup_command(
+pub fn get_session_metrics(
     pty_manager: State<'_, SharedPtyManager>,
     id: String,
-    command: Option<String>,
-) -> Result<(), String> {
+) -> Result<SessionMetrics, String> {
     let manager = pty_manager.lock();
-    manager.set_startup_command(&id, command)
+    manager.get_session_metrics(&id)
 }
 
-// ============ Group Commands ============
+/// `get_session_metrics` for every session, e.g. to spot the one flooding output
+#[tauri::command]
+pub fn get_all_metrics(pty_manager: State<'_, SharedPtyManager>) -> Vec<SessionMetrics> {
+    pty_manager.lock().get_all_metrics()
+}
 
+/// Whether the backend can add integration markers to `shell_path`'s prompts
 #[tauri::command]
-pub fn create_group(
-    group_state: State<'_, GroupState>,
+pub fn shell_supports_integration(shell_path: String) -> bool {
+    process::shell_supports_integration(&shell_path)
+}
+
+/// Ids of sessions whose foreground process is `name` (basename, case-insensitive)
+#[tauri::command]
+pub fn find_sessions_by_process(
+    pty_manager: State<'_, SharedPtyManager>,
     name: String,
-) -> SessionGroup {
-    let id = uuid::Uuid::new_v4().to_string();
-    let order = group_state.groups.lock().len() as i32;
-    let group = SessionGroup {
-        id: id.clone(),
-        name,
-        collapsed: false,
-        order,
-    };
-    group_state.groups.lock().push(group.clone());
-    group
+) -> Vec<String> {
+    pty_manager.lock().find_sessions_by_process(&name)
 }
 
+/// Ids of sessions with no output in the last `idle_ms` milliseconds
 #[tauri::command]
-pub fn delete_group(
-    group_state: State<'_, GroupState>,
-    id: String,
-) -> Result<(), String> {
-    let mut groups = group_state.groups.lock();
-    if let Some(pos) = groups.iter().position(|g| g.id == id) {
-        groups.remove(pos);
-        Ok(())
-    } else {
-        Err(format!("Group not found: {}", id))
-    }
+pub fn get_idle_sessions(
+    pty_manager: State<'_, SharedPtyManager>,
+    idle_ms: u64,
+) -> Vec<String> {
+    pty_manager.lock().get_idle_sessions(idle_ms)
 }
 
+/// Kill every session whose foreground process is `name`; returns the affected ids
 #[tauri::command]
-pub fn rename_group(
-    group_state: State<'_, GroupState>,
-    id: String,
+pub fn kill_sessions_by_process(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
     name: String,
-) -> Result<(), String> {
-    let mut groups = group_state.groups.lock();
-    if let Some(group) = groups.iter_mut().find(|g| g.id == id) {
-        group.name = name;
-        Ok(())
-    } else {
-        Err(format!("Group not found: {}", id))
+) -> Result<Vec<String>, String> {
+    let killed: Vec<SessionInfo> = {
+        let manager = pty_manager.lock();
+        let mut killed = Vec::new();
+        for id in manager.find_sessions_by_process(&name) {
+            if let Some(info) = manager.get_session_info(&id) {
+                manager.kill_session(&id)?;
+                killed.push(info);
+            }
+        }
+        killed
+    };
+
+    for info in &killed {
+        reconcile_active_session(&app, info);
     }
+    Ok(killed.into_iter().map(|info| info.id).collect())
 }
 
+/// Tee a session's raw output to a temp file or `session-tap` events, for live debugging
 #[tauri::command]
-pub fn toggle_group_collapsed(
-    group_state: State<'_, GroupState>,
+pub fn tap_session(
+    pty_manager: State<'_, SharedPtyManager>,
     id: String,
-) -> Result<bool, String> {
-    let mut groups = group_state.groups.lock();
-    if let Some(group) = groups.iter_mut().find(|g| g.id == id) {
-        group.collapsed = !group.collapsed;
-        Ok(group.collapsed)
-    } else {
-        Err(format!("Group not found: {}", id))
-    }
+    target: Option<TapTarget>,
+) -> Result<TapInfo, String> {
+    let manager = pty_manager.lock();
+    manager.tap_session(&id, target.unwrap_or(TapTarget::File))
 }
 
 #[tauri::command]
-pub fn get_all_groups(
-    group_state: State<'_, GroupState>,
-) -> Vec<SessionGroup> {
-    group_state.groups.lock().clone()
+pub fn untap_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    tap_id: String,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.untap_session(&tap_id)
 }
 
-// ============ Active Session Commands ============
-
+/// Append a session's raw output to the file at `path` (a transcript) until stopped
 #[tauri::command]
-pub fn set_active_session(
-    group_state: State<'_, GroupState>,
-    id: Option<String>,
-) {
-    *group_state.active_session_id.lock() = id;
+pub fn start_logging(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    path: PathBuf,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.start_logging(&id, path)
 }
 
 #[tauri::command]
-pub fn get_active_session(
-    group_state: State<'_, GroupState>,
-) -> Option<String> {
-    group_state.active_session_id.lock().clone()
+pub fn stop_logging(pty_manager: State<'_, SharedPtyManager>, id: String) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.stop_logging(&id)
 }
 
-// ============ Persistence Commands ============
-
+/// Move a session's log to `<path>.1` (shifting older ones along) and start a fresh file
 #[tauri::command]
-pub fn save_layout(
-    pty_manager: State<'_, SharedPtyManager>,
-    group_state: State<'_, GroupState>,
-) -> Result<(), String> {
-    let sessions = pty_manager.lock().get_all_sessions();
-    let groups = group_state.groups.lock().clone();
-    let active_id = group_state.active_session_id.lock().clone();
-
-    persistence::save_sessions(&sessions, &groups, active_id)
+pub fn rotate_session_log(pty_manager: State<'_, SharedPtyManager>, id: String) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.rotate_session_log(&id)
+}
+
+/// Start recording a session's output for export as an asciinema cast
+#[tauri::command]
+pub fn start_recording(pty_manager: State<'_, SharedPtyManager>, id: String) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.start_recording(&id)
+}
+
+/// Stop recording a session and save the recording to `path` as an asciinema v2 `.cast` file
+#[tauri::command]
+pub fn stop_recording(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    path: PathBuf,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.stop_recording(&id, &path)
+}
+
+/// Save a session's output as an HTML page at `path`, keeping its colors
+#[tauri::command]
+pub fn export_session_html(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    path: PathBuf,
+    theme: Option<HtmlTheme>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.export_session_html(&id, &path, theme.unwrap_or_default())
+}
+
+/// Serve a session's raw output to clients connecting to a Unix socket at `path`
+#[tauri::command]
+pub fn attach_session_socket(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    path: PathBuf,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.attach_session_socket(&id, path)
+}
+
+#[tauri::command]
+pub fn detach_session_socket(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.detach_session_socket(&id)
+}
+
+/// Reset a session's terminal modes (like typing `reset`) without killing the shell;
+/// returns whether the PTY line discipline was reset too
+#[tauri::command]
+pub fn reset_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<bool, String> {
+    let manager = pty_manager.lock();
+    manager.reset_session(&id)
+}
+
+/// Send a signal (e.g. an interrupt) to the program running in a session without closing it
+#[tauri::command]
+pub fn send_signal(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    signal: Signal,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.signal_session(&id, signal)
+}
+
+/// Send end-of-file to the program reading a session's input
+#[tauri::command]
+pub fn send_eof(pty_manager: State<'_, SharedPtyManager>, id: String) -> Result<(), String> {
+    let input = pty_manager.lock().session_input(&id)?;
+    input.send_eof()
+}
+
+/// Recent raw output of a session, for replaying into a freshly mounted terminal
+#[tauri::command]
+pub fn get_session_scrollback(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<Vec<u8>, String> {
+    let manager = pty_manager.lock();
+    manager.get_session_scrollback(&id)
+}
+
+/// Find sessions whose name or recent output contains `query` (ignoring case).
+/// Runs off the main thread; only the newest part of each scrollback is searched.
+#[tauri::command]
+pub async fn search_sessions(
+    pty_manager: State<'_, SharedPtyManager>,
+    query: String,
+) -> Result<Vec<SearchHit>, String> {
+    let targets = pty_manager.lock().search_targets();
+    tokio::task::spawn_blocking(move || search::search(targets, &query))
+        .await
+        .map_err(|e| format!("Search failed to run: {}", e))
+}
+
+/// Seed the output shown for a session without a running shell, such as a
+/// restored placeholder, e.g. with output saved from its last run
+#[tauri::command]
+pub fn set_session_output(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    data: Vec<u8>,
+) -> Result<(), String> {
+    pty_manager.lock().set_session_output(&id, &data)
+}
+
+/// Longest `read_session` will block waiting for output
+const MAX_READ_WAIT: Duration = Duration::from_secs(30);
+
+/// Output a session produced after `since_offset`, waiting up to `max_wait_ms`
+/// for some if there's none yet; pass the returned offset to the next call
+#[tauri::command]
+pub async fn read_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    since_offset: u64,
+    max_wait_ms: Option<u64>,
+) -> Result<SessionRead, String> {
+    let scrollback = pty_manager.lock().session_scrollback(&id)?;
+    let max_wait = Duration::from_millis(max_wait_ms.unwrap_or(0)).min(MAX_READ_WAIT);
+    tokio::task::spawn_blocking(move || scrollback.read_since(since_offset, max_wait))
+        .await
+        .map_err(|e| format!("Read failed to run: {}", e))
+}
+
+/// The newest `count` lines of a session's output as plain text
+#[tauri::command]
+pub fn get_session_lines(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    count: usize,
+) -> Result<Vec<String>, String> {
+    pty_manager.lock().get_session_lines(&id, count)
+}
+
+/// Find `pattern` (text, or a regex with `regex`) in every session's output,
+/// with each match's line, column and a snippet, grouped by session
+#[tauri::command]
+pub async fn search_all_sessions(
+    pty_manager: State<'_, SharedPtyManager>,
+    pattern: String,
+    regex: Option<bool>,
+) -> Result<Vec<SessionMatches>, String> {
+    let targets = pty_manager.lock().search_targets();
+    tokio::task::spawn_blocking(move || search::search_all(targets, &pattern, regex.unwrap_or(false)))
+        .await
+        .map_err(|e| format!("Search failed to run: {}", e))?
+}
+
+/// Clear a session's screen and the scrollback kept for it
+#[tauri::command]
+pub fn clear_session(pty_manager: State<'_, SharedPtyManager>, id: String) -> Result<(), String> {
+    pty_manager.lock().clear_scrollback(&id)
+}
+
+#[tauri::command]
+pub fn get_session_echo(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<EchoState, String> {
+    let manager = pty_manager.lock();
+    manager.get_session_echo(&id)
+}
+
+#[tauri::command]
+pub fn get_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Option<SessionInfo> {
+    let manager = pty_manager.lock();
+    manager.get_session_info(&id)
+}
+
+#[tauri::command]
+pub fn get_all_sessions(
+    pty_manager: State<'_, SharedPtyManager>,
+) -> Vec<SessionInfo> {
+    let manager = pty_manager.lock();
+    manager.get_all_sessions()
+}
+
+/// Get all sessions sorted by `by`, with id as a stable tie-breaker
+#[tauri::command]
+pub fn get_sessions_sorted(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    by: SortKey,
+    descending: bool,
+) -> Vec<SessionInfo> {
+    let mut sessions = pty_manager.lock().get_all_sessions();
+    let group_orders: HashMap<String, i32> = group_state
+        .groups
+        .lock()
+        .iter()
+        .map(|g| (g.id.clone(), g.order))
+        .collect();
+
+    sessions.sort_by(|a, b| {
+        let primary = match by {
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::CreatedAt => a.created_at.cmp(&b.created_at),
+            SortKey::LastActivity => a.last_activity.cmp(&b.last_activity),
+            SortKey::Group => {
+                let order = |s: &SessionInfo| {
+                    s.group_id
+                        .as_ref()
+                        .and_then(|g| group_orders.get(g).copied())
+                        .unwrap_or(i32::MAX)
+                };
+                order(a).cmp(&order(b)).then_with(|| a.order.cmp(&b.order))
+            }
+            SortKey::Pid => a.pid.unwrap_or(u32::MAX).cmp(&b.pid.unwrap_or(u32::MAX)),
+        };
+        let primary = if descending { primary.reverse() } else { primary };
+        primary.then_with(|| a.id.cmp(&b.id))
+    });
+    sessions
+}
+
+/// Get all sessions in display order: pinned ones first, then by group order
+/// and order within the group
+#[tauri::command]
+pub fn get_all_sessions_sorted(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+) -> Vec<SessionInfo> {
+    let mut sessions = get_sessions_sorted(pty_manager, group_state, SortKey::Group, false);
+    // Stable, so each half keeps the group ordering
+    sessions.sort_by_key(|s| !s.pinned);
+    sessions
+}
+
+/// Pin or unpin a session; returns whether it's now pinned
+#[tauri::command]
+pub fn toggle_session_pinned(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<bool, String> {
+    let manager = pty_manager.lock();
+    manager.toggle_session_pinned(&id)
+}
+
+#[tauri::command]
+pub fn set_session_group(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    group_id: Option<String>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.set_session_group(&id, group_id)
+}
+
+/// Move all of one group's sessions to the end of another, e.g. to merge groups;
+/// returns the moved ids
+#[tauri::command]
+pub fn move_group_sessions(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    from_group_id: String,
+    to_group_id: String,
+) -> Result<Vec<String>, String> {
+    let groups = group_state.groups.lock();
+    for id in [&from_group_id, &to_group_id] {
+        if !groups.iter().any(|g| &g.id == id) {
+            return Err(format!("Group not found: {}", id));
+        }
+    }
+    let manager = pty_manager.lock();
+    Ok(manager.move_group_sessions(&from_group_id, &to_group_id))
+}
+
+/// Move a session to `new_order` within its group; returns the group's sessions in order
+#[tauri::command]
+pub fn reorder_session(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+    new_order: i32,
+) -> Result<Vec<SessionInfo>, String> {
+    let sessions = pty_manager.lock().reorder_session(&id, new_order)?;
+    persist_state(&app, collect_state(&pty_manager, &group_state))?;
+    Ok(sessions)
+}
+
+#[tauri::command]
+pub fn set_startup_command(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    command: Option<String>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.set_startup_commands(&id, command.into_iter().collect(), None)
+}
+
+/// Replace a session's startup commands and the delay before the first one
+#[tauri::command]
+pub fn set_startup_commands(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    commands: Vec<String>,
+    delay_ms: Option<u64>,
+) -> Result<(), String> {
+    pty_manager.lock().set_startup_commands(&id, commands, delay_ms)
+}
+
+/// Limit how fast input is written to a session; `None` removes the limit
+#[tauri::command]
+pub fn set_input_rate_limit(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    bytes_per_sec: Option<u64>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.set_input_rate_limit(&id, bytes_per_sec)
+}
+
+#[tauri::command]
+pub fn get_input_pacing(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<InputPacingStats, String> {
+    let manager = pty_manager.lock();
+    manager.get_input_pacing(&id)
+}
+
+/// Throw away input queued by the rate limiter so it's never replayed into the shell
+#[tauri::command]
+pub fn discard_pending_input(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<usize, String> {
+    let manager = pty_manager.lock();
+    manager.discard_pending_input(&id)
+}
+
+/// Assign (or clear) a session's hotkey label; hotkeys are unique across sessions and groups
+#[tauri::command]
+pub fn set_session_hotkey(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+    hotkey: Option<String>,
+) -> Result<(), String> {
+    let hotkey = normalize_hotkey(hotkey);
+    let groups = group_state.groups.lock();
+    let manager = pty_manager.lock();
+
+    if let Some(ref key) = hotkey {
+        check_hotkey_available(key, &manager.get_all_sessions(), &groups, &id)?;
+    }
+    manager.set_session_hotkey(&id, hotkey)
+}
+
+/// Set a session's hex color; `None` clears it
+#[tauri::command]
+pub fn set_session_color(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    color: Option<String>,
+) -> Result<(), String> {
+    let color = normalize_color(color)?;
+    let manager = pty_manager.lock();
+    manager.set_session_color(&id, color)
+}
+
+#[tauri::command]
+pub fn set_session_tags(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.set_session_tags(&id, normalize_tags(tags))
+}
+
+/// Have restore start `id` after `depends_on` (`None` clears it); fails on a dependency cycle
+#[tauri::command]
+pub fn set_session_dependency(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    depends_on: Option<String>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.set_session_dependency(&id, depends_on)
+}
+
+/// Sessions tagged `tag` (ignoring case)
+#[tauri::command]
+pub fn get_sessions_by_tag(
+    pty_manager: State<'_, SharedPtyManager>,
+    tag: String,
+) -> Vec<SessionInfo> {
+    pty_manager.lock().get_sessions_by_tag(&tag)
+}
+
+/// Get a session's color, giving it one derived from its project directory if it has none
+#[tauri::command]
+pub fn derive_session_color(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<String, String> {
+    let manager = pty_manager.lock();
+    manager.derive_session_color(&id)
+}
+
+/// Override the TERM a session advertises; takes effect when its shell is next spawned
+#[tauri::command]
+pub fn set_session_term(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    term: Option<String>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.set_session_term(&id, term.filter(|t| !t.trim().is_empty()))
+}
+
+/// Get the TERM a session advertises (its override, or the default)
+#[tauri::command]
+pub fn get_session_term(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<String, String> {
+    let info = pty_manager
+        .lock()
+        .get_session_info(&id)
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+    Ok(info.term_override.unwrap_or_else(|| DEFAULT_TERM.to_string()))
+}
+
+#[tauri::command]
+pub fn set_session_scroll(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+    position: Option<serde_json::Value>,
+) -> Result<(), String> {
+    let manager = pty_manager.lock();
+    manager.set_session_scroll(&id, position)
+}
+
+/// Opt in to `terminal-query` events (DSR/DA/window-size requests seen in output)
+#[tauri::command]
+pub fn set_terminal_query_detection(
+    pty_manager: State<'_, SharedPtyManager>,
+    enabled: bool,
+) {
+    pty_manager.lock().set_terminal_query_detection(enabled);
+}
+
+/// Set how long bursts of output are coalesced into one `pty-output` event (0 disables batching)
+#[tauri::command]
+pub fn set_output_flush_interval(
+    pty_manager: State<'_, SharedPtyManager>,
+    ms: u64,
+) {
+    pty_manager.lock().set_output_flush_interval(ms);
+}
+
+/// Cap how much data one `pty-output` event carries; bigger batches are split
+/// into several events. `None` restores the 64 KiB default.
+#[tauri::command]
+pub fn set_max_output_event_size(
+    pty_manager: State<'_, SharedPtyManager>,
+    bytes: Option<usize>,
+) {
+    pty_manager.lock().set_max_output_event_size(bytes);
+}
+
+/// Switch `pty-output` events between number-array and base64 data
+#[tauri::command]
+pub fn set_output_encoding(
+    pty_manager: State<'_, SharedPtyManager>,
+    encoding: OutputEncoding,
+) {
+    pty_manager.lock().set_output_encoding(encoding);
+}
+
+// ============ Group Commands ============
+
+/// Trim a group name and check it is non-empty and unlike (ignoring case) the
+/// name of any group other than `renaming`
+fn validate_group_name(groups: &[SessionGroup], name: &str, renaming: Option<&str>) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Group name cannot be empty".to_string());
+    }
+    let lower = name.to_lowercase();
+    if groups
+        .iter()
+        .any(|g| Some(g.id.as_str()) != renaming && g.name.trim().to_lowercase() == lower)
+    {
+        return Err(format!("A group named \"{}\" already exists", name));
+    }
+    Ok(name.to_string())
+}
+
+#[tauri::command]
+pub fn create_group(
+    group_state: State<'_, GroupState>,
+    name: String,
+) -> Result<SessionGroup, String> {
+    let mut groups = group_state.groups.lock();
+    let name = validate_group_name(&groups, &name, None)?;
+    let group = SessionGroup {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        collapsed: false,
+        order: groups.len() as i32,
+        hotkey: None,
+        default_startup_command: None,
+        layout_hint: None,
+        color: None,
+        tags: Vec::new(),
+    };
+    groups.push(group.clone());
+    Ok(group)
+}
+
+#[tauri::command]
+pub fn delete_group(
+    group_state: State<'_, GroupState>,
+    id: String,
+) -> Result<(), String> {
+    let mut groups = group_state.groups.lock();
+    if let Some(pos) = groups.iter().position(|g| g.id == id) {
+        groups.remove(pos);
+        Ok(())
+    } else {
+        Err(format!("Group not found: {}", id))
+    }
+}
+
+/// Kill a group's sessions, then remove the group; returns the killed session ids
+#[tauri::command]
+pub fn delete_group_with_sessions(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+) -> Result<Vec<String>, String> {
+    if !group_state.groups.lock().iter().any(|g| g.id == id) {
+        return Err(format!("Group not found: {}", id));
+    }
+    let killed = kill_group_members(&app, &pty_manager, &id)?;
+    group_state.groups.lock().retain(|g| g.id != id);
+    Ok(killed)
+}
+
+/// Kill a group's sessions but keep the group; returns the killed session ids
+#[tauri::command]
+pub fn kill_group_sessions(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+) -> Result<Vec<String>, String> {
+    if !group_state.groups.lock().iter().any(|g| g.id == id) {
+        return Err(format!("Group not found: {}", id));
+    }
+    kill_group_members(&app, &pty_manager, &id)
+}
+
+fn kill_group_members(app: &AppHandle, pty_manager: &SharedPtyManager, group_id: &str) -> Result<Vec<String>, String> {
+    let killed: Vec<SessionInfo> = {
+        let manager = pty_manager.lock();
+        let mut killed = Vec::new();
+        for id in manager.find_sessions_in_group(group_id) {
+            if let Some(info) = manager.get_session_info(&id) {
+                manager.kill_session(&id)?;
+                killed.push(info);
+            }
+        }
+        killed
+    };
+
+    for info in &killed {
+        reconcile_active_session(app, info);
+    }
+    Ok(killed.into_iter().map(|info| info.id).collect())
+}
+
+#[tauri::command]
+pub fn rename_group(
+    group_state: State<'_, GroupState>,
+    id: String,
+    name: String,
+) -> Result<(), String> {
+    let mut groups = group_state.groups.lock();
+    let name = validate_group_name(&groups, &name, Some(&id))?;
+    if let Some(group) = groups.iter_mut().find(|g| g.id == id) {
+        group.name = name;
+        Ok(())
+    } else {
+        Err(format!("Group not found: {}", id))
+    }
+}
+
+#[tauri::command]
+pub fn toggle_group_collapsed(
+    group_state: State<'_, GroupState>,
+    id: String,
+) -> Result<bool, String> {
+    let mut groups = group_state.groups.lock();
+    if let Some(group) = groups.iter_mut().find(|g| g.id == id) {
+        group.collapsed = !group.collapsed;
+        Ok(group.collapsed)
+    } else {
+        Err(format!("Group not found: {}", id))
+    }
+}
+
+#[tauri::command]
+pub fn get_all_groups(
+    group_state: State<'_, GroupState>,
+) -> Vec<SessionGroup> {
+    group_state.groups.lock().clone()
+}
+
+/// Assign (or clear) a group's hotkey label; hotkeys are unique across sessions and groups
+#[tauri::command]
+pub fn set_group_hotkey(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+    hotkey: Option<String>,
+) -> Result<(), String> {
+    let hotkey = normalize_hotkey(hotkey);
+    let mut groups = group_state.groups.lock();
+    let manager = pty_manager.lock();
+
+    if let Some(ref key) = hotkey {
+        check_hotkey_available(key, &manager.get_all_sessions(), &groups, &id)?;
+    }
+    let group = groups
+        .iter_mut()
+        .find(|g| g.id == id)
+        .ok_or_else(|| format!("Group not found: {}", id))?;
+    group.hotkey = hotkey;
+    Ok(())
+}
+
+/// Set the startup command applied to new sessions created in this group
+#[tauri::command]
+pub fn set_group_startup_command(
+    group_state: State<'_, GroupState>,
+    id: String,
+    command: Option<String>,
+) -> Result<(), String> {
+    let mut groups = group_state.groups.lock();
+    if let Some(group) = groups.iter_mut().find(|g| g.id == id) {
+        group.default_startup_command = command.filter(|c| !c.trim().is_empty());
+        Ok(())
+    } else {
+        Err(format!("Group not found: {}", id))
+    }
+}
+
+/// Set a group's hex color; `None` clears it
+#[tauri::command]
+pub fn set_group_color(
+    group_state: State<'_, GroupState>,
+    id: String,
+    color: Option<String>,
+) -> Result<(), String> {
+    let color = normalize_color(color)?;
+    let mut groups = group_state.groups.lock();
+    let group = groups
+        .iter_mut()
+        .find(|g| g.id == id)
+        .ok_or_else(|| format!("Group not found: {}", id))?;
+    group.color = color;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_group_tags(
+    group_state: State<'_, GroupState>,
+    id: String,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    let mut groups = group_state.groups.lock();
+    let group = groups
+        .iter_mut()
+        .find(|g| g.id == id)
+        .ok_or_else(|| format!("Group not found: {}", id))?;
+    group.tags = normalize_tags(tags);
+    Ok(())
+}
+
+/// Store the frontend's layout preference for a group; the backend doesn't interpret it
+#[tauri::command]
+pub fn set_group_layout(
+    group_state: State<'_, GroupState>,
+    id: String,
+    hint: Option<String>,
+) -> Result<(), String> {
+    let mut groups = group_state.groups.lock();
+    if let Some(group) = groups.iter_mut().find(|g| g.id == id) {
+        group.layout_hint = hint;
+        Ok(())
+    } else {
+        Err(format!("Group not found: {}", id))
+    }
+}
+
+/// Trim a hotkey label, treating an empty one as "no hotkey"
+fn normalize_hotkey(hotkey: Option<String>) -> Option<String> {
+    hotkey
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+}
+
+/// Validate a hex color, treating an empty one as "no color"
+fn normalize_color(color: Option<String>) -> Result<Option<String>, String> {
+    color
+        .filter(|c| !c.trim().is_empty())
+        .map(|c| color::parse_hex_color(&c))
+        .transpose()
+}
+
+/// Trim tags, dropping empty ones and repeats (ignoring case) while keeping the first spelling
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase()))
+        .collect()
+}
+
+/// Error if `hotkey` is already bound to a session or group other than `owner_id`
+fn check_hotkey_available(
+    hotkey: &str,
+    sessions: &[SessionInfo],
+    groups: &[SessionGroup],
+    owner_id: &str,
+) -> Result<(), String> {
+    let taken = |other: &Option<String>| {
+        other
+            .as_deref()
+            .is_some_and(|h| h.eq_ignore_ascii_case(hotkey))
+    };
+
+    if let Some(session) = sessions.iter().find(|s| s.id != owner_id && taken(&s.hotkey)) {
+        return Err(format!("Hotkey {} is already assigned to session '{}'", hotkey, session.name));
+    }
+    if let Some(group) = groups.iter().find(|g| g.id != owner_id && taken(&g.hotkey)) {
+        return Err(format!("Hotkey {} is already assigned to group '{}'", hotkey, group.name));
+    }
+    Ok(())
+}
+
+/// Move a group to position `new_order`, renumbering the others to stay contiguous
+#[tauri::command]
+pub fn reorder_group(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+    new_order: i32,
+) -> Result<Vec<SessionGroup>, String> {
+    let groups = {
+        let mut groups = group_state.groups.lock();
+        groups.sort_by_key(|g| g.order);
+        let from = groups
+            .iter()
+            .position(|g| g.id == id)
+            .ok_or_else(|| format!("Group not found: {}", id))?;
+        let group = groups.remove(from);
+        let to = usize::try_from(new_order).unwrap_or(0).min(groups.len());
+        groups.insert(to, group);
+        for (index, group) in groups.iter_mut().enumerate() {
+            group.order = index as i32;
+        }
+        groups.clone()
+    };
+
+    persist_state(&app, collect_state(&pty_manager, &group_state))?;
+
+    let _ = app.emit("groups-updated", groups.clone());
+    Ok(groups)
+}
+
+/// Rewrite group orders to a contiguous 0..n sequence, keeping the current sort order
+#[tauri::command]
+pub fn normalize_group_orders(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+) -> Result<Vec<SessionGroup>, String> {
+    let groups = {
+        let mut groups = group_state.groups.lock();
+        // Stable sort so groups sharing an order keep their relative position
+        groups.sort_by_key(|g| g.order);
+        for (index, group) in groups.iter_mut().enumerate() {
+            group.order = index as i32;
+        }
+        groups.clone()
+    };
+
+    persist_state(&app, collect_state(&pty_manager, &group_state))?;
+
+    let _ = app.emit("groups-updated", groups.clone());
+    Ok(groups)
+}
+
+// ============ Profile Commands ============
+
+/// Save a reusable session configuration
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn create_profile(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    name: String,
+    shell: Option<String>,
+    args: Option<Vec<String>>,
+    cwd: Option<PathBuf>,
+    env: Option<HashMap<String, String>>,
+    startup_commands: Option<Vec<String>>,
+) -> Result<Profile, String> {
+    let profile = Profile {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        shell: shell.filter(|s| !s.trim().is_empty()),
+        args,
+        cwd,
+        env: env.unwrap_or_default().into_iter().collect(),
+        startup_commands: startup_commands.unwrap_or_default(),
+    };
+    group_state.profiles.lock().push(profile.clone());
+
+    persist_state(&app, collect_state(&pty_manager, &group_state))?;
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn delete_profile(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    id: String,
+) -> Result<(), String> {
+    {
+        let mut profiles = group_state.profiles.lock();
+        let pos = profiles
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| format!("Profile not found: {}", id))?;
+        profiles.remove(pos);
+    }
+
+    persist_state(&app, collect_state(&pty_manager, &group_state))
+}
+
+#[tauri::command]
+pub fn get_all_profiles(group_state: State<'_, GroupState>) -> Vec<Profile> {
+    group_state.profiles.lock().clone()
+}
+
+/// Per-invocation changes to a profile for `create_session_from_profile`
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct ProfileOverrides {
+    /// Defaults to the profile's name
+    pub name: Option<String>,
+    pub shell: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub cwd: Option<PathBuf>,
+    /// Merged over the profile's variables
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub startup_commands: Option<Vec<String>>,
+    pub group_id: Option<String>,
+}
+
+/// Open a session configured by profile `profile_id`, with `overrides` applied on top
+#[tauri::command]
+pub fn create_session_from_profile(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    profile_id: String,
+    overrides: Option<ProfileOverrides>,
+    rows: Option<u16>,
+    cols: Option<u16>,
+) -> Result<SessionInfo, String> {
+    let profile = group_state
+        .profiles
+        .lock()
+        .iter()
+        .find(|p| p.id == profile_id)
+        .cloned()
+        .ok_or_else(|| format!("Profile not found: {}", profile_id))?;
+    let overrides = overrides.unwrap_or_default();
+
+    let mut info = SessionInfo::new(
+        uuid::Uuid::new_v4().to_string(),
+        overrides.name.unwrap_or_else(|| profile.name.clone()),
+        overrides
+            .shell
+            .or(profile.shell)
+            .unwrap_or_else(PtyManager::default_shell),
+        overrides
+            .cwd
+            .or(profile.cwd)
+            .unwrap_or_else(PtyManager::default_cwd),
+    );
+    info.group_id = overrides.group_id;
+    info.shell_args = overrides.args.or(profile.args);
+    info.env = profile.env;
+    info.env.extend(overrides.env);
+    info.startup_commands = overrides.startup_commands.unwrap_or(profile.startup_commands);
+    info.origin = SessionOrigin::Template(profile.name);
+
+    let manager = pty_manager.lock();
+    info.order = manager.next_session_order(info.group_id.as_deref());
+    let info = manager.spawn_session(info, rows.unwrap_or(24), cols.unwrap_or(80))?;
+    run_startup_commands(pty_manager.inner().clone(), &info);
+
+    Ok(info)
+}
+
+// ============ Active Session Commands ============
+
+/// Move the active session pointer off a session that has exited or been removed.
+///
+/// Depending on `Settings::active_session_fallback`, the pointer is either cleared
+/// or moved to the previous (else next) session in the same group. Emits
+/// `active-session-changed` when the pointer changes.
+pub fn reconcile_active_session(app: &AppHandle, gone: &SessionInfo) {
+    let group_state = app.state::<GroupState>();
+    if group_state.active_session_id.lock().as_deref() != Some(gone.id.as_str()) {
+        return;
+    }
+
+    let (fallback, sessions) = {
+        let manager = app.state::<SharedPtyManager>();
+        let manager = manager.lock();
+        (manager.settings().active_session_fallback, manager.get_all_sessions())
+    };
+
+    let next_id = match fallback {
+        ActiveSessionFallback::Clear => None,
+        ActiveSessionFallback::Neighbor => pick_neighbor(&sessions, gone),
+    };
+
+    {
+        let mut active = group_state.active_session_id.lock();
+        // Someone else may have moved focus while we were looking for a neighbor
+        if active.as_deref() != Some(gone.id.as_str()) {
+            return;
+        }
+        *active = next_id.clone();
+    }
+    let _ = app.emit("active-session-changed", next_id);
+}
+
+/// Re-apply a persisted active session id after restore.
+///
+/// The id is kept only if it names a session that is actually running now;
+/// a stale id falls back to the first session in tab order (or to none).
+/// Always emits `active-session-changed` so the frontend focuses what we chose.
+fn restore_active_session(app: &AppHandle, saved_id: Option<String>) {
+    let group_state = app.state::<GroupState>();
+    let sessions = app.state::<SharedPtyManager>().lock().get_all_sessions();
+    let active_id = restored_active_session(sessions, saved_id);
+
+    *group_state.active_session_id.lock() = active_id.clone();
+    let _ = app.emit("active-session-changed", active_id);
+}
+
+/// The saved active id if it names one of `sessions`, else the first session in tab order
+fn restored_active_session(mut sessions: Vec<SessionInfo>, saved_id: Option<String>) -> Option<String> {
+    sessions.sort_by(|a, b| tab_order(a).cmp(&tab_order(b)));
+    saved_id
+        .filter(|id| sessions.iter().any(|s| &s.id == id))
+        .or_else(|| sessions.first().map(|s| s.id.clone()))
+}
+
+/// Where a session's tab sits: by order, with creation time and id as
+/// tie-breakers, as `PtyManager::find_sessions_in_group` sorts them
+fn tab_order(session: &SessionInfo) -> (i32, i64, &str) {
+    (session.order, session.created_at, session.id.as_str())
+}
+
+/// Find the session closest to `gone` in tab order, preferring its own group
+fn pick_neighbor(sessions: &[SessionInfo], gone: &SessionInfo) -> Option<String> {
+    let mut candidates: Vec<&SessionInfo> = sessions
+        .iter()
+        .filter(|s| s.id != gone.id && s.group_id == gone.group_id)
+        .collect();
+    if candidates.is_empty() {
+        candidates = sessions.iter().filter(|s| s.id != gone.id).collect();
+    }
+    candidates.sort_by(|a, b| tab_order(a).cmp(&tab_order(b)));
+
+    let key = tab_order(gone);
+    let previous = candidates.iter().rev().find(|s| tab_order(s) < key);
+    let next = candidates.iter().find(|s| tab_order(s) > key);
+
+    previous.or(next).map(|s| s.id.clone())
+}
+
+#[tauri::command]
+pub fn set_active_session(
+    group_state: State<'_, GroupState>,
+    id: Option<String>,
+) {
+    *group_state.active_session_id.lock() = id;
+}
+
+#[tauri::command]
+pub fn get_active_session(
+    group_state: State<'_, GroupState>,
+) -> Option<String> {
+    group_state.active_session_id.lock().clone()
+}
+
+#[tauri::command]
+pub fn set_active_session_fallback(
+    pty_manager: State<'_, SharedPtyManager>,
+    fallback: ActiveSessionFallback,
+) {
+    pty_manager.lock().settings_mut().active_session_fallback = fallback;
+}
+
+// ============ Settings Commands ============
+
+#[tauri::command]
+pub fn get_settings(
+    pty_manager: State<'_, SharedPtyManager>,
+) -> Settings {
+    pty_manager.lock().settings().clone()
+}
+
+/// Set directories to add to (or replace) PATH for newly spawned sessions
+#[tauri::command]
+pub fn set_path_override(
+    pty_manager: State<'_, SharedPtyManager>,
+    dirs: Vec<String>,
+    mode: PathMode,
+) {
+    let mut manager = pty_manager.lock();
+    let settings = manager.settings_mut();
+    settings.path_dirs = dirs
+        .into_iter()
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+        .collect();
+    settings.path_mode = mode;
+}
+
+/// Set how many bytes of output each session retains; `None` restores the default
+#[tauri::command]
+pub fn set_scrollback_limit(
+    pty_manager: State<'_, SharedPtyManager>,
+    bytes: Option<usize>,
+) {
+    pty_manager.lock().set_scrollback_limit(bytes);
+}
+
+/// Set how many lines of output each session retains; `None` removes the line cap
+#[tauri::command]
+pub fn set_scrollback_line_limit(
+    pty_manager: State<'_, SharedPtyManager>,
+    lines: Option<usize>,
+) {
+    pty_manager.lock().set_scrollback_line_limit(lines);
+}
+
+/// Rotate session logs once they'd pass `max_bytes` (`None` never rotates),
+/// keeping `max_files` old logs each (`None` restores the default of 5)
+#[tauri::command]
+pub fn set_log_rotation(
+    pty_manager: State<'_, SharedPtyManager>,
+    max_bytes: Option<u64>,
+    max_files: Option<usize>,
+) {
+    pty_manager.lock().set_log_rotation(max_bytes, max_files);
+}
+
+/// Limit how many sessions can be running at once; `None` means unlimited
+#[tauri::command]
+pub fn set_max_sessions(
+    pty_manager: State<'_, SharedPtyManager>,
+    max: Option<usize>,
+) {
+    pty_manager.lock().set_max_sessions(max);
+}
+
+/// Turn the scripting control socket on or off and remember the choice;
+/// returns the socket's path while it's listening
+#[tauri::command]
+pub fn set_control_socket(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    ipc_state: State<'_, IpcState>,
+    enabled: bool,
+) -> Result<Option<PathBuf>, String> {
+    let path = if enabled {
+        Some(ipc_state.start(&app)?)
+    } else {
+        ipc_state.stop();
+        None
+    };
+    pty_manager.lock().settings_mut().control_socket = enabled;
+    Ok(path)
+}
+
+/// Path of the control socket while it's listening
+#[tauri::command]
+pub fn get_control_socket_path(ipc_state: State<'_, IpcState>) -> Option<PathBuf> {
+    ipc_state.path()
+}
+
+// ============ Diagnostics Commands ============
+
+/// Measure how long a sentinel takes to come back through a session's output pipeline
+#[tauri::command]
+pub async fn ping_session(
+    pty_manager: State<'_, SharedPtyManager>,
+    id: String,
+) -> Result<PingResult, String> {
+    let pending = pty_manager.lock().start_ping(&id)?;
+    let pty_manager = pty_manager.inner().clone();
+
+    tokio::task::spawn_blocking(move || {
+        let received = pending.received.recv_timeout(PING_TIMEOUT);
+        // The session may have gone away meanwhile, taking the sink with it
+        let _ = pty_manager.lock().untap_session(&pending.sink_id);
+        let received = received.map_err(|_| format!("Ping timed out for session: {}", id))?;
+        Ok(PingResult {
+            latency_ms: received.duration_since(pending.sent_at).as_secs_f64() * 1000.0,
+        })
+    })
+    .await
+    .map_err(|e| format!("Ping failed to run: {}", e))?
+}
+
+/// Exercise spawn → write → read → resize → kill on a throwaway PTY
+#[tauri::command]
+pub async fn self_test() -> Result<SelfTestReport, String> {
+    tokio::task::spawn_blocking(self_test::run_self_test)
+        .await
+        .map_err(|e| format!("Self-test failed to run: {}", e))
+}
+
+// ============ Persistence Commands ============
+
+#[tauri::command]
+pub fn save_layout(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+) -> Result<(), String> {
+    persist_state(&app, collect_state(&pty_manager, &group_state))
+}
+
+/// Check that the data dir exists and is writable, so the UI can warn before a save fails
+#[tauri::command]
+pub fn check_persistence_health() -> PersistenceHealth {
+    persistence::check_health()
+}
+
+/// Assemble the current state without writing it to disk
+#[tauri::command]
+pub fn snapshot_state(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+) -> AppState {
+    collect_state(&pty_manager, &group_state)
 }
 
 #[tauri::command]
 pub fn load_layout() -> Result<AppState, String> {
     persistence::load_state()
 }
+
+/// Summarize a layout file (counts, referenced shells/dirs, warnings) without applying it
+#[tauri::command]
+pub fn import_layout_preview(path: PathBuf) -> Result<LayoutPreview, String> {
+    persistence::preview_layout(&path)
+}
+
+/// Write the current groups, profiles, settings and session configs to a layout file.
+///
+/// Sessions are written as configs to respawn: stopped, without pids or exit codes.
+#[tauri::command]
+pub fn export_layout(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    path: PathBuf,
+) -> Result<(), String> {
+    let mut state = collect_state(&pty_manager, &group_state);
+    for session in &mut state.sessions {
+        as_config(session);
+    }
+    persistence::export_layout(&state, &path)
+}
+
+/// Load a layout file, migrating it from an older version if needed.
+///
+/// Without `merge`, running sessions are killed and the file's layout replaces
+/// the current one, as with `restore_state_backup`. With `merge`, the file's
+/// groups, profiles and sessions whose ids aren't already present are added,
+/// and the current settings and active session are kept. Either way the
+/// imported sessions are registered stopped, like lazy sessions, and the result
+/// is saved as the current state and returned; `spawn_pending_session` starts them.
+#[tauri::command]
+pub fn import_layout(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    path: PathBuf,
+    merge: bool,
+) -> Result<AppState, String> {
+    let mut imported = persistence::load_layout(&path)?;
+    for session in &mut imported.sessions {
+        as_config(session);
+    }
+    persistence::flag_foreign_sessions(&mut imported);
+
+    let state = if merge {
+        let mut state = collect_state(&pty_manager, &group_state);
+        merge_layout(&mut state, imported);
+        *group_state.groups.lock() = state.groups.clone();
+        *group_state.profiles.lock() = state.profiles.clone();
+        state
+    } else {
+        replace_layout(&pty_manager, &group_state, &imported)?;
+        AppState {
+            hostname: persistence::current_hostname(),
+            ..imported
+        }
+    };
+
+    // Registered before saving, so the next save rebuilds them from the manager
+    {
+        let manager = pty_manager.lock();
+        for session in &state.sessions {
+            if manager.get_session_info(&session.id).is_none() {
+                manager.add_pending_session(session.clone());
+            }
+        }
+    }
+    persist_state(&app, state.clone())?;
+    Ok(state)
+}
+
+/// Strip the parts of a session that only describe a live shell
+fn as_config(session: &mut SessionInfo) {
+    session.status = SessionStatus::Stopped;
+    session.pid = None;
+    session.exit_code = None;
+}
+
+/// Add `imported`'s groups, profiles and sessions unless their ids are taken;
+/// added groups go after the existing ones
+fn merge_layout(state: &mut AppState, imported: AppState) {
+    let next_order = state.groups.iter().map(|g| g.order + 1).max().unwrap_or(0);
+    let mut new_groups: Vec<SessionGroup> = imported
+        .groups
+        .into_iter()
+        .filter(|g| !state.groups.iter().any(|existing| existing.id == g.id))
+        .collect();
+    new_groups.sort_by_key(|g| g.order);
+    for (index, mut group) in new_groups.into_iter().enumerate() {
+        group.order = next_order + index as i32;
+        state.groups.push(group);
+    }
+
+    for profile in imported.profiles {
+        if !state.profiles.iter().any(|existing| existing.id == profile.id) {
+            state.profiles.push(profile);
+        }
+    }
+    for session in imported.sessions {
+        if !state.sessions.iter().any(|existing| existing.id == session.id) {
+            state.sessions.push(session);
+        }
+    }
+}
+
+/// List the automatic backups of previous saves, newest first
+#[tauri::command]
+pub fn list_state_backups() -> Result<Vec<StateBackup>, String> {
+    persistence::list_state_backups()
+}
+
+/// Replace the current layout with a backup.
+///
+/// Running sessions are killed, the backup's groups and settings are loaded and
+/// it is saved as the current state (so the layout it replaced becomes a
+/// backup itself), then its sessions are respawned as `load_workspace` does.
+#[tauri::command]
+pub async fn restore_state_backup(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    name: String,
+) -> Result<RestoreReport, String> {
+    let state = persistence::load_state_backup(&name)?;
+    replace_layout(&pty_manager, &group_state, &state)?;
+    persist_state(&app, state.clone())?;
+
+    let report = respawn_sessions(pty_manager.inner().clone(), state.sessions).await?;
+    restore_active_session(&app, state.active_session_id);
+    Ok(report)
+}
+
+/// Kill every session and take on a state's groups, profiles, settings and active session id
+fn replace_layout(pty_manager: &SharedPtyManager, group_state: &GroupState, state: &AppState) -> Result<(), String> {
+    let mut groups = group_state.groups.lock();
+    let mut profiles = group_state.profiles.lock();
+    let mut manager = pty_manager.lock();
+    let mut active_session_id = group_state.active_session_id.lock();
+
+    for session in manager.get_all_sessions() {
+        manager.kill_session(&session.id)?;
+    }
+    *groups = state.groups.clone();
+    *profiles = state.profiles.clone();
+    *manager.settings_mut() = state.settings.clone();
+    *active_session_id = state.active_session_id.clone();
+    Ok(())
+}
+
+/// List the saved workspaces, by name
+#[tauri::command]
+pub fn list_workspaces() -> Result<Vec<Workspace>, String> {
+    persistence::list_workspaces()
+}
+
+/// Save the current layout as a named workspace and make it the active one
+#[tauri::command]
+pub fn save_workspace(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    name: String,
+) -> Result<(), String> {
+    persistence::save_workspace(&name, &collect_state(&pty_manager, &group_state))
+}
+
+/// Switch to a named workspace.
+///
+/// Running sessions are killed, the workspace's layout replaces the current
+/// one (and is saved as the current state), and its sessions are respawned.
+#[tauri::command]
+pub async fn load_workspace(
+    app: AppHandle,
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+    name: String,
+) -> Result<RestoreReport, String> {
+    let state = persistence::load_workspace(&name)?;
+    replace_layout(&pty_manager, &group_state, &state)?;
+    persist_state(&app, state.clone())?;
+
+    let report = respawn_sessions(pty_manager.inner().clone(), state.sessions).await?;
+    restore_active_session(&app, state.active_session_id);
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn delete_workspace(name: String) -> Result<(), String> {
+    persistence::delete_workspace(&name)
+}
+
+/// Get the host the last saved state was written on, next to this machine's
+#[tauri::command]
+pub fn get_state_host(group_state: State<'_, GroupState>) -> StateHost {
+    let saved_on = group_state
+        .last_saved
+        .lock()
+        .as_ref()
+        .and_then(|saved| saved.state.hostname.clone());
+    let current = persistence::current_hostname();
+    StateHost {
+        same_host: saved_on.is_none() || saved_on == current,
+        saved_on,
+        current,
+    }
+}
+
+/// Outcome of the startup restore, for a frontend that missed `restore-complete`
+#[tauri::command]
+pub fn get_restore_report(group_state: State<'_, GroupState>) -> Option<RestoreReport> {
+    group_state.restore_report.lock().clone()
+}
+
+/// Compare the in-memory state against the last saved state
+#[tauri::command]
+pub fn is_state_dirty(
+    pty_manager: State<'_, SharedPtyManager>,
+    group_state: State<'_, GroupState>,
+) -> StateDirtyReport {
+    let current = collect_state(&pty_manager, &group_state);
+    let last_saved = group_state.last_saved.lock();
+    let Some(saved) = last_saved.as_ref() else {
+        return StateDirtyReport {
+            dirty: true,
+            changes: vec!["Layout has never been saved".to_string()],
+        };
+    };
+
+    if persistence::state_hash(&current) == saved.hash {
+        return StateDirtyReport {
+            dirty: false,
+            changes: Vec::new(),
+        };
+    }
+
+    let mut changes = Vec::new();
+    describe_list_changes(
+        "session",
+        &ids_and_values(&persistence::without_sizes(&saved.state).sessions, |s| &s.id),
+        &ids_and_values(&persistence::without_sizes(&current).sessions, |s| &s.id),
+        &mut changes,
+    );
+    describe_list_changes(
+        "group",
+        &ids_and_values(&saved.state.groups, |g| &g.id),
+        &ids_and_values(&current.groups, |g| &g.id),
+        &mut changes,
+    );
+    if saved.state.active_session_id != current.active_session_id {
+        changes.push("Active session changed".to_string());
+    }
+    if serde_json::to_value(&saved.state.settings).ok() != serde_json::to_value(&current.settings).ok() {
+        changes.push("Settings changed".to_string());
+    }
+
+    StateDirtyReport {
+        dirty: true,
+        changes,
+    }
+}
+
+/// Pair each item's id with its JSON form for comparison
+fn ids_and_values<T: serde::Serialize>(
+    items: &[T],
+    id: impl Fn(&T) -> &String,
+) -> HashMap<String, serde_json::Value> {
+    items
+        .iter()
+        .map(|item| (id(item).clone(), serde_json::to_value(item).unwrap_or_default()))
+        .collect()
+}
+
+/// Summarize added/removed/modified items between two id → value maps
+fn describe_list_changes(
+    kind: &str,
+    saved: &HashMap<String, serde_json::Value>,
+    current: &HashMap<String, serde_json::Value>,
+    changes: &mut Vec<String>,
+) {
+    let added = current.keys().filter(|id| !saved.contains_key(*id)).count();
+    let removed = saved.keys().filter(|id| !current.contains_key(*id)).count();
+    let modified = current
+        .iter()
+        .filter(|(id, value)| saved.get(*id).is_some_and(|v| v != *value))
+        .count();
+
+    for (count, verb) in [(added, "added"), (removed, "removed"), (modified, "changed")] {
+        if count > 0 {
+            let plural = if count == 1 { "" } else { "s" };
+            changes.push(format!("{} {}{} {}", count, kind, plural, verb));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, created_at: i64) -> SessionInfo {
+        let mut info = SessionInfo::new(id.to_string(), id.to_string(), "/bin/sh".to_string(), PathBuf::from("/"));
+        info.created_at = created_at;
+        info
+    }
+
+    #[test]
+    fn restored_active_session_keeps_a_valid_id() {
+        let sessions = vec![session("b", 2), session("a", 1)];
+        assert_eq!(restored_active_session(sessions, Some("b".to_string())), Some("b".to_string()));
+    }
+
+    #[test]
+    fn stale_active_session_falls_back_to_the_first() {
+        let sessions = vec![session("b", 2), session("a", 1), session("c", 1)];
+        assert_eq!(restored_active_session(sessions.clone(), Some("gone".to_string())), Some("a".to_string()));
+        assert_eq!(restored_active_session(sessions, None), Some("a".to_string()));
+        assert_eq!(restored_active_session(Vec::new(), Some("gone".to_string())), None);
+    }
+
+    /// `session` placed at tab `order` in group `g`
+    fn tab(id: &str, created_at: i64, order: i32) -> SessionInfo {
+        let mut info = session(id, created_at);
+        info.group_id = Some("g".to_string());
+        info.order = order;
+        info
+    }
+
+    #[test]
+    fn neighbors_follow_tab_order() {
+        // Created a, b, c, then c was dragged to the front: c, a, b
+        let sessions = vec![tab("a", 1, 1), tab("b", 2, 2), tab("c", 3, 0)];
+        assert_eq!(pick_neighbor(&sessions, &sessions[0]), Some("c".to_string()));
+        assert_eq!(pick_neighbor(&sessions, &sessions[2]), Some("a".to_string()));
+        assert_eq!(pick_neighbor(&sessions, &sessions[1]), Some("a".to_string()));
+        assert_eq!(restored_active_session(sessions, Some("gone".to_string())), Some("c".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parallel_spawn_keeps_order_and_the_session_cap() {
+        let mut manager = PtyManager::new();
+        manager.set_max_sessions(Some(4));
+        let sessions: Vec<SessionInfo> = (0..6).map(|n| session(&format!("s{}", n), n)).collect();
+        // Spawned through a spawner, they land in `manager` itself
+        let results = spawn_in_parallel(&manager.spawner(), sessions);
+
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["s0", "s1", "s2", "s3", "s4", "s5"]);
+        for (id, result) in &results[..4] {
+            assert_eq!(result.as_ref().map(|info| info.id.as_str()), Ok(id.as_str()));
+        }
+        for (_, result) in &results[4..] {
+            assert_eq!(result.as_ref().map(|_| ()), Err(&"Session limit reached (4)".to_string()));
+        }
+        assert_eq!(manager.free_session_slots(), Some(0));
+        manager.shutdown_all();
+    }
+
+    fn group(id: &str, name: &str) -> SessionGroup {
+        SessionGroup::new(id.to_string(), name.to_string())
+    }
+
+    #[test]
+    fn group_names_are_trimmed_and_not_blank() {
+        assert_eq!(validate_group_name(&[], "  Work  ", None), Ok("Work".to_string()));
+        assert_eq!(validate_group_name(&[], " \t ", None), Err("Group name cannot be empty".to_string()));
+        assert_eq!(validate_group_name(&[], "", None), Err("Group name cannot be empty".to_string()));
+    }
+
+    #[test]
+    fn group_names_must_be_unique_ignoring_case() {
+        let groups = [group("a", "Work"), group("b", "Personal")];
+        assert_eq!(
+            validate_group_name(&groups, "work ", None),
+            Err("A group named \"work\" already exists".to_string())
+        );
+        // Renaming onto another group's name collides; keeping its own name doesn't
+        assert_eq!(
+            validate_group_name(&groups, "PERSONAL", Some("a")),
+            Err("A group named \"PERSONAL\" already exists".to_string())
+        );
+        assert_eq!(validate_group_name(&groups, "work", Some("a")), Ok("work".to_string()));
+    }
+}
@@ -0,0 +1,326 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::commands;
+use crate::pty::process::Signal;
+
+/// The control socket, while it's listening
+#[derive(Default)]
+pub struct IpcState {
+    #[cfg(unix)]
+    socket: Mutex<Option<unix::ControlSocket>>,
+    #[cfg(not(unix))]
+    socket: Mutex<Option<()>>,
+}
+
+impl IpcState {
+    /// Start listening on the control socket if it isn't already; returns its path
+    #[cfg(unix)]
+    pub fn start(&self, app: &AppHandle) -> Result<PathBuf, String> {
+        let mut socket = self.socket.lock();
+        if let Some(socket) = socket.as_ref() {
+            return Ok(socket.path().to_path_buf());
+        }
+        let control = unix::ControlSocket::bind(crate::persistence::get_control_socket_path(), app.clone())?;
+        let path = control.path().to_path_buf();
+        *socket = Some(control);
+        Ok(path)
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(&self, _app: &AppHandle) -> Result<PathBuf, String> {
+        Err("The control socket is not supported on this platform".to_string())
+    }
+
+    /// Stop listening and remove the socket file; clients already connected keep their connection
+    pub fn stop(&self) {
+        self.socket.lock().take();
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        #[cfg(unix)]
+        {
+            self.socket.lock().as_ref().map(|socket| socket.path().to_path_buf())
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+}
+
+/// One line sent by a client: `{"id": 1, "command": "write_to_session", "args": {...}}`
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Echoed back in the response so clients can match them up
+    #[serde(default)]
+    id: Option<Value>,
+    command: String,
+    #[serde(default)]
+    args: Value,
+}
+
+/// One line sent back for each request
+#[derive(Debug, Serialize)]
+struct Response {
+    id: Option<Value>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateSessionArgs {
+    name: String,
+    shell: Option<String>,
+    cwd: Option<String>,
+    group_id: Option<String>,
+    rows: Option<u16>,
+    cols: Option<u16>,
+    command: Option<String>,
+    then_shell: Option<bool>,
+    args: Option<Vec<String>>,
+    env: Option<HashMap<String, String>>,
+    startup_commands: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionArgs {
+    id: String,
+}
+
+/// `data` is text here rather than the frontend's byte array
+#[derive(Debug, Deserialize)]
+struct WriteArgs {
+    id: String,
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteArgs {
+    id: String,
+    force: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RenameArgs {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResizeArgs {
+    id: String,
+    rows: u16,
+    cols: u16,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignalArgs {
+    id: String,
+    signal: Signal,
+}
+
+/// Handle one request line and build the response line for it
+fn handle_line(app: &AppHandle, line: &str) -> String {
+    let response = match serde_json::from_str::<Request>(line) {
+        Ok(request) => match dispatch(app, &request.command, request.args) {
+            Ok(result) => Response {
+                id: request.id,
+                ok: true,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Response {
+                id: request.id,
+                ok: false,
+                result: None,
+                error: Some(error),
+            },
+        },
+        Err(e) => Response {
+            id: None,
+            ok: false,
+            result: None,
+            error: Some(format!("Invalid request: {}", e)),
+        },
+    };
+    serde_json::to_string(&response).unwrap_or_default()
+}
+
+/// Run a command by the same name as its Tauri command
+fn dispatch(app: &AppHandle, command: &str, args: Value) -> Result<Value, String> {
+    match command {
+        "get_all_sessions" => to_value(commands::get_all_sessions(app.state())),
+        "create_session" => {
+            let a: CreateSessionArgs = parse_args(args)?;
+            to_value(commands::create_session(
+                app.state(),
+                app.state(),
+                a.name,
+                a.shell,
+                a.cwd,
+                a.group_id,
+                None,
+                a.rows,
+                a.cols,
+                None,
+                None,
+                a.command,
+                a.then_shell,
+                None,
+                None,
+                None,
+                None,
+                None,
+                a.args,
+                a.env,
+                a.startup_commands,
+                None,
+                None,
+            )?)
+        }
+        "write_to_session" => {
+            let a: WriteArgs = parse_args(args)?;
+            to_value(commands::write_to_session(app.state(), a.id, a.data.into_bytes())?)
+        }
+        // Output as (lossy) text, escape sequences included
+        "get_session_scrollback" => {
+            let a: SessionArgs = parse_args(args)?;
+            let output = commands::get_session_scrollback(app.state(), a.id)?;
+            to_value(String::from_utf8_lossy(&output))
+        }
+        "delete_session" => {
+            let a: DeleteArgs = parse_args(args)?;
+            to_value(commands::delete_session(app.clone(), app.state(), a.id, a.force)?)
+        }
+        "rename_session" => {
+            let a: RenameArgs = parse_args(args)?;
+            to_value(commands::rename_session(app.state(), a.id, a.name)?)
+        }
+        "resize_session" => {
+            let a: ResizeArgs = parse_args(args)?;
+            to_value(commands::resize_session(app.state(), a.id, a.rows, a.cols)?)
+        }
+        "send_signal" => {
+            let a: SignalArgs = parse_args(args)?;
+            to_value(commands::send_signal(app.state(), a.id, a.signal)?)
+        }
+        _ => Err(format!("Unknown command: {}", command)),
+    }
+}
+
+fn parse_args<T: serde::de::DeserializeOwned>(args: Value) -> Result<T, String> {
+    // Commands without arguments may leave `args` out entirely
+    let args = if args.is_null() { Value::Object(Default::default()) } else { args };
+    serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use tauri::AppHandle;
+
+    /// A Unix socket accepting line-delimited JSON commands
+    pub struct ControlSocket {
+        path: PathBuf,
+        stopped: Arc<AtomicBool>,
+    }
+
+    impl ControlSocket {
+        /// Listen on `path`, readable and writable by the current user only
+        pub fn bind(path: PathBuf, app: AppHandle) -> Result<Self, String> {
+            // The socket's directory is private before the socket exists, so
+            // there's no moment when another user could connect
+            if let Some(dir) = path.parent() {
+                std::fs::DirBuilder::new()
+                    .recursive(true)
+                    .mode(0o700)
+                    .create(dir)
+                    .map_err(|e| format!("Failed to create socket dir {}: {}", dir.display(), e))?;
+                std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+                    .map_err(|e| format!("Failed to restrict socket dir {}: {}", dir.display(), e))?;
+            }
+
+            // A socket file left by a crashed run can't be bound again; only
+            // replace it if nothing is listening there
+            if path.exists() && UnixStream::connect(&path).is_err() {
+                let _ = std::fs::remove_file(&path);
+            }
+            let listener = UnixListener::bind(&path)
+                .map_err(|e| format!("Failed to bind socket {}: {}", path.display(), e))?;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+                .map_err(|e| format!("Failed to restrict socket {}: {}", path.display(), e))?;
+
+            let stopped = Arc::new(AtomicBool::new(false));
+            thread::spawn({
+                let stopped = stopped.clone();
+                move || accept_clients(listener, app, stopped)
+            });
+            Ok(Self { path, stopped })
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl Drop for ControlSocket {
+        fn drop(&mut self) {
+            self.stopped.store(true, Ordering::Relaxed);
+            // Wake the accept loop so it sees the flag
+            let _ = UnixStream::connect(&self.path);
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn accept_clients(listener: UnixListener, app: AppHandle, stopped: Arc<AtomicBool>) {
+        for stream in listener.incoming() {
+            if stopped.load(Ordering::Relaxed) {
+                break;
+            }
+            match stream {
+                Ok(stream) => {
+                    let app = app.clone();
+                    thread::spawn(move || serve_client(stream, app));
+                }
+                Err(e) => eprintln!("Control socket accept error: {}", e),
+            }
+        }
+    }
+
+    /// Answer each request line with a response line until the client hangs up
+    fn serve_client(stream: UnixStream, app: AppHandle) {
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = super::handle_line(&app, &line);
+            if writeln!(writer, "{}", response).is_err() {
+                return;
+            }
+        }
+    }
+}
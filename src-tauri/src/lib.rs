@@ -1,10 +1,12 @@
 mod commands;
+mod ipc;
 mod persistence;
 mod pty;
 
-use commands::GroupState;
-use pty::create_shared_manager;
-use tauri::Manager;
+use commands::{GroupState, RestoreError, RestoreReport, SavedState};
+use ipc::IpcState;
+use pty::{create_shared_manager, SharedPtyManager};
+use tauri::{Emitter, Manager, RunEvent};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -15,17 +17,50 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .manage(pty_manager.clone())
         .manage(group_state)
+        .manage(IpcState::default())
         .setup(move |app| {
             // Set the app handle on the PTY manager for event emission
             let handle = app.handle().clone();
             pty_manager.lock().set_app_handle(handle);
 
             // Load saved layout on startup
-            if let Ok(state) = persistence::load_state() {
-                let group_state: tauri::State<GroupState> = app.state();
-                *group_state.groups.lock() = state.groups;
-                *group_state.active_session_id.lock() = state.active_session_id;
-                // Note: Sessions will be respawned by frontend if needed
+            let group_state: tauri::State<GroupState> = app.state();
+            let mut report = RestoreReport {
+                sessions_restored: 0,
+                errors: Vec::new(),
+                elapsed_ms: 0,
+            };
+            let mut sessions_to_restore = false;
+            match persistence::load_state() {
+                Ok(state) => {
+                    sessions_to_restore = !state.sessions.is_empty();
+                    *group_state.groups.lock() = state.groups.clone();
+                    *group_state.profiles.lock() = state.profiles.clone();
+                    *pty_manager.lock().settings_mut() = state.settings.clone();
+                    // No session is live yet: `restore_sessions` checks the id and
+                    // tells the frontend once they're respawned
+                    *group_state.active_session_id.lock() = state.active_session_id.clone();
+                    *group_state.last_saved.lock() = Some(SavedState::new(state));
+                }
+                Err(error) => report.errors.push(RestoreError {
+                    session_id: None,
+                    error,
+                }),
+            }
+
+            if pty_manager.lock().settings().control_socket {
+                let ipc_state: tauri::State<IpcState> = app.state();
+                if let Err(e) = ipc_state.start(app.handle()) {
+                    // Not fatal: the app works without scripting
+                    eprintln!("{}", e);
+                }
+            }
+
+            // With sessions to restore, `restore_sessions` reports once they're back;
+            // otherwise the restore is already complete. Kept for late listeners either way.
+            if !sessions_to_restore {
+                *group_state.restore_report.lock() = Some(report.clone());
+                let _ = app.emit("restore-complete", report);
             }
 
             Ok(())
@@ -33,27 +68,135 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             // Session commands
             commands::create_session,
+            commands::duplicate_session,
+            commands::restart_session,
+            commands::spawn_pending_session,
+            commands::restore_sessions,
             commands::delete_session,
+            commands::delete_sessions,
+            commands::set_close_behavior,
             commands::rename_session,
             commands::write_to_session,
+            commands::write_to_session_bulk,
+            commands::paste_to_session,
             commands::resize_session,
+            commands::reset_session,
+            commands::send_signal,
+            commands::send_eof,
+            commands::get_session_scrollback,
+            commands::get_session_lines,
+            commands::read_session,
+            commands::set_session_output,
+            commands::clear_session,
+            commands::search_sessions,
+            commands::search_all_sessions,
+            commands::get_session_echo,
+            commands::tap_session,
+            commands::untap_session,
+            commands::start_logging,
+            commands::stop_logging,
+            commands::rotate_session_log,
+            commands::start_recording,
+            commands::stop_recording,
+            commands::export_session_html,
+            commands::attach_session_socket,
+            commands::detach_session_socket,
+            commands::get_foreground_process,
+            commands::get_session_process_tree,
+            commands::get_session_resource_usage,
+            commands::get_session_metrics,
+            commands::get_all_metrics,
+            commands::shell_supports_integration,
+            commands::find_sessions_by_process,
+            commands::get_idle_sessions,
+            commands::kill_sessions_by_process,
             commands::get_session,
             commands::get_all_sessions,
+            commands::get_sessions_sorted,
+            commands::get_all_sessions_sorted,
+            commands::toggle_session_pinned,
             commands::set_session_group,
+            commands::move_group_sessions,
+            commands::reorder_session,
             commands::set_startup_command,
+            commands::set_startup_commands,
+            commands::set_input_rate_limit,
+            commands::get_input_pacing,
+            commands::discard_pending_input,
+            commands::set_session_hotkey,
+            commands::set_session_color,
+            commands::set_session_tags,
+            commands::set_session_dependency,
+            commands::get_sessions_by_tag,
+            commands::derive_session_color,
+            commands::set_session_term,
+            commands::get_session_term,
+            commands::set_session_scroll,
+            commands::set_terminal_query_detection,
+            commands::set_output_encoding,
+            commands::set_output_flush_interval,
+            commands::set_max_output_event_size,
             // Group commands
             commands::create_group,
             commands::delete_group,
+            commands::delete_group_with_sessions,
+            commands::kill_group_sessions,
+            commands::restart_group,
             commands::rename_group,
             commands::toggle_group_collapsed,
             commands::get_all_groups,
+            commands::set_group_hotkey,
+            commands::set_group_startup_command,
+            commands::set_group_color,
+            commands::set_group_tags,
+            commands::set_group_layout,
+            commands::reorder_group,
+            commands::normalize_group_orders,
             // Active session
             commands::set_active_session,
             commands::get_active_session,
+            commands::set_active_session_fallback,
+            // Settings
+            commands::get_settings,
+            commands::set_path_override,
+            commands::set_scrollback_limit,
+            commands::set_scrollback_line_limit,
+            commands::set_log_rotation,
+            commands::set_max_sessions,
+            commands::set_control_socket,
+            commands::get_control_socket_path,
+            // Diagnostics
+            commands::ping_session,
+            commands::self_test,
             // Persistence
             commands::save_layout,
+            commands::check_persistence_health,
+            commands::snapshot_state,
             commands::load_layout,
+            commands::import_layout_preview,
+            commands::export_layout,
+            commands::import_layout,
+            commands::list_workspaces,
+            commands::save_workspace,
+            commands::load_workspace,
+            commands::delete_workspace,
+            commands::list_state_backups,
+            commands::restore_state_backup,
+            commands::is_state_dirty,
+            commands::get_restore_report,
+            commands::get_state_host,
+            commands::create_profile,
+            commands::delete_profile,
+            commands::get_all_profiles,
+            commands::create_session_from_profile,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running ShellTree");
+        .build(tauri::generate_context!())
+        .expect("error while building ShellTree")
+        .run(|app, event| {
+            // Don't leave shells running behind a closed app
+            if let RunEvent::Exit = event {
+                app.state::<IpcState>().stop();
+                app.state::<SharedPtyManager>().lock().shutdown_all();
+            }
+        });
 }
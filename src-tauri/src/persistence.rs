@@ -1,6 +1,78 @@
-use crate::pty::{AppState, SessionGroup, SessionInfo};
+use crate::pty::{AppState, STATE_VERSION};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Data directory name used before the app was renamed to ShellTree
+const LEGACY_DATA_DIR_NAME: &str = "TerminalManager";
+
+/// Number of previous state files kept in the backups directory
+const MAX_STATE_BACKUPS: usize = 5;
+
+/// Timestamp format embedded in backup file names; sorts chronologically
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// A shell or directory a layout refers to, and whether it exists here
+#[derive(Debug, Clone, Serialize)]
+pub struct PathCheck {
+    pub path: String,
+    pub exists: bool,
+}
+
+/// What importing a layout file would bring in, computed without applying it
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutPreview {
+    pub session_count: usize,
+    pub group_count: usize,
+    /// Distinct shells referenced by the layout's sessions
+    pub shells: Vec<PathCheck>,
+    /// Distinct working directories referenced by the layout's sessions
+    pub directories: Vec<PathCheck>,
+    /// Host the layout was saved on, if recorded
+    pub hostname: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Whether the state can be saved, from `check_persistence_health`
+#[derive(Debug, Clone, Serialize)]
+pub struct PersistenceHealth {
+    pub data_dir: PathBuf,
+    pub exists: bool,
+    /// A probe file could be written to and removed from the data dir
+    pub writable: bool,
+    /// Why the data dir is missing or unwritable
+    pub error: Option<String>,
+}
+
+/// A previous state file kept as a backup
+#[derive(Debug, Clone, Serialize)]
+pub struct StateBackup {
+    /// File name, used to restore it
+    pub name: String,
+    /// When this state was replaced, as a Unix timestamp
+    pub created_at: i64,
+    pub size: u64,
+}
+
+/// A named layout saved under the workspaces directory
+#[derive(Debug, Clone, Serialize)]
+pub struct Workspace {
+    pub name: String,
+    /// When it was last saved, as a Unix timestamp
+    pub saved_at: i64,
+    /// Whether it's the workspace last saved or loaded
+    pub active: bool,
+}
+
+/// Contents of the workspaces directory's index file
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct WorkspaceIndex {
+    active: Option<String>,
+}
 
 /// Get the app data directory
 fn get_app_data_dir() -> PathBuf {
@@ -8,11 +80,37 @@ fn get_app_data_dir() -> PathBuf {
     base.join("ShellTree")
 }
 
+/// Get the pre-rename app data directory
+fn get_legacy_data_dir() -> PathBuf {
+    let base = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    base.join(LEGACY_DATA_DIR_NAME)
+}
+
 /// Get the state file path
 fn get_state_file_path() -> PathBuf {
     get_app_data_dir().join("state.json")
 }
 
+/// Get the directory holding state backups
+fn get_backup_dir() -> PathBuf {
+    get_app_data_dir().join("backups")
+}
+
+/// Get the directory holding named workspaces
+fn get_workspace_dir() -> PathBuf {
+    get_app_data_dir().join("workspaces")
+}
+
+/// Get the file recording the active workspace
+fn get_workspace_index_path() -> PathBuf {
+    get_workspace_dir().join("index.json")
+}
+
+/// Get the path the control socket listens on, in a directory of its own
+pub fn get_control_socket_path() -> PathBuf {
+    get_app_data_dir().join("ipc").join("control.sock")
+}
+
 /// Ensure the app data directory exists
 fn ensure_data_dir() -> Result<(), String> {
     let dir = get_app_data_dir();
@@ -22,8 +120,59 @@ fn ensure_data_dir() -> Result<(), String> {
     Ok(())
 }
 
+/// Check the data dir exists (creating it if needed) and a file can be written there
+pub fn check_health() -> PersistenceHealth {
+    let data_dir = get_app_data_dir();
+    if let Err(error) = ensure_data_dir() {
+        return PersistenceHealth {
+            exists: data_dir.is_dir(),
+            data_dir,
+            writable: false,
+            error: Some(error),
+        };
+    }
+
+    let probe = data_dir.join(".write-probe");
+    let result = fs::write(&probe, b"ok")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| format!("Data dir is not writable: {}", e));
+    PersistenceHealth {
+        exists: true,
+        writable: result.is_ok(),
+        error: result.err(),
+        data_dir,
+    }
+}
+
+/// Copy state saved under the legacy data directory into the current one.
+///
+/// Only runs when there is no current state file, so once the copy lands it
+/// never runs again. The legacy file is left in place.
+fn migrate_legacy_data_dir() -> Result<(), String> {
+    let path = get_state_file_path();
+    let legacy_path = get_legacy_data_dir().join("state.json");
+    if path.exists() || !legacy_path.exists() {
+        return Ok(());
+    }
+
+    ensure_data_dir()?;
+    fs::copy(&legacy_path, &path)
+        .map_err(|e| format!("Failed to migrate legacy state file: {}", e))?;
+    eprintln!(
+        "Migrated state from {} to {}",
+        legacy_path.display(),
+        path.display()
+    );
+    Ok(())
+}
+
 /// Load the application state from disk
 pub fn load_state() -> Result<AppState, String> {
+    if let Err(e) = migrate_legacy_data_dir() {
+        // Not fatal: start with whatever the current directory holds
+        eprintln!("{}", e);
+    }
+
     let path = get_state_file_path();
     if !path.exists() {
         return Ok(AppState::default());
@@ -32,8 +181,125 @@ pub fn load_state() -> Result<AppState, String> {
     let content = fs::read_to_string(&path)
         .map_err(|e| format!("Failed to read state file: {}", e))?;
 
-    serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse state file: {}", e))
+    let mut state = parse_state(&content)
+        .map_err(|e| format!("Failed to parse state file: {}", e))?;
+    flag_foreign_sessions(&mut state);
+    Ok(state)
+}
+
+/// A state synced from another machine may point at directories or shells
+/// that don't exist here; flag those instead of letting restore trip on them
+pub fn flag_foreign_sessions(state: &mut AppState) {
+    if state.hostname.is_none() || state.hostname == current_hostname() {
+        return;
+    }
+    for session in &mut state.sessions {
+        let shell = Path::new(&session.shell);
+        let shell_missing = shell.is_absolute() && !shell.exists();
+        session.needs_review = shell_missing || !session.cwd.is_dir();
+    }
+}
+
+/// Parse a state file of any known version
+fn parse_state(content: &str) -> Result<AppState, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    migrate_state(value)
+}
+
+/// Upgrade a state written by an older version of the app to the current shape.
+///
+/// Each step takes the JSON from one version to the next, so a file can be
+/// several versions behind. A file without a version predates versioning and is
+/// version 0. Files from a newer app are rejected rather than guessed at.
+pub fn migrate_state(mut value: Value) -> Result<AppState, String> {
+    let version = match value.get("version") {
+        None | Some(Value::Null) => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| format!("Invalid state version: {}", v))?,
+    };
+    if version > STATE_VERSION {
+        return Err(format!(
+            "State version {} is newer than this app supports ({})",
+            version, STATE_VERSION
+        ));
+    }
+    let Some(object) = value.as_object_mut() else {
+        return Err("State is not a JSON object".to_string());
+    };
+
+    if version < 1 {
+        migrate_v0_to_v1(object);
+    }
+    if version < 2 {
+        migrate_v1_to_v2(object);
+    }
+
+    object.insert("version".to_string(), Value::from(STATE_VERSION));
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+/// Version 0 files came from several releases and may lack fields that later
+/// became required; fill them so the state still parses
+fn migrate_v0_to_v1(state: &mut serde_json::Map<String, Value>) {
+    for key in ["sessions", "groups"] {
+        if !state.get(key).is_some_and(Value::is_array) {
+            state.insert(key.to_string(), Value::Array(Vec::new()));
+        }
+    }
+
+    if let Some(Value::Array(groups)) = state.get_mut("groups") {
+        for (index, group) in groups.iter_mut().enumerate() {
+            if let Some(group) = group.as_object_mut() {
+                group.entry("collapsed").or_insert(Value::Bool(false));
+                group.entry("order").or_insert(Value::from(index));
+            }
+        }
+    }
+
+    if let Some(Value::Array(sessions)) = state.get_mut("sessions") {
+        for session in sessions.iter_mut() {
+            if let Some(session) = session.as_object_mut() {
+                session
+                    .entry("status")
+                    .or_insert(serde_json::json!({ "type": "Stopped" }));
+                session.entry("created_at").or_insert(Value::from(0));
+            }
+        }
+    }
+}
+
+/// Version 2 replaced each session's single `startup_command` with a `startup_commands` list
+fn migrate_v1_to_v2(state: &mut serde_json::Map<String, Value>) {
+    if let Some(Value::Array(sessions)) = state.get_mut("sessions") {
+        for session in sessions.iter_mut() {
+            if let Some(session) = session.as_object_mut() {
+                let commands = match session.remove("startup_command") {
+                    Some(Value::String(command)) => vec![Value::String(command)],
+                    _ => Vec::new(),
+                };
+                session
+                    .entry("startup_commands")
+                    .or_insert(Value::Array(commands));
+            }
+        }
+    }
+}
+
+/// This machine's hostname, if it can be determined
+pub fn current_hostname() -> Option<String> {
+    #[cfg(unix)]
+    {
+        nix::unistd::gethostname()
+            .ok()
+            .and_then(|name| name.into_string().ok())
+            .filter(|name| !name.is_empty())
+    }
+    #[cfg(not(unix))]
+    {
+        std::env::var("COMPUTERNAME").ok()
+    }
 }
 
 /// Save the application state to disk
@@ -44,16 +310,313 @@ pub fn save_state(state: &AppState) -> Result<(), String> {
     let content = serde_json::to_string_pretty(state)
         .map_err(|e| format!("Failed to serialize state: {}", e))?;
 
-    fs::write(&path, content)
+    // Keep the state being replaced, unless this save changes nothing but pane sizes
+    if let Ok(previous) = fs::read_to_string(&path) {
+        let unchanged = serde_json::from_str::<AppState>(&previous)
+            .is_ok_and(|previous| state_hash(&previous) == state_hash(state));
+        if !unchanged {
+            if let Err(e) = backup_state(&previous) {
+                // Not fatal: losing a backup is better than losing the save
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    // Write alongside and rename so a crash never leaves a truncated state file
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write state file: {}", e))?;
+    fs::rename(&tmp_path, &path)
         .map_err(|e| format!("Failed to write state file: {}", e))
 }
 
-/// Save just the session info list (for quick updates)
-pub fn save_sessions(sessions: &[SessionInfo], groups: &[SessionGroup], active_id: Option<String>) -> Result<(), String> {
-    let state = AppState {
-        sessions: sessions.to_vec(),
-        groups: groups.to_vec(),
-        active_session_id: active_id,
-    };
-    save_state(&state)
+/// Store a copy of a state file's contents and prune the oldest backups
+fn backup_state(content: &str) -> Result<(), String> {
+    let dir = get_backup_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backup dir: {}", e))?;
+
+    let name = format!("state-{}.json", chrono::Utc::now().format(BACKUP_TIMESTAMP_FORMAT));
+    fs::write(dir.join(&name), content)
+        .map_err(|e| format!("Failed to write state backup {}: {}", name, e))?;
+
+    for old in list_state_backups()?.iter().skip(MAX_STATE_BACKUPS) {
+        let _ = fs::remove_file(dir.join(&old.name));
+    }
+    Ok(())
+}
+
+/// Parse the timestamp out of a backup file name; `None` if it isn't one
+fn backup_timestamp(name: &str) -> Option<i64> {
+    let stamp = name.strip_prefix("state-")?.strip_suffix(".json")?;
+    chrono::NaiveDateTime::parse_from_str(stamp, BACKUP_TIMESTAMP_FORMAT)
+        .ok()
+        .map(|time| time.and_utc().timestamp())
+}
+
+/// List state backups, newest first
+pub fn list_state_backups() -> Result<Vec<StateBackup>, String> {
+    let dir = get_backup_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read backup dir: {}", e))?;
+    let mut backups: Vec<StateBackup> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let created_at = backup_timestamp(&name)?;
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            Some(StateBackup {
+                name,
+                created_at,
+                size,
+            })
+        })
+        .collect();
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+/// Load the state held in a backup
+pub fn load_state_backup(name: &str) -> Result<AppState, String> {
+    // Only names we generated; this also keeps the path inside the backup dir
+    if backup_timestamp(name).is_none() {
+        return Err(format!("Backup not found: {}", name));
+    }
+    let path = get_backup_dir().join(name);
+    if !path.exists() {
+        return Err(format!("Backup not found: {}", name));
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read backup {}: {}", name, e))?;
+
+    parse_state(&content)
+        .map_err(|e| format!("Failed to parse backup {}: {}", name, e))
+}
+
+/// Path of a workspace's file; the name must be usable as a file name as-is
+fn workspace_path(name: &str) -> Result<PathBuf, String> {
+    let valid = !name.trim().is_empty()
+        && name.trim() == name
+        && !name.starts_with('.')
+        && name != "index"
+        && !name.contains(['/', '\\', ':'])
+        && !name.chars().any(char::is_control);
+    if !valid {
+        return Err(format!("Invalid workspace name: {}", name));
+    }
+    Ok(get_workspace_dir().join(format!("{}.json", name)))
+}
+
+fn read_workspace_index() -> WorkspaceIndex {
+    fs::read_to_string(get_workspace_index_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_workspace_index(index: &WorkspaceIndex) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(index)
+        .map_err(|e| format!("Failed to serialize workspace index: {}", e))?;
+    fs::write(get_workspace_index_path(), content)
+        .map_err(|e| format!("Failed to write workspace index: {}", e))
+}
+
+/// Name of the workspace last saved or loaded, if it still exists
+pub fn active_workspace() -> Option<String> {
+    read_workspace_index()
+        .active
+        .filter(|name| workspace_path(name).is_ok_and(|path| path.exists()))
+}
+
+/// List saved workspaces by name
+pub fn list_workspaces() -> Result<Vec<Workspace>, String> {
+    let dir = get_workspace_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let active = active_workspace();
+    let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read workspaces dir: {}", e))?;
+    let mut workspaces: Vec<Workspace> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let name = file_name.strip_suffix(".json")?.to_string();
+            workspace_path(&name).ok()?;
+            let saved_at = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(|time| chrono::DateTime::<chrono::Utc>::from(time).timestamp())
+                .unwrap_or(0);
+            Some(Workspace {
+                active: active.as_deref() == Some(name.as_str()),
+                name,
+                saved_at,
+            })
+        })
+        .collect();
+    workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(workspaces)
+}
+
+/// Save a state as a named workspace, replacing one of the same name, and make it active
+pub fn save_workspace(name: &str, state: &AppState) -> Result<(), String> {
+    let path = workspace_path(name)?;
+    fs::create_dir_all(get_workspace_dir())
+        .map_err(|e| format!("Failed to create workspaces dir: {}", e))?;
+
+    let content = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize workspace: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write workspace {}: {}", name, e))?;
+    fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to write workspace {}: {}", name, e))?;
+
+    write_workspace_index(&WorkspaceIndex {
+        active: Some(name.to_string()),
+    })
+}
+
+/// Load a named workspace's state and make it active
+pub fn load_workspace(name: &str) -> Result<AppState, String> {
+    let path = workspace_path(name)?;
+    if !path.exists() {
+        return Err(format!("Workspace not found: {}", name));
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read workspace {}: {}", name, e))?;
+    let mut state = parse_state(&content)
+        .map_err(|e| format!("Failed to parse workspace {}: {}", name, e))?;
+    flag_foreign_sessions(&mut state);
+
+    write_workspace_index(&WorkspaceIndex {
+        active: Some(name.to_string()),
+    })?;
+    Ok(state)
+}
+
+/// Delete a named workspace; deleting the active one leaves none active
+pub fn delete_workspace(name: &str) -> Result<(), String> {
+    let path = workspace_path(name)?;
+    if !path.exists() {
+        return Err(format!("Workspace not found: {}", name));
+    }
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete workspace {}: {}", name, e))?;
+
+    if read_workspace_index().active.as_deref() == Some(name) {
+        write_workspace_index(&WorkspaceIndex::default())?;
+    }
+    Ok(())
+}
+
+/// Write a state to a layout file anywhere, for moving it to another machine
+pub fn export_layout(state: &AppState, path: &Path) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize layout: {}", e))?;
+    fs::write(path, content)
+        .map_err(|e| format!("Failed to write layout {}: {}", path.display(), e))
+}
+
+/// Read a layout file of any known version, migrating it to the current one
+pub fn load_layout(path: &Path) -> Result<AppState, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read layout {}: {}", path.display(), e))?;
+    parse_state(&content)
+        .map_err(|e| format!("Failed to parse layout {}: {}", path.display(), e))
+}
+
+/// Parse a layout file and summarize it without touching the current state
+pub fn preview_layout(path: &Path) -> Result<LayoutPreview, String> {
+    let state = load_layout(path)?;
+
+    let mut warnings = Vec::new();
+    let mut shells: Vec<PathCheck> = Vec::new();
+    let mut directories: Vec<PathCheck> = Vec::new();
+    let mut session_ids = HashSet::new();
+    let group_ids: HashSet<&str> = state.groups.iter().map(|g| g.id.as_str()).collect();
+
+    if group_ids.len() != state.groups.len() {
+        warnings.push("Layout contains duplicate group ids".to_string());
+    }
+
+    for session in &state.sessions {
+        if !session_ids.insert(session.id.as_str()) {
+            warnings.push(format!("Duplicate session id: {}", session.id));
+        }
+        if let Some(group_id) = &session.group_id {
+            if !group_ids.contains(group_id.as_str()) {
+                warnings.push(format!("Session '{}' refers to a missing group", session.name));
+            }
+        }
+
+        if !shells.iter().any(|c| c.path == session.shell) {
+            // Bare names are looked up on PATH at spawn time, so only check real paths
+            let shell = Path::new(&session.shell);
+            let exists = !shell.is_absolute() || shell.exists();
+            if !exists {
+                warnings.push(format!("Shell not found on this machine: {}", session.shell));
+            }
+            shells.push(PathCheck {
+                path: session.shell.clone(),
+                exists,
+            });
+        }
+
+        let cwd = session.cwd.to_string_lossy().into_owned();
+        if !directories.iter().any(|c| c.path == cwd) {
+            let exists = session.cwd.is_dir();
+            if !exists {
+                warnings.push(format!("Directory not found on this machine: {}", cwd));
+            }
+            directories.push(PathCheck { path: cwd, exists });
+        }
+    }
+
+    if let Some(active) = &state.active_session_id {
+        if !session_ids.contains(active.as_str()) {
+            warnings.push("Active session id doesn't match any session".to_string());
+        }
+    }
+    if state.hostname.is_some() && state.hostname != current_hostname() {
+        warnings.push(format!(
+            "Layout was saved on another machine ({})",
+            state.hostname.as_deref().unwrap_or_default()
+        ));
+    }
+
+    Ok(LayoutPreview {
+        session_count: state.sessions.len(),
+        group_count: state.groups.len(),
+        shells,
+        directories,
+        hostname: state.hostname,
+        warnings,
+    })
+}
+
+/// Hash a state's serialized form, for cheap unsaved-change checks.
+///
+/// Pane sizes are saved so restored sessions open at the right size, but
+/// resizing a window isn't a layout change, so they're left out here.
+pub fn state_hash(state: &AppState) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(&without_sizes(state))
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A copy of `state` with every session's size cleared, for comparing layouts
+pub fn without_sizes(state: &AppState) -> AppState {
+    let mut state = state.clone();
+    for session in &mut state.sessions {
+        session.rows = 0;
+        session.cols = 0;
+    }
+    state
 }
@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+/// Bells closer together than this are reported once
+pub const BELL_DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    /// Inside an OSC/DCS/APC/PM string, where BEL is a terminator rather than a bell
+    String,
+    StringEscape,
+}
+
+/// Finds bells in PTY output, ignoring the BELs that terminate OSC sequences
+#[derive(Default)]
+pub struct BellDetector {
+    state: State,
+    last_bell: Option<Instant>,
+}
+
+impl BellDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan a chunk of output; true if it rang a bell that isn't debounced away
+    pub fn scan(&mut self, data: &[u8]) -> bool {
+        let mut rang = false;
+        for &byte in data {
+            self.state = match (self.state, byte) {
+                (State::Ground, 0x07) => {
+                    rang = true;
+                    State::Ground
+                }
+                (State::Ground, 0x1b) => State::Escape,
+                (State::Ground, _) => State::Ground,
+                (State::Escape, b']' | b'P' | b'_' | b'^') => State::String,
+                (State::Escape, 0x1b) => State::Escape,
+                (State::Escape, _) => State::Ground,
+                (State::String, 0x07) => State::Ground,
+                (State::String, 0x1b) => State::StringEscape,
+                (State::String, _) => State::String,
+                (State::StringEscape, b'\\') => State::Ground,
+                (State::StringEscape, 0x1b) => State::StringEscape,
+                (State::StringEscape, _) => State::String,
+            };
+        }
+
+        if !rang || self.last_bell.is_some_and(|at| at.elapsed() < BELL_DEBOUNCE) {
+            return false;
+        }
+        self.last_bell = Some(Instant::now());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_bel_rings() {
+        assert!(BellDetector::new().scan(b"done\x07"));
+        assert!(!BellDetector::new().scan(b"no bell here"));
+    }
+
+    #[test]
+    fn string_terminators_are_not_bells() {
+        let mut detector = BellDetector::new();
+        assert!(!detector.scan(b"\x1b]0;title\x07"));
+        assert!(!detector.scan(b"\x1bPdata\x07\x1b_apc\x07\x1b^pm\x07"));
+        // A BEL after an ST-terminated string is a real bell
+        assert!(detector.scan(b"\x1b]2;title\x1b\\\x07"));
+    }
+
+    #[test]
+    fn follows_strings_split_across_reads() {
+        let mut detector = BellDetector::new();
+        assert!(!detector.scan(b"\x1b"));
+        assert!(!detector.scan(b"]7;file:///tmp"));
+        assert!(!detector.scan(b"\x07"));
+        assert!(detector.scan(b"\x07"));
+    }
+
+    #[test]
+    fn bells_are_debounced() {
+        let mut detector = BellDetector::new();
+        assert!(detector.scan(b"\x07"));
+        assert!(!detector.scan(b"\x07"));
+        detector.last_bell = Some(Instant::now() - BELL_DEBOUNCE);
+        assert!(detector.scan(b"\x07"));
+    }
+}
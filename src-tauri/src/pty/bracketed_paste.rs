@@ -0,0 +1,130 @@
+/// Sent before pasted text while bracketed paste is on
+pub const PASTE_START: &[u8] = b"\x1b[200~";
+
+/// Sent after pasted text while bracketed paste is on
+pub const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// Longest partial escape sequence kept between reads
+const MAX_CARRY: usize = 32;
+
+/// Follows DECSET/DECRST 2004 (`CSI ? 2004 h` / `CSI ? 2004 l`) in PTY output,
+/// handling sequences split across reads
+#[derive(Default)]
+pub struct PasteModeScanner {
+    carry: Vec<u8>,
+}
+
+impl PasteModeScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan a chunk of output; returns the mode the last 2004 set/reset in it left behind
+    pub fn scan(&mut self, data: &[u8]) -> Option<bool> {
+        let mut buf = std::mem::take(&mut self.carry);
+        buf.extend_from_slice(data);
+
+        let mut mode = None;
+        let mut i = 0;
+        while i < buf.len() {
+            if buf[i] != 0x1b {
+                i += 1;
+                continue;
+            }
+            if i + 1 >= buf.len() {
+                self.hold(&buf[i..]);
+                break;
+            }
+            if buf[i + 1] != b'[' {
+                i += 1;
+                continue;
+            }
+
+            let mut j = i + 2;
+            while j < buf.len() && (0x20..=0x3f).contains(&buf[j]) {
+                j += 1;
+            }
+            if j >= buf.len() {
+                self.hold(&buf[i..]);
+                break;
+            }
+            // Several private modes can be set at once (`CSI ? 1 ; 2004 h`)
+            if let Some(params) = buf[i + 2..j].strip_prefix(b"?") {
+                if matches!(buf[j], b'h' | b'l') && params.split(|&b| b == b';').any(|p| p == b"2004") {
+                    mode = Some(buf[j] == b'h');
+                }
+            }
+            i = j + 1;
+        }
+        mode
+    }
+
+    fn hold(&mut self, partial: &[u8]) {
+        if partial.len() <= MAX_CARRY {
+            self.carry.extend_from_slice(partial);
+        }
+    }
+}
+
+/// Remove end-of-paste markers from pasted text so it can't end the paste early.
+///
+/// Removing one marker can join the bytes around it into another
+/// (`ESC[20` + marker + `1~`), so this repeats until none are left.
+pub fn strip_paste_end(data: &[u8]) -> Vec<u8> {
+    let mut out = data.to_vec();
+    while let Some(start) = out.windows(PASTE_END.len()).position(|w| w == PASTE_END) {
+        out.drain(start..start + PASTE_END.len());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_plain_marker() {
+        assert_eq!(strip_paste_end(b"a\x1b[201~b"), b"ab");
+    }
+
+    #[test]
+    fn strips_nested_markers() {
+        assert_eq!(strip_paste_end(b"x\x1b[20\x1b[201~1~y"), b"xy");
+        assert_eq!(strip_paste_end(b"\x1b[2\x1b[20\x1b[201~1~01~"), b"");
+    }
+
+    #[test]
+    fn leaves_other_text_alone() {
+        assert_eq!(strip_paste_end(b"\x1b[200~ok\x1b[20"), b"\x1b[200~ok\x1b[20");
+    }
+
+    #[test]
+    fn scans_set_and_reset() {
+        let mut scanner = PasteModeScanner::new();
+        assert_eq!(scanner.scan(b"prompt\x1b[?2004h$ "), Some(true));
+        assert_eq!(scanner.scan(b"\x1b[?2004l"), Some(false));
+        assert_eq!(scanner.scan(b"plain output"), None);
+    }
+
+    #[test]
+    fn scans_marker_split_across_reads() {
+        let mut scanner = PasteModeScanner::new();
+        assert_eq!(scanner.scan(b"abc\x1b"), None);
+        assert_eq!(scanner.scan(b"[?20"), None);
+        assert_eq!(scanner.scan(b"04h"), Some(true));
+    }
+
+    #[test]
+    fn scans_combined_private_modes() {
+        let mut scanner = PasteModeScanner::new();
+        assert_eq!(scanner.scan(b"\x1b[?1;2004h"), Some(true));
+        assert_eq!(scanner.scan(b"\x1b[?1;2004l"), Some(false));
+        assert_eq!(scanner.scan(b"\x1b[?1;20045h"), None);
+    }
+
+    #[test]
+    fn last_mode_in_chunk_wins() {
+        let mut scanner = PasteModeScanner::new();
+        assert_eq!(scanner.scan(b"\x1b[?2004h\x1b[?2004l"), Some(false));
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::{Path, PathBuf};
+
+/// Directory whose sessions share a color: the enclosing git checkout, else `cwd` itself
+pub fn project_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+/// Check a user-chosen color is `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`; returns it lowercased
+pub fn parse_hex_color(input: &str) -> Result<String, String> {
+    let color = input.trim();
+    let valid = color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        return Err(format!("Invalid color (expected hex like #1e90ff): {}", input));
+    }
+    Ok(color.to_ascii_lowercase())
+}
+
+/// Deterministic CSS color for a directory, e.g. `hsl(212, 65%, 55%)`.
+///
+/// Uses FNV-1a rather than `DefaultHasher`, whose output may change between
+/// Rust releases, so a directory keeps its color across builds.
+pub fn color_for_path(path: &Path) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in path.to_string_lossy().as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("hsl({}, 65%, 55%)", hash % 360)
+}
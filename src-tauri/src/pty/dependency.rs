@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use super::session::SessionInfo;
+
+/// Follow `depends_on` links from `id`, nearest dependency first.
+///
+/// The chain ends where `depends_on` returns `None`. If it loops, the ids
+/// around the loop are returned as the error, starting and ending with the
+/// repeated one.
+pub fn dependency_chain(id: &str, depends_on: impl Fn(&str) -> Option<String>) -> Result<Vec<String>, Vec<String>> {
+    let mut walked = vec![id.to_string()];
+    let mut current = id.to_string();
+    while let Some(next) = depends_on(&current) {
+        if let Some(start) = walked.iter().position(|seen| *seen == next) {
+            let mut cycle = walked.split_off(start);
+            cycle.push(next);
+            return Err(cycle);
+        }
+        walked.push(next.clone());
+        current = next;
+    }
+    walked.remove(0);
+    Ok(walked)
+}
+
+/// Describe a cycle from `dependency_chain` by session name
+pub fn describe_cycle(cycle: &[String], name: impl Fn(&str) -> String) -> String {
+    let names: Vec<String> = cycle.iter().map(|id| name(id)).collect();
+    format!("Dependency cycle: {}", names.join(" -> "))
+}
+
+/// Split sessions into waves to start in order, each session in a later wave
+/// than anything it depends on among `sessions`.
+///
+/// Sessions on a dependency cycle, or depending on one, are left out of the
+/// waves and returned with the reason as `(session id, error)`.
+pub fn start_waves(sessions: Vec<SessionInfo>) -> (Vec<Vec<SessionInfo>>, Vec<(String, String)>) {
+    let links: HashMap<String, Option<String>> = sessions
+        .iter()
+        .map(|s| (s.id.clone(), s.depends_on.clone()))
+        .collect();
+    let names: HashMap<String, String> = sessions.iter().map(|s| (s.id.clone(), s.name.clone())).collect();
+    let depends_on = |id: &str| {
+        links
+            .get(id)
+            .cloned()
+            .flatten()
+            .filter(|dependency| links.contains_key(dependency))
+    };
+
+    let mut waves: Vec<Vec<SessionInfo>> = Vec::new();
+    let mut errors = Vec::new();
+    for session in sessions {
+        match dependency_chain(&session.id, depends_on) {
+            Ok(chain) => {
+                let depth = chain.len();
+                if waves.len() <= depth {
+                    waves.resize_with(depth + 1, Vec::new);
+                }
+                waves[depth].push(session);
+            }
+            Err(cycle) => {
+                let error = describe_cycle(&cycle, |id| names.get(id).cloned().unwrap_or_else(|| id.to_string()));
+                errors.push((session.id, error));
+            }
+        }
+    }
+    // Waves are only empty when every session at that depth sits on a cycle
+    waves.retain(|wave| !wave.is_empty());
+    (waves, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn session(id: &str, depends_on: Option<&str>) -> SessionInfo {
+        let mut info = SessionInfo::new(id.to_string(), format!("{}-name", id), "/bin/sh".to_string(), PathBuf::from("/"));
+        info.depends_on = depends_on.map(str::to_string);
+        info
+    }
+
+    fn ids(wave: &[SessionInfo]) -> Vec<&str> {
+        wave.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn follows_the_chain_nearest_first() {
+        let links: HashMap<&str, &str> = [("c", "b"), ("b", "a")].into();
+        let depends_on = |id: &str| links.get(id).map(|d| d.to_string());
+        assert_eq!(dependency_chain("c", depends_on), Ok(vec!["b".to_string(), "a".to_string()]));
+        assert_eq!(dependency_chain("a", depends_on), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let links: HashMap<&str, &str> = [("a", "b"), ("b", "c"), ("c", "b")].into();
+        let cycle = dependency_chain("a", |id| links.get(id).map(|d| d.to_string())).unwrap_err();
+        assert_eq!(cycle, ["b", "c", "b"]);
+        assert_eq!(describe_cycle(&cycle, |id| id.to_uppercase()), "Dependency cycle: B -> C -> B");
+
+        let cycle = dependency_chain("a", |_| Some("a".to_string())).unwrap_err();
+        assert_eq!(cycle, ["a", "a"]);
+    }
+
+    #[test]
+    fn groups_sessions_into_waves() {
+        let sessions = vec![
+            session("web", Some("api")),
+            session("api", Some("db")),
+            session("db", None),
+            session("logs", None),
+            // Depends on a session that isn't being started, so it goes first
+            session("worker", Some("gone")),
+        ];
+        let (waves, errors) = start_waves(sessions);
+        let waves: Vec<Vec<&str>> = waves.iter().map(|wave| ids(wave)).collect();
+        assert_eq!(waves, [vec!["db", "logs", "worker"], vec!["api"], vec!["web"]]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn leaves_cycles_out_of_the_waves() {
+        let sessions = vec![
+            session("a", Some("b")),
+            session("b", Some("a")),
+            session("c", Some("a")),
+            session("d", None),
+        ];
+        let (waves, errors) = start_waves(sessions);
+        let waves: Vec<Vec<&str>> = waves.iter().map(|wave| ids(wave)).collect();
+        assert_eq!(waves, [vec!["d"]]);
+        assert_eq!(errors, [
+            ("a".to_string(), "Dependency cycle: a-name -> b-name -> a-name".to_string()),
+            ("b".to_string(), "Dependency cycle: b-name -> a-name -> b-name".to_string()),
+            ("c".to_string(), "Dependency cycle: a-name -> b-name -> a-name".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A dotenv file whose variables are loaded into a session's environment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EnvFile {
+    pub path: PathBuf,
+    /// Fail the spawn if the file is missing; otherwise just warn
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl EnvFile {
+    /// Read and parse the file, resolving a relative path against `cwd`.
+    /// A missing optional file yields no variables.
+    pub fn load(&self, cwd: &Path) -> Result<Vec<(String, String)>, String> {
+        let path = cwd.join(&self.path);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => parse(&content).map_err(|e| format!("Invalid env file {}: {}", path.display(), e)),
+            Err(e) if !self.required && e.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("Env file {} not found, skipping", path.display());
+                Ok(Vec::new())
+            }
+            Err(e) => Err(format!("Failed to read env file {}: {}", path.display(), e)),
+        }
+    }
+}
+
+/// Parse dotenv-format content into (key, value) pairs, in file order.
+///
+/// Supports `KEY=VALUE` lines with an optional `export ` prefix, `#` comments
+/// (whole-line, or after whitespace in an unquoted value), single-quoted values
+/// taken literally, and double-quoted values that may span lines and understand
+/// `\n`, `\r`, `\t`, `\"`, `\\` and `\$`. No variable interpolation is done.
+pub fn parse(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = Vec::new();
+    let mut chars = content.chars().peekable();
+    let mut line = 1;
+
+    loop {
+        // Skip blank lines, indentation and comment lines
+        while let Some(&c) = chars.peek() {
+            if c == '\n' {
+                line += 1;
+                chars.next();
+            } else if c.is_whitespace() {
+                chars.next();
+            } else if c == '#' {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            } else {
+                break;
+            }
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|&c| c != '=' && c != '\n') {
+            key.push(c);
+        }
+        let key = key.trim();
+        let key = key
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map(str::trim_start)
+            .unwrap_or(key);
+        if chars.next() != Some('=') {
+            return Err(format!("line {}: expected KEY=VALUE", line));
+        }
+        if !is_valid_key(key) {
+            return Err(format!("line {}: invalid variable name '{}'", line, key));
+        }
+
+        while chars.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+
+        let start_line = line;
+        let value = match chars.peek() {
+            Some('\'') => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => return Err(format!("line {}: unterminated single quote", start_line)),
+                    }
+                }
+                value
+            }
+            Some('"') => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('r') => value.push('\r'),
+                            Some('t') => value.push('\t'),
+                            Some(c @ ('"' | '\\' | '$')) => value.push(c),
+                            Some(c) => {
+                                // Unknown escapes are kept as written
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                value.push('\\');
+                                value.push(c);
+                            }
+                            None => return Err(format!("line {}: unterminated double quote", start_line)),
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => return Err(format!("line {}: unterminated double quote", start_line)),
+                    }
+                }
+                value
+            }
+            _ => {
+                let mut value = String::new();
+                while let Some(c) = chars.next_if(|&c| c != '\n') {
+                    if c == '#' && value.ends_with([' ', '\t']) {
+                        while chars.next_if(|&c| c != '\n').is_some() {}
+                        break;
+                    }
+                    value.push(c);
+                }
+                value.trim_end().to_string()
+            }
+        };
+
+        // Anything after a closing quote must be whitespace or a comment
+        while chars.next_if(|&c| c == ' ' || c == '\t' || c == '\r').is_some() {}
+        match chars.peek() {
+            None | Some('\n') => {}
+            Some('#') => while chars.next_if(|&c| c != '\n').is_some() {},
+            Some(_) => return Err(format!("line {}: unexpected text after value", line)),
+        }
+
+        vars.push((key.to_string(), value));
+    }
+
+    Ok(vars)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(content: &str) -> Vec<(String, String)> {
+        parse(content).unwrap()
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn parses_plain_values_and_comments() {
+        let content = "# settings\n\nA=1\n  B = two words  # trailing\nC=a#b\nD=\n";
+        assert_eq!(pairs(content), [pair("A", "1"), pair("B", "two words"), pair("C", "a#b"), pair("D", "")]);
+    }
+
+    #[test]
+    fn single_quotes_are_literal() {
+        assert_eq!(pairs("A='x \\n $HOME # not a comment'\n"), [pair("A", "x \\n $HOME # not a comment")]);
+        assert_eq!(pairs("A='two\nlines'"), [pair("A", "two\nlines")]);
+    }
+
+    #[test]
+    fn double_quotes_understand_escapes() {
+        assert_eq!(
+            pairs(r#"A="tab\there \"quoted\" back\\slash \$HOME\nnext" # comment"#),
+            [pair("A", "tab\there \"quoted\" back\\slash $HOME\nnext")]
+        );
+        assert_eq!(pairs(r#"A="keep \q""#), [pair("A", "keep \\q")]);
+        assert_eq!(pairs("A=\"multi\nline\"\nB=2"), [pair("A", "multi\nline"), pair("B", "2")]);
+    }
+
+    #[test]
+    fn export_prefix_is_dropped() {
+        assert_eq!(pairs("export A=1\nexport\tB=2\nexported=3"), [pair("A", "1"), pair("B", "2"), pair("exported", "3")]);
+    }
+
+    #[test]
+    fn duplicate_keys_keep_file_order() {
+        // Applied in order, so the last one wins in the environment
+        assert_eq!(pairs("A=1\nB=2\nA=3"), [pair("A", "1"), pair("B", "2"), pair("A", "3")]);
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        assert_eq!(parse("A=1\nnot a pair\n"), Err("line 2: expected KEY=VALUE".to_string()));
+        assert_eq!(parse("1A=x"), Err("line 1: invalid variable name '1A'".to_string()));
+        assert_eq!(parse("A='open\n"), Err("line 1: unterminated single quote".to_string()));
+        assert_eq!(parse("\nA=\"open"), Err("line 2: unterminated double quote".to_string()));
+        assert_eq!(parse("A=\"x\" y"), Err("line 1: unexpected text after value".to_string()));
+    }
+
+    #[test]
+    fn missing_optional_file_is_empty() {
+        let file = EnvFile {
+            path: PathBuf::from("shelltree-missing.env"),
+            required: false,
+        };
+        assert_eq!(file.load(Path::new("/nonexistent")), Ok(Vec::new()));
+        let required = EnvFile { required: true, ..file };
+        assert!(required.load(Path::new("/nonexistent")).unwrap_err().starts_with("Failed to read env file"));
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Directory changes closer together than this are reported once, for where they end up
+pub const REPO_CONTEXT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The git repository a directory is in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepoContext {
+    /// The working tree's top directory
+    pub root: PathBuf,
+    /// Checked-out branch; unset for a detached HEAD
+    pub branch: Option<String>,
+}
+
+/// Find the repository containing `dir` by looking for `.git` in it and its
+/// parents. A `.git` file (linked worktrees, submodules) points at the real
+/// git directory.
+pub fn repo_context(dir: &Path) -> Option<RepoContext> {
+    for root in dir.ancestors() {
+        let dot_git = root.join(".git");
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else if dot_git.is_file() {
+            let content = std::fs::read_to_string(&dot_git).ok()?;
+            root.join(content.trim().strip_prefix("gitdir:")?.trim())
+        } else {
+            continue;
+        };
+        return Some(RepoContext {
+            root: root.to_path_buf(),
+            branch: head_branch(&git_dir),
+        });
+    }
+    None
+}
+
+fn head_branch(git_dir: &Path) -> Option<String> {
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim().strip_prefix("ref: refs/heads/").map(str::to_string)
+}
+
+/// Look up the repository for each directory from `cwds`, calling `changed`
+/// whenever it differs from the last one (entering, leaving or switching
+/// repositories or branches). Returns once the sender is dropped.
+pub fn watch_repo_context(cwds: Receiver<PathBuf>, mut changed: impl FnMut(Option<RepoContext>)) {
+    let mut current = None;
+    while let Ok(mut cwd) = cwds.recv() {
+        // Wait for a burst of `cd`s to settle
+        loop {
+            match cwds.recv_timeout(REPO_CONTEXT_DEBOUNCE) {
+                Ok(next) => cwd = next,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        let context = repo_context(&cwd);
+        if context != current {
+            current = context.clone();
+            changed(context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+
+    /// A fresh directory under the system temp dir
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shelltree-git-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn init_repo(root: &Path, head: &str) {
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".git/HEAD"), head).unwrap();
+    }
+
+    #[test]
+    fn finds_the_repo_above_a_directory() {
+        let dir = temp_dir();
+        init_repo(&dir, "ref: refs/heads/feature/x\n");
+        fs::create_dir_all(dir.join("src/deep")).unwrap();
+
+        let context = repo_context(&dir.join("src/deep")).unwrap();
+        assert_eq!(context, RepoContext {
+            root: dir.clone(),
+            branch: Some("feature/x".to_string()),
+        });
+
+        fs::write(dir.join(".git/HEAD"), "0123456789abcdef0123456789abcdef01234567\n").unwrap();
+        assert_eq!(repo_context(&dir).unwrap().branch, None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn follows_gitdir_files() {
+        let dir = temp_dir();
+        init_repo(&dir.join("store"), "ref: refs/heads/linked\n");
+        fs::create_dir_all(dir.join("worktree")).unwrap();
+        fs::write(dir.join("worktree/.git"), "gitdir: ../store/.git\n").unwrap();
+
+        let context = repo_context(&dir.join("worktree")).unwrap();
+        assert_eq!(context.root, dir.join("worktree"));
+        assert_eq!(context.branch.as_deref(), Some("linked"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reports_changes_once_cd_settles() {
+        let dir = temp_dir();
+        init_repo(&dir.join("repo"), "ref: refs/heads/main\n");
+        fs::create_dir_all(dir.join("repo/sub")).unwrap();
+        fs::create_dir_all(dir.join("plain")).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let watcher = std::thread::spawn(move || {
+            let mut seen = Vec::new();
+            watch_repo_context(rx, |context| seen.push(context.map(|c| c.root)));
+            seen
+        });
+        // None -> None isn't a change
+        tx.send(dir.join("plain")).unwrap();
+        std::thread::sleep(REPO_CONTEXT_DEBOUNCE * 2);
+        // Only where the burst ends up counts
+        tx.send(dir.join("repo")).unwrap();
+        tx.send(dir.join("plain")).unwrap();
+        tx.send(dir.join("repo/sub")).unwrap();
+        std::thread::sleep(REPO_CONTEXT_DEBOUNCE * 2);
+        // Same repo, no event
+        tx.send(dir.join("repo")).unwrap();
+        std::thread::sleep(REPO_CONTEXT_DEBOUNCE * 2);
+        tx.send(dir.join("plain")).unwrap();
+        std::thread::sleep(REPO_CONTEXT_DEBOUNCE * 2);
+        drop(tx);
+
+        assert_eq!(watcher.join().unwrap(), [Some(dir.join("repo")), None]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
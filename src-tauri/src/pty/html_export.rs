@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Page colors for `export_session_html`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum HtmlTheme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl HtmlTheme {
+    /// (background, foreground)
+    fn colors(self) -> (&'static str, &'static str) {
+        match self {
+            HtmlTheme::Dark => ("#1e1e1e", "#d4d4d4"),
+            HtmlTheme::Light => ("#ffffff", "#1e1e1e"),
+        }
+    }
+}
+
+/// The 16 ANSI colors, normal then bright
+const ANSI_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x31, 0x31),
+    (0x0d, 0xbc, 0x79),
+    (0xe5, 0xe5, 0x10),
+    (0x24, 0x72, 0xc8),
+    (0xbc, 0x3f, 0xbc),
+    (0x11, 0xa8, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x66, 0x66, 0x66),
+    (0xf1, 0x4c, 0x4c),
+    (0x23, 0xd1, 0x8b),
+    (0xf5, 0xf5, 0x43),
+    (0x3b, 0x8e, 0xea),
+    (0xd6, 0x70, 0xd6),
+    (0x29, 0xb8, 0xdb),
+    (0xff, 0xff, 0xff),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Rgb(r, g, b) => (r, g, b),
+            Color::Indexed(i) if i < 16 => ANSI_COLORS[i as usize],
+            // 6x6x6 color cube
+            Color::Indexed(i) if i < 232 => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let i = i - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            // Grayscale ramp
+            Color::Indexed(i) => {
+                let v = 8 + (i - 232) * 10;
+                (v, v, v)
+            }
+        }
+    }
+
+    fn css(self) -> String {
+        let (r, g, b) = self.rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    underline: bool,
+}
+
+impl Style {
+    /// Apply an SGR sequence's parameters, each with its `:` sub-parameters
+    fn apply(&mut self, params: &[Vec<u16>]) {
+        let mut i = 0;
+        while i < params.len() {
+            let param = &params[i];
+            i += 1;
+            match param[0] {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                4 => self.underline = true,
+                24 => self.underline = false,
+                n @ 30..=37 => self.fg = Some(Color::Indexed(n as u8 - 30)),
+                n @ 90..=97 => self.fg = Some(Color::Indexed(n as u8 - 90 + 8)),
+                39 => self.fg = None,
+                n @ 40..=47 => self.bg = Some(Color::Indexed(n as u8 - 40)),
+                n @ 100..=107 => self.bg = Some(Color::Indexed(n as u8 - 100 + 8)),
+                49 => self.bg = None,
+                n @ (38 | 48) => {
+                    let color = if param.len() > 1 {
+                        colon_color(&param[1..])
+                    } else {
+                        // `38;5;n` / `38;2;r;g;b`: the color takes the next few parameters
+                        let rest: Vec<u16> = params[i..].iter().map(|p| p[0]).collect();
+                        let (color, used) = semicolon_color(&rest);
+                        i += used;
+                        color
+                    };
+                    match (n, color) {
+                        (38, Some(color)) => self.fg = Some(color),
+                        (_, Some(color)) => self.bg = Some(color),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn css(&self) -> String {
+        let mut css = Vec::new();
+        if let Some(fg) = self.fg {
+            css.push(format!("color:{}", fg.css()));
+        }
+        if let Some(bg) = self.bg {
+            css.push(format!("background-color:{}", bg.css()));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if self.underline {
+            css.push("text-decoration:underline".to_string());
+        }
+        css.join(";")
+    }
+}
+
+/// `5;n` or `2;r;g;b` at the start of `args`, and how many of them it used
+fn semicolon_color(args: &[u16]) -> (Option<Color>, usize) {
+    match args {
+        [5, n, ..] => (byte(*n).map(Color::Indexed), 2),
+        [2, r, g, b, ..] => (rgb(*r, *g, *b), 4),
+        _ => (None, 0),
+    }
+}
+
+/// `5:n` or `2:r:g:b`, the latter optionally with a color space id before r
+fn colon_color(args: &[u16]) -> Option<Color> {
+    match *args {
+        [5, n] => byte(n).map(Color::Indexed),
+        [2, r, g, b] | [2, _, r, g, b] => rgb(r, g, b),
+        _ => None,
+    }
+}
+
+fn byte(value: u16) -> Option<u8> {
+    u8::try_from(value).ok()
+}
+
+fn rgb(r: u16, g: u16, b: u16) -> Option<Color> {
+    Some(Color::Rgb(byte(r)?, byte(g)?, byte(b)?))
+}
+
+/// One line of output as styled characters, written at a cursor column so
+/// carriage returns and backspaces overwrite the way a terminal shows them
+#[derive(Default)]
+struct Line {
+    cells: Vec<(char, Style)>,
+    cursor: usize,
+}
+
+impl Line {
+    fn put(&mut self, c: char, style: Style) {
+        if self.cursor < self.cells.len() {
+            self.cells[self.cursor] = (c, style);
+        } else {
+            self.cells.resize(self.cursor, (' ', Style::default()));
+            self.cells.push((c, style));
+        }
+        self.cursor += 1;
+    }
+
+    /// `CSI K`: 0 erases to the end of the line, 1 to the cursor, 2 all of it
+    fn erase(&mut self, mode: u16) {
+        match mode {
+            0 => self.cells.truncate(self.cursor),
+            1 => {
+                let end = (self.cursor + 1).min(self.cells.len());
+                self.cells[..end].fill((' ', Style::default()));
+            }
+            2 => self.cells.clear(),
+            _ => {}
+        }
+    }
+}
+
+/// Render terminal output as a standalone HTML page, with SGR colors (16,
+/// 256 and truecolor), bold and underline as inline styles. Other escape
+/// sequences are dropped.
+pub fn render_html(output: &[u8], title: &str, theme: HtmlTheme) -> String {
+    let text = String::from_utf8_lossy(output);
+    let mut lines: Vec<Line> = Vec::new();
+    let mut line = Line::default();
+    let mut style = Style::default();
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut body = String::new();
+                    let mut last = None;
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            last = Some(c);
+                            break;
+                        }
+                        body.push(c);
+                    }
+                    // Private sequences (`CSI ? ...`) aren't SGR or EL
+                    if body.starts_with(|c: char| !c.is_ascii_digit() && c != ';' && c != ':') {
+                        continue;
+                    }
+                    let params: Vec<Vec<u16>> = body
+                        .split(';')
+                        .map(|p| p.split(':').map(|v| v.parse().unwrap_or(0)).collect())
+                        .collect();
+                    match last {
+                        Some('m') => style.apply(&params),
+                        Some('K') => line.erase(params[0][0]),
+                        _ => {}
+                    }
+                }
+                // OSC/DCS/APC/PM: up to BEL or ST
+                Some(']' | 'P' | '_' | '^') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\n' => lines.push(std::mem::take(&mut line)),
+            '\r' => line.cursor = 0,
+            '\x08' => line.cursor = line.cursor.saturating_sub(1),
+            '\t' => line.put('\t', style),
+            c if c.is_control() => {}
+            c => line.put(c, style),
+        }
+    }
+    if !line.cells.is_empty() {
+        lines.push(line);
+    }
+
+    let (background, foreground) = theme.colors();
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ margin: 0; background: {}; color: {}; }}\n\
+         pre {{ margin: 0; padding: 1em; font-family: ui-monospace, Menlo, Consolas, monospace; \
+         font-size: 13px; line-height: 1.3; white-space: pre-wrap; }}\n\
+         </style>\n</head>\n<body><pre>",
+        escape(title),
+        background,
+        foreground
+    );
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        write_line(&mut html, &line.cells);
+    }
+    html.push_str("</pre></body>\n</html>\n");
+    html
+}
+
+/// Write cells as runs of text, each run with a style in its own span
+fn write_line(html: &mut String, cells: &[(char, Style)]) {
+    for run in cells.chunk_by(|a, b| a.1 == b.1) {
+        let text: String = run.iter().map(|(c, _)| *c).collect();
+        let css = run[0].1.css();
+        if css.is_empty() {
+            html.push_str(&escape(&text));
+        } else {
+            let _ = write!(html, "<span style=\"{}\">{}</span>", css, escape(&text));
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The page's `<pre>` contents
+    fn body(output: &[u8]) -> String {
+        let html = render_html(output, "t", HtmlTheme::Dark);
+        let start = html.find("<pre>").unwrap() + "<pre>".len();
+        let end = html.find("</pre>").unwrap();
+        html[start..end].to_string()
+    }
+
+    #[test]
+    fn converts_basic_colors_and_attributes() {
+        assert_eq!(
+            body(b"\x1b[1;31merror\x1b[0m: \x1b[4mfile\x1b[24m ok"),
+            "<span style=\"color:#cd3131;font-weight:bold\">error</span>: \
+             <span style=\"text-decoration:underline\">file</span> ok"
+        );
+        assert_eq!(body(b"\x1b[94;41mx"), "<span style=\"color:#3b8eea;background-color:#cd3131\">x</span>");
+    }
+
+    #[test]
+    fn converts_256_and_truecolor() {
+        assert_eq!(body(b"\x1b[38;5;196mr"), "<span style=\"color:#ff0000\">r</span>");
+        assert_eq!(body(b"\x1b[48;5;244mg"), "<span style=\"background-color:#808080\">g</span>");
+        assert_eq!(body(b"\x1b[38;2;1;2;3;1mt"), "<span style=\"color:#010203;font-weight:bold\">t</span>");
+        assert_eq!(body(b"\x1b[38:2::16:32:48mc"), "<span style=\"color:#102030\">c</span>");
+    }
+
+    #[test]
+    fn escapes_html_and_drops_other_sequences() {
+        assert_eq!(body(b"\x1b]0;title\x07\x1b[?25l<a & \"b\">\x1b[2J"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn carriage_returns_overwrite() {
+        assert_eq!(body(b"  0%\r 50%\r100%\r\ndone\r\n"), "100%\ndone");
+        assert_eq!(body(b"long line\rab\x1b[K"), "ab");
+    }
+
+    #[test]
+    fn themes_set_page_colors() {
+        assert!(render_html(b"", "t", HtmlTheme::Light).contains("background: #ffffff"));
+        assert!(render_html(b"", "<t>", HtmlTheme::Dark).contains("<title>&lt;t&gt;</title>"));
+    }
+}
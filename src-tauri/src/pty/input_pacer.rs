@@ -0,0 +1,172 @@
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the pacer releases a chunk of queued input
+const TICK: Duration = Duration::from_millis(50);
+const TICKS_PER_SEC: u64 = 20;
+
+/// A PTY writer shared between the manager and a pacer thread
+pub type SharedWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Input pacing statistics for a session
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct InputPacingStats {
+    pub limit_bytes_per_sec: Option<u64>,
+    /// Bytes queued but not yet written to the PTY
+    pub pending_bytes: usize,
+    /// Total bytes that went through the pacer
+    pub paced_bytes_total: u64,
+    /// True while queued input is being paced out
+    pub active: bool,
+}
+
+struct PacerShared {
+    queue: Mutex<VecDeque<u8>>,
+    ready: Condvar,
+    stopped: AtomicBool,
+    bytes_per_sec: AtomicU64,
+    paced_total: AtomicU64,
+}
+
+/// Rate-limits input to a session by queueing writes and releasing them in chunks
+pub struct InputPacer {
+    shared: Arc<PacerShared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl InputPacer {
+    pub fn new(writer: SharedWriter, bytes_per_sec: u64) -> Self {
+        let shared = Arc::new(PacerShared {
+            queue: Mutex::new(VecDeque::new()),
+            ready: Condvar::new(),
+            stopped: AtomicBool::new(false),
+            bytes_per_sec: AtomicU64::new(bytes_per_sec.max(1)),
+            paced_total: AtomicU64::new(0),
+        });
+
+        let thread = thread::spawn({
+            let shared = shared.clone();
+            move || run_pacer(shared, writer)
+        });
+
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Change the rate of an existing pacer
+    pub fn set_rate(&self, bytes_per_sec: u64) {
+        self.shared
+            .bytes_per_sec
+            .store(bytes_per_sec.max(1), Ordering::Relaxed);
+    }
+
+    /// A handle for queueing input to be written at the configured rate
+    pub fn queue(&self) -> PacerQueue {
+        PacerQueue {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Drop queued input that hasn't been written yet; returns how many bytes were dropped
+    pub fn discard(&self) -> usize {
+        let mut queue = self.shared.queue.lock();
+        let dropped = queue.len();
+        queue.clear();
+        dropped
+    }
+
+    pub fn stats(&self) -> InputPacingStats {
+        let pending_bytes = self.shared.queue.lock().len();
+        InputPacingStats {
+            limit_bytes_per_sec: Some(self.shared.bytes_per_sec.load(Ordering::Relaxed)),
+            pending_bytes,
+            paced_bytes_total: self.shared.paced_total.load(Ordering::Relaxed),
+            active: pending_bytes > 0,
+        }
+    }
+
+    /// Stop the pacer thread and hand back whatever input it had not written yet
+    pub fn stop(mut self) -> Vec<u8> {
+        self.signal_stop();
+        if let Some(thread) = self.thread.take() {
+            // Wait for any in-flight chunk so the caller can write the rest in order
+            let _ = thread.join();
+        }
+        self.shared.queue.lock().drain(..).collect()
+    }
+
+    fn signal_stop(&self) {
+        let _queue = self.shared.queue.lock();
+        self.shared.stopped.store(true, Ordering::Relaxed);
+        self.shared.ready.notify_one();
+    }
+}
+
+/// Queues input on a pacer; outlives the pacer, but stops accepting input once it has stopped
+#[derive(Clone)]
+pub struct PacerQueue {
+    shared: Arc<PacerShared>,
+}
+
+impl PacerQueue {
+    /// Queue chunks in order; returns false, queueing nothing, if the pacer has stopped
+    pub fn enqueue(&self, chunks: &[&[u8]]) -> bool {
+        {
+            // `stopped` is set under this lock, so nothing lands after `stop` drains the queue
+            let mut queue = self.shared.queue.lock();
+            if self.shared.stopped.load(Ordering::Relaxed) {
+                return false;
+            }
+            for chunk in chunks {
+                queue.extend(*chunk);
+            }
+        }
+        self.shared.ready.notify_one();
+        true
+    }
+}
+
+impl Drop for InputPacer {
+    fn drop(&mut self) {
+        self.signal_stop();
+    }
+}
+
+fn run_pacer(shared: Arc<PacerShared>, writer: SharedWriter) {
+    loop {
+        let chunk: Vec<u8> = {
+            let mut queue = shared.queue.lock();
+            while queue.is_empty() && !shared.stopped.load(Ordering::Relaxed) {
+                shared.ready.wait(&mut queue);
+            }
+            if shared.stopped.load(Ordering::Relaxed) {
+                return;
+            }
+            let per_tick = (shared.bytes_per_sec.load(Ordering::Relaxed) / TICKS_PER_SEC).max(1);
+            let n = (per_tick as usize).min(queue.len());
+            queue.drain(..n).collect()
+        };
+
+        let result = {
+            let mut writer = writer.lock();
+            writer.write_all(&chunk).and_then(|_| writer.flush())
+        };
+        if let Err(e) = result {
+            eprintln!("Paced write error: {}", e);
+            shared.queue.lock().clear();
+            continue;
+        }
+
+        shared
+            .paced_total
+            .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        thread::sleep(TICK);
+    }
+}
@@ -1,19 +1,105 @@
-use parking_lot::Mutex;
-use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use base64::Engine;
+use parking_lot::{Condvar, Mutex, RwLock};
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
-use tauri::{AppHandle, Emitter};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
 
-use super::session::{SessionInfo, SessionStatus};
+use super::bell::BellDetector;
+use super::bracketed_paste::{self, PasteModeScanner, PASTE_END, PASTE_START};
+use super::color;
+use super::dependency;
+use super::git;
+use super::html_export::{self, HtmlTheme};
+use super::input_pacer::{InputPacer, InputPacingStats, PacerQueue, SharedWriter};
+use super::osc::{OscEvent, OscScanner};
+use super::output_batcher::{self, DEFAULT_FLUSH_INTERVAL_MS, DEFAULT_MAX_EVENT_BYTES};
+use super::output_sinks::{OutputSink, SentinelSink, SessionSinks};
+use super::output_stats::OutputStats;
+use super::process::{self, ProcessNode, Signal};
+use super::recording::{Recording, RecordingSink};
+use super::resource_limits;
+use super::scrollback::{Scrollback, DEFAULT_SCROLLBACK_BYTES};
+use super::search::{self, SearchTarget};
+#[cfg(unix)]
+use super::socket_sink::SessionSocket;
+use super::session_log::{SessionLog, DEFAULT_LOG_MAX_FILES};
+use super::session::{CloseBehavior, PathMode, SessionCommand, SessionInfo, SessionKind, SessionStatus, Settings};
+use super::terminal_query::{QueryScanner, TerminalQuery};
+use super::termios::{echo_state, reset_termios, EchoProbe, EchoState};
+use super::utf8::Utf8Carry;
+
+/// Written to the frontend terminal by `reset_session`: full reset (RIS), soft
+/// reset (DECSTR), then the modes RIS doesn't reliably clear everywhere — alt
+/// screen, mouse reporting, bracketed paste, hidden cursor and SGR attributes
+const TERMINAL_RESET_SEQUENCE: &[u8] =
+    b"\x1bc\x1b[!p\x1b[?1049l\x1b[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1006l\x1b[?2004l\x1b[?25h\x1b[0m";
+
+/// TERM advertised to sessions without an override
+pub const DEFAULT_TERM: &str = "xterm-256color";
+
+/// How long the reader waits at EOF for the shell's exit status before reporting no code
+const EXIT_STATUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Reported for a session ended by `kill_session`: what a shell reports for a job
+/// killed by SIGHUP (128 + 1)
+const KILLED_EXIT_CODE: u32 = 129;
+
+/// Resizes of one session closer together than this are collapsed into the last one
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Sessions inside a resize debounce window, with the latest size (rows, cols)
+/// requested during it, if any
+type PendingResizes = Mutex<HashMap<String, Option<(u16, u16)>>>;
+
+/// What `ssh` exits with when it fails itself (e.g. the host is down), as
+/// opposed to passing on the remote command's status
+const SSH_ERROR_EXIT_CODE: u32 = 255;
+
+/// Output searched for ssh's error message when it fails to connect
+const SSH_ERROR_TAIL_BYTES: usize = 4096;
+
+/// How long `shutdown_all` gives shells to exit before killing them outright
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
 
 /// Output event sent to the frontend
 #[derive(Clone, serde::Serialize)]
 pub struct PtyOutput {
     pub id: String,
-    pub data: Vec<u8>,
+    pub data: OutputData,
+}
+
+/// How `pty-output` events carry their bytes
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum OutputEncoding {
+    /// A JSON array of numbers
+    Raw,
+    /// A base64 string, roughly 40% the size of `Raw` on the wire
+    Base64,
+}
+
+/// Output bytes in the current `OutputEncoding`
+#[derive(Clone, serde::Serialize)]
+#[serde(untagged)]
+pub enum OutputData {
+    Raw(Vec<u8>),
+    Base64(String),
+}
+
+impl OutputData {
+    fn encode(data: &[u8], base64: bool) -> Self {
+        if base64 {
+            Self::Base64(base64::engine::general_purpose::STANDARD.encode(data))
+        } else {
+            Self::Raw(data.to_vec())
+        }
+    }
 }
 
 /// Session exit event
@@ -23,25 +109,328 @@ pub struct PtyExit {
     pub code: Option<u32>,
 }
 
-/// Active PTY session with handles
+/// Terminal query event (a program asked the terminal for its state)
+#[derive(Clone, serde::Serialize)]
+pub struct PtyTerminalQuery {
+    pub id: String,
+    pub query: TerminalQuery,
+}
+
+/// Echo state change event (e.g. a password prompt turned echo off)
+#[derive(Clone, serde::Serialize)]
+pub struct PtyEchoChanged {
+    pub id: String,
+    pub echo: EchoState,
+}
+
+/// A session rang the terminal bell
+#[derive(Clone, serde::Serialize)]
+pub struct PtyBell {
+    pub id: String,
+}
+
+/// A session's shell moved into, out of or between git repositories (or branches)
+#[derive(Clone, serde::Serialize)]
+pub struct RepoContextChanged {
+    pub id: String,
+    /// Unset once the shell is outside any repository
+    pub root: Option<PathBuf>,
+    pub branch: Option<String>,
+}
+
+/// A session's scrollback was wiped
+#[derive(Clone, serde::Serialize)]
+pub struct SessionCleared {
+    pub id: String,
+}
+
+/// A session took its name from a terminal title
+#[derive(Clone, serde::Serialize)]
+pub struct SessionRenamed {
+    pub id: String,
+    pub name: String,
+}
+
+/// The program currently in the foreground of a session
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ForegroundProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// CPU and memory used by a session's process tree
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ResourceUsage {
+    /// Percent of one core since the previous sample; `None` on the first sample
+    pub cpu_percent: Option<f64>,
+    pub rss_bytes: u64,
+    pub process_count: usize,
+}
+
+/// How much output a session has produced
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionMetrics {
+    pub id: String,
+    /// Output bytes read since the session was created
+    pub total_bytes: u64,
+    /// Output rate over roughly the last second
+    pub bytes_per_sec: f64,
+    /// Unix ms of the last output
+    pub last_activity: i64,
+}
+
+/// Last usage reading for a session, kept to compute CPU deltas and to cache results
+struct UsageSample {
+    taken_at: Instant,
+    cpu_seconds: f64,
+    usage: ResourceUsage,
+}
+
+/// How long a usage reading is reused before walking the process tree again
+const USAGE_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// Where a tap sends its copy of a session's output
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub enum TapTarget {
+    /// Append raw bytes to a file in the temp directory
+    File,
+    /// Emit `session-tap` events
+    Event,
+}
+
+/// A registered output tap
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TapInfo {
+    pub tap_id: String,
+    pub session_id: String,
+    /// File the tap writes to, for `TapTarget::File`
+    pub path: Option<PathBuf>,
+}
+
+/// Tapped output event
+#[derive(Clone, serde::Serialize)]
+pub struct PtyTapOutput {
+    pub tap_id: String,
+    pub id: String,
+    pub data: Vec<u8>,
+}
+
+/// A latency probe in flight, started by `PtyManager::start_ping`
+pub struct PendingPing {
+    /// Sink watching for the sentinel; remove it with `untap_session`
+    pub sink_id: String,
+    pub sent_at: Instant,
+    pub received: mpsc::Receiver<Instant>,
+}
+
+/// A child's exit code, filled in by the thread waiting on it
+#[derive(Default)]
+struct ExitWatch {
+    code: Mutex<Option<u32>>,
+    exited: Condvar,
+    /// Set by `kill_session` so the signal it sends is reported as a kill
+    killed: AtomicBool,
+}
+
+impl ExitWatch {
+    fn set(&self, code: u32) {
+        *self.code.lock() = Some(code);
+        self.exited.notify_all();
+    }
+
+    fn get(&self) -> Option<u32> {
+        *self.code.lock()
+    }
+
+    /// Block until the child has exited or `timeout` passes
+    fn wait_timeout(&self, timeout: Duration) -> Option<u32> {
+        let mut code = self.code.lock();
+        if code.is_none() {
+            self.exited.wait_for(&mut code, timeout);
+        }
+        *code
+    }
+}
+
+/// Sink that re-emits output as `session-tap` events
+struct EventTap {
+    handle: AppHandle,
+    tap_id: String,
+    session_id: String,
+}
+
+impl OutputSink for EventTap {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.handle
+            .emit("session-tap", PtyTapOutput {
+                tap_id: self.tap_id.clone(),
+                id: self.session_id.clone(),
+                data: data.to_vec(),
+            })
+            .map_err(|e| std::io::Error::other(e.to_string()))
+    }
+}
+
+/// Logging to a session's transcript file failed, so it was stopped
+#[derive(Clone, serde::Serialize)]
+pub struct SessionLogError {
+    pub id: String,
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Sink appending output to a session's transcript; reports the write error
+/// that gets it dropped (e.g. a full disk)
+struct LogSink {
+    log: Arc<Mutex<SessionLog>>,
+    session_id: String,
+    handle: Option<AppHandle>,
+}
+
+impl OutputSink for LogSink {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let mut log = self.log.lock();
+        let result = log.write(data);
+        if let (Err(e), Some(handle)) = (&result, &self.handle) {
+            let _ = handle.emit("session-log-error", SessionLogError {
+                id: self.session_id.clone(),
+                path: log.path().to_path_buf(),
+                error: e.to_string(),
+            });
+        }
+        result
+    }
+}
+
+/// A session's config plus, once spawned, its process
 struct ActiveSession {
     pub info: SessionInfo,
+    /// `None` for a lazy session that hasn't been spawned yet
+    pub process: Option<SessionProcess>,
+    /// Extra consumers of the session's output (taps)
+    pub sinks: Arc<SessionSinks>,
+    /// Recent output, for rehydrating a terminal after a reload
+    pub scrollback: Arc<Scrollback>,
+    /// Output counters, including the last activity time; kept outside `info`
+    /// so the reader doesn't need the lock
+    pub output_stats: Arc<OutputStats>,
+    /// Transcript file output is appended to, if logging
+    pub log: Option<Arc<Mutex<SessionLog>>>,
+    /// Output being captured for `stop_recording`
+    pub recording: Option<Arc<Mutex<Recording>>>,
+    /// Directory the shell last reported (OSC 7)
+    pub live_cwd: Option<PathBuf>,
+    /// Terminal title the shell last set (OSC 0/2)
+    pub title: Option<String>,
+    /// Unix socket serving the session's output, if attached
+    #[cfg(unix)]
+    pub socket: Option<SessionSocket>,
+}
+
+impl ActiveSession {
+    /// The session's info with its live activity time, cwd and title filled in
+    fn info(&self) -> SessionInfo {
+        let mut info = self.info.clone();
+        info.last_activity = self.output_stats.last_activity();
+        if let Some(cwd) = &self.live_cwd {
+            info.cwd = cwd.clone();
+        }
+        if let (Some(title), false) = (&self.title, info.name_is_custom) {
+            info.name = title.clone();
+        }
+        info
+    }
+
+    /// The session's info as saved with the layout: its configured cwd and
+    /// name, and none of the state that changes as the shell runs
+    fn layout_info(&self) -> SessionInfo {
+        let mut info = self.info.clone();
+        info.status = SessionStatus::Stopped;
+        info.pid = None;
+        info.exit_code = None;
+        info.last_activity = info.created_at * 1000;
+        info
+    }
+}
+
+/// A session's entry in the manager, locked on its own so that work on one
+/// session never waits on another
+type SessionHandle = Arc<Mutex<ActiveSession>>;
+
+/// PTY handles of a spawned session
+struct SessionProcess {
     pub master: Box<dyn MasterPty + Send>,
-    pub child: Box<dyn Child + Send + Sync>,
-    pub writer: Box<dyn Write + Send>,
+    /// The child itself is owned by its waiter thread
+    pub killer: Box<dyn ChildKiller + Send + Sync>,
+    pub pid: Option<u32>,
+    pub exit: Arc<ExitWatch>,
+    pub writer: SharedWriter,
+    /// Whether the program has turned on bracketed paste (DECSET 2004)
+    pub bracketed_paste: Arc<AtomicBool>,
+    /// Present while an input rate limit is set
+    pub input_pacer: Option<InputPacer>,
+    /// cgroup holding the session when resource limits are applied
+    pub cgroup: Option<PathBuf>,
+}
+
+/// A session between `PtyManager::begin_restart` and `finish_restart`
+pub struct PendingRestart {
+    /// The shell being replaced, until `stop_old`
+    old: Option<SessionProcess>,
+    info: SessionInfo,
+    sinks: Arc<SessionSinks>,
+    scrollback: Arc<Scrollback>,
+    output_stats: Arc<OutputStats>,
+    rows: u16,
+    cols: u16,
+}
+
+impl PendingRestart {
+    /// Stop the old shell and reap it before its replacement starts; blocks
+    /// for up to `EXIT_STATUS_TIMEOUT`
+    pub fn stop_old(&mut self) {
+        if let Some(old) = self.old.take() {
+            let exit = old.exit.clone();
+            stop_process(old);
+            if exit.wait_timeout(EXIT_STATUS_TIMEOUT).is_none() {
+                eprintln!("Old shell for session {} did not exit in time", self.info.id);
+            }
+        }
+    }
 }
 
 /// Manages all PTY sessions
 pub struct PtyManager {
-    sessions: Mutex<HashMap<String, ActiveSession>>,
+    /// The map is only write-locked to add or remove sessions; reader threads
+    /// share it to record how their session ended
+    sessions: Arc<RwLock<HashMap<String, SessionHandle>>>,
     app_handle: Option<AppHandle>,
+    settings: Settings,
+    /// Opt-in: parse output for DSR/DA/window-size queries
+    detect_terminal_queries: Arc<AtomicBool>,
+    /// Send `pty-output` data as base64 rather than a number array
+    base64_output: Arc<AtomicBool>,
+    /// How long `pty-output` data is coalesced before it's emitted, in ms
+    output_flush_interval_ms: Arc<AtomicU64>,
+    /// Largest `pty-output` payload; bigger batches go out as several events
+    max_output_event_bytes: Arc<AtomicUsize>,
+    /// Most recent `get_session_resource_usage` reading per session
+    usage_samples: Mutex<HashMap<String, UsageSample>>,
+    pending_resizes: Arc<PendingResizes>,
 }
 
 impl PtyManager {
     pub fn new() -> Self {
         Self {
-            sessions: Mutex::new(HashMap::new()),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
             app_handle: None,
+            settings: Settings::default(),
+            detect_terminal_queries: Arc::new(AtomicBool::new(false)),
+            base64_output: Arc::new(AtomicBool::new(false)),
+            output_flush_interval_ms: Arc::new(AtomicU64::new(DEFAULT_FLUSH_INTERVAL_MS)),
+            max_output_event_bytes: Arc::new(AtomicUsize::new(DEFAULT_MAX_EVENT_BYTES)),
+            usage_samples: Mutex::new(HashMap::new()),
+            pending_resizes: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -49,22 +438,251 @@ impl PtyManager {
         self.app_handle = Some(handle);
     }
 
+    /// A manager sharing this one's sessions and output options, with a copy
+    /// of its settings, for spawning sessions without holding the shared lock
+    pub fn spawner(&self) -> PtyManager {
+        Self {
+            sessions: self.sessions.clone(),
+            app_handle: self.app_handle.clone(),
+            settings: self.settings.clone(),
+            detect_terminal_queries: self.detect_terminal_queries.clone(),
+            base64_output: self.base64_output.clone(),
+            output_flush_interval_ms: self.output_flush_interval_ms.clone(),
+            max_output_event_bytes: self.max_output_event_bytes.clone(),
+            usage_samples: Mutex::new(HashMap::new()),
+            pending_resizes: self.pending_resizes.clone(),
+        }
+    }
+
+    /// Get the current backend settings
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Mutable access to the backend settings
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
+    /// Enable or disable `terminal-query` events for all sessions
+    pub fn set_terminal_query_detection(&self, enabled: bool) {
+        self.detect_terminal_queries.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Choose how `pty-output` events encode their data, for all sessions
+    pub fn set_output_encoding(&self, encoding: OutputEncoding) {
+        self.base64_output
+            .store(encoding == OutputEncoding::Base64, Ordering::Relaxed);
+    }
+
+    /// Set how long bursts of output are coalesced into one `pty-output` event; 0 sends every read
+    pub fn set_output_flush_interval(&self, ms: u64) {
+        self.output_flush_interval_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Cap the data in one `pty-output` event; `None` restores `DEFAULT_MAX_EVENT_BYTES`
+    pub fn set_max_output_event_size(&self, bytes: Option<usize>) {
+        self.max_output_event_bytes
+            .store(bytes.unwrap_or(DEFAULT_MAX_EVENT_BYTES), Ordering::Relaxed);
+    }
+
+    /// Default shell for new sessions: `$SHELL` on Unix; on Windows PowerShell 7
+    /// (`pwsh.exe`) if it's on PATH, else Windows PowerShell
+    pub fn default_shell() -> String {
+        #[cfg(unix)]
+        {
+            std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string())
+        }
+        #[cfg(not(unix))]
+        {
+            let path = std::env::var_os("PATH");
+            let has_pwsh = check_shell("pwsh.exe", &Self::default_cwd(), path.as_deref()).is_ok();
+            if has_pwsh { "pwsh.exe" } else { "powershell.exe" }.to_string()
+        }
+    }
+
+    /// Default working directory for new sessions
+    pub fn default_cwd() -> PathBuf {
+        #[cfg(unix)]
+        let fallback = "/";
+        #[cfg(not(unix))]
+        let fallback = "C:\\";
+        dirs::home_dir().unwrap_or_else(|| PathBuf::from(fallback))
+    }
+
     /// Get app handle reference
     #[allow(dead_code)]
     fn get_app_handle(&self) -> Option<&AppHandle> {
         self.app_handle.as_ref()
     }
 
-    /// Spawn a new terminal session
+    /// Look up a session's entry; the map is only locked for the lookup
+    fn session(&self, id: &str) -> Result<SessionHandle, String> {
+        self.sessions
+            .read()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("Session not found: {}", id))
+    }
+
+    /// Every session's entry, to lock one at a time
+    fn entries(&self) -> Vec<SessionHandle> {
+        self.sessions.read().values().cloned().collect()
+    }
+
+    /// Spawn the shell for a session described by `info`.
+    ///
+    /// Everything about the process (shell, cwd, command, TERM, limits) comes from
+    /// `info`, so the same call can bring a saved session back to life.
     pub fn spawn_session(
         &self,
-        id: String,
-        name: String,
-        shell: Option<String>,
-        cwd: Option<PathBuf>,
+        mut info: SessionInfo,
         rows: u16,
         cols: u16,
     ) -> Result<SessionInfo, String> {
+        self.check_session_limit()?;
+        let sinks = Arc::new(SessionSinks::new());
+        let scrollback = self.new_scrollback();
+        let output_stats = Arc::new(OutputStats::new(info.last_activity));
+        let process = self.spawn_process(
+            &mut info,
+            sinks.clone(),
+            scrollback.clone(),
+            output_stats.clone(),
+            rows,
+            cols,
+        )?;
+
+        // Spawns through `spawner` don't hold the shared lock, so two can race for an id
+        let mut sessions = self.sessions.write();
+        let Entry::Vacant(entry) = sessions.entry(info.id.clone()) else {
+            drop(sessions);
+            stop_process(process);
+            return Err(format!("Session already exists: {}", info.id));
+        };
+        entry.insert(Arc::new(Mutex::new(ActiveSession {
+            info: info.clone(),
+            process: Some(process),
+            sinks,
+            scrollback,
+            output_stats,
+            log: None,
+            recording: None,
+            live_cwd: None,
+            title: None,
+            #[cfg(unix)]
+            socket: None,
+        })));
+
+        Ok(info)
+    }
+
+    /// Bring a saved session back under its original id, at its saved size.
+    ///
+    /// If the saved cwd no longer exists the shell starts in the home directory
+    /// instead and the session is marked `Error` saying so.
+    pub fn respawn_from_info(&self, mut info: SessionInfo) -> Result<SessionInfo, String> {
+        if self.sessions.read().contains_key(&info.id) {
+            return Err(format!("Session already exists: {}", info.id));
+        }
+
+        let missing_cwd = (!info.cwd.is_dir()).then(|| std::mem::replace(&mut info.cwd, Self::default_cwd()));
+        let (rows, cols) = (info.rows, info.cols);
+        let info = self.spawn_session(info, rows, cols)?;
+        match missing_cwd {
+            Some(cwd) => {
+                let note = format!("Directory not found: {}; started in {}", cwd.display(), info.cwd.display());
+                Ok(self.set_session_status(&info.id, SessionStatus::Error(note)).unwrap_or(info))
+            }
+            None => Ok(info),
+        }
+    }
+
+    /// Register a session without spawning it; `spawn_pending_session` starts it later
+    pub fn add_pending_session(&self, mut info: SessionInfo) -> SessionInfo {
+        info.status = SessionStatus::Stopped;
+        info.pid = None;
+        let active_session = ActiveSession {
+            info: info.clone(),
+            process: None,
+            sinks: Arc::new(SessionSinks::new()),
+            scrollback: self.new_scrollback(),
+            output_stats: Arc::new(OutputStats::new(info.last_activity)),
+            log: None,
+            recording: None,
+            live_cwd: None,
+            title: None,
+            #[cfg(unix)]
+            socket: None,
+        };
+        self.sessions.write().insert(info.id.clone(), Arc::new(Mutex::new(active_session)));
+        info
+    }
+
+    /// Spawn a session registered with `add_pending_session`, using its stored config
+    pub fn spawn_pending_session(&self, id: &str, rows: u16, cols: u16) -> Result<SessionInfo, String> {
+        self.check_session_limit()?;
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        if session.process.is_some() {
+            return Err(format!("Session already spawned: {}", id));
+        }
+
+        let mut info = session.info();
+        let process = self.spawn_process(
+            &mut info,
+            session.sinks.clone(),
+            session.scrollback.clone(),
+            session.output_stats.clone(),
+            rows,
+            cols,
+        )?;
+        session.process = Some(process);
+        session.info = info.clone();
+        Ok(info)
+    }
+
+    /// Refuse another shell once `Settings::max_sessions` sessions have one.
+    /// Pending (unspawned) sessions don't count; killed ones stop counting at once.
+    fn check_session_limit(&self) -> Result<(), String> {
+        match (self.settings.max_sessions, self.free_session_slots()) {
+            (Some(max), Some(0)) => Err(format!("Session limit reached ({})", max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// How many more shells `Settings::max_sessions` allows; `None` without a cap
+    pub fn free_session_slots(&self) -> Option<usize> {
+        let max = self.settings.max_sessions?;
+        let spawned = self
+            .entries()
+            .iter()
+            .filter(|entry| entry.lock().process.is_some())
+            .count();
+        Some(max.saturating_sub(spawned))
+    }
+
+    fn new_scrollback(&self) -> Arc<Scrollback> {
+        let scrollback = Scrollback::new(self.settings.scrollback_bytes.unwrap_or(DEFAULT_SCROLLBACK_BYTES));
+        scrollback.set_max_lines(self.settings.scrollback_lines);
+        Arc::new(scrollback)
+    }
+
+    /// Open a PTY and start the session's shell, with a reader thread feeding
+    /// `sinks` and `scrollback` and counting into `output_stats`
+    fn spawn_process(
+        &self,
+        info: &mut SessionInfo,
+        sinks: Arc<SessionSinks>,
+        scrollback: Arc<Scrollback>,
+        output_stats: Arc<OutputStats>,
+        rows: u16,
+        cols: u16,
+    ) -> Result<SessionProcess, String> {
+        // portable_pty quietly falls back to $HOME for a bad cwd, so check it here
+        check_cwd(&info.cwd)?;
+
         let pty_system = native_pty_system();
 
         let size = PtySize {
@@ -78,42 +696,87 @@ impl PtyManager {
             .openpty(size)
             .map_err(|e| format!("Failed to open PTY: {}", e))?;
 
-        // Determine shell
-        let shell_path = shell.unwrap_or_else(|| {
-            std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string())
-        });
-
-        // Determine working directory
-        let working_dir = cwd.unwrap_or_else(|| {
-            dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
-        });
-
-        // Spawn as login shell to load user's profile (.zshrc, .bash_profile, etc.)
-        let mut cmd = CommandBuilder::new(&shell_path);
-        cmd.arg("-l"); // Login shell flag
-        cmd.cwd(&working_dir);
+        let program = match info.kind {
+            SessionKind::Local => info.shell.clone(),
+            SessionKind::Ssh { .. } => "ssh".to_string(),
+        };
+        let mut cmd = CommandBuilder::new(&program);
+        match &info.kind {
+            SessionKind::Local => {
+                let shell_args = info.shell_args.clone().unwrap_or_else(default_shell_args);
+                cmd.args(&shell_args);
+                if let Some(ref command) = info.command {
+                    cmd.args(command_args(&info.shell, &shell_args, command));
+                }
+            }
+            SessionKind::Ssh { host, user, port, identity_file } => {
+                cmd.args(ssh_args(host, user.as_deref(), *port, identity_file.as_deref(), info.command.as_ref()));
+            }
+        }
+        cmd.cwd(&info.cwd);
 
         // Inherit all environment variables from parent process
         for (key, value) in std::env::vars() {
             cmd.env(key, value);
         }
 
+        // Then the session's env file, so it can override inherited values
+        let mut inherited_path = std::env::var_os("PATH");
+        if let Some(ref env_file) = info.env_file {
+            for (key, value) in env_file.load(&info.cwd)? {
+                if key == "PATH" {
+                    inherited_path = Some(value.clone().into());
+                }
+                cmd.env(key, value);
+            }
+        }
+
+        // A PATH set for the session still gets the configured extra dirs
+        if let Some(path) = info.env.get("PATH") {
+            inherited_path = Some(path.into());
+        }
+
+        let path = build_path(&self.settings, inherited_path.clone());
+        if let Some(ref path) = path {
+            cmd.env("PATH", path);
+        }
+
         // Override specific terminal settings
-        cmd.env("TERM", "xterm-256color");
+        cmd.env("TERM", info.term_override.as_deref().unwrap_or(DEFAULT_TERM));
         cmd.env("COLORTERM", "truecolor");
+        #[cfg(unix)]
         cmd.env("LANG", std::env::var("LANG").unwrap_or_else(|_| "en_US.UTF-8".to_string()));
 
-        let child = pair
+        // The session's own variables win over all of the above
+        for (key, value) in info.env.iter().filter(|(key, _)| *key != "PATH") {
+            cmd.env(key, value);
+        }
+
+        // portable_pty reports a bad shell path only as text; check it for a clearer message
+        check_shell(&program, &info.cwd, path.or(inherited_path).as_deref())?;
+
+        let mut child = pair
             .slave
             .spawn_command(cmd)
-            .map_err(|e| format!("Failed to spawn shell: {}", e))?;
+            .map_err(|e| spawn_error(e.downcast_ref::<std::io::Error>(), &e.to_string(), &program))?;
 
-        let session_info = SessionInfo::new(
-            id.clone(),
-            name,
-            shell_path,
-            working_dir,
-        );
+        let id = info.id.clone();
+        info.status = SessionStatus::Running;
+        let pid = child.process_id();
+        info.pid = pid;
+        info.exit_code = None;
+        info.rows = rows;
+        info.cols = cols;
+        let killer = child.clone_killer();
+
+        // Re-apply resource limits; where they can't be enforced the session still runs
+        let mut cgroup = None;
+        if let (Some(limits), Some(pid)) = (&info.resource_limits, pid) {
+            match resource_limits::apply(&id, pid, limits) {
+                Ok(path) => cgroup = path,
+                Err(e) => eprintln!("Resource limits not applied to session {}: {}", id, e),
+            }
+        }
 
         // Get a writer for input
         let writer = pair
@@ -121,6 +784,24 @@ impl PtyManager {
             .take_writer()
             .map_err(|e| format!("Failed to get writer: {}", e))?;
 
+        // Reap the child on its own thread so its exit code is ready when the reader hits EOF
+        let exit = Arc::new(ExitWatch::default());
+        thread::spawn({
+            let exit = exit.clone();
+            let id = id.clone();
+            move || {
+                let code = match child.wait() {
+                    Ok(status) if exit.killed.load(Ordering::Relaxed) && !status.success() => KILLED_EXIT_CODE,
+                    Ok(status) => status.exit_code(),
+                    Err(e) => {
+                        eprintln!("Failed to wait for session {}: {}", id, e);
+                        return;
+                    }
+                };
+                exit.set(code);
+            }
+        });
+
         // Set up reader for output streaming
         let mut reader = pair
             .master
@@ -130,79 +811,358 @@ impl PtyManager {
         // Clone for the reader thread
         let session_id = id.clone();
         let app_handle = self.app_handle.clone();
+        let detect_queries = self.detect_terminal_queries.clone();
+        let base64_output = self.base64_output.clone();
+        let flush_interval_ms = self.output_flush_interval_ms.clone();
+        let max_event_bytes = self.max_output_event_bytes.clone();
+        let echo_probe = EchoProbe::new(&*pair.master);
+        let reader_sinks = sinks;
+        let reader_exit = exit.clone();
+        let bracketed_paste = Arc::new(AtomicBool::new(false));
+        let reader_bracketed_paste = bracketed_paste.clone();
+        let is_ssh = matches!(info.kind, SessionKind::Ssh { .. });
+        let reader_sessions = self.sessions.clone();
 
         // Spawn reader thread
         thread::spawn(move || {
-            let mut buffer = [0u8; 4096];
-            loop {
-                match reader.read(&mut buffer) {
-                    Ok(0) => {
-                        // EOF - session ended
-                        if let Some(handle) = &app_handle {
-                            let _ = handle.emit("pty-exit", PtyExit {
+            // Frontend events go out from their own thread, batched, so a chatty
+            // process doesn't flood the webview with one event per read
+            let emitter = app_handle.clone().map(|handle| {
+                let (tx, rx) = mpsc::channel::<Vec<u8>>();
+                let session_id = session_id.clone();
+                let emitter = thread::spawn(move || {
+                    let mut query_scanner = QueryScanner::new();
+                    let mut osc_scanner = OscScanner::new();
+                    // OSC payloads are decoded as text, so don't hand the scanner half a character
+                    let mut osc_utf8 = Utf8Carry::new();
+                    let mut bell_detector = BellDetector::new();
+                    let hostname = crate::persistence::current_hostname();
+                    let mut last_echo = echo_probe.state();
+
+                    // Repository lookups run on their own thread, debounced
+                    let (cwd_tx, cwd_rx) = mpsc::channel::<PathBuf>();
+                    thread::spawn({
+                        let handle = handle.clone();
+                        let session_id = session_id.clone();
+                        move || {
+                            git::watch_repo_context(cwd_rx, |context| {
+                                let (root, branch) = context.map_or((None, None), |c| (Some(c.root), c.branch));
+                                let _ = handle.emit("repo-context-changed", RepoContextChanged {
+                                    id: session_id.clone(),
+                                    root,
+                                    branch,
+                                });
+                            })
+                        }
+                    });
+
+                    output_batcher::run(rx, flush_interval_ms, |batch| {
+                        let base64 = base64_output.load(Ordering::Relaxed);
+                        for piece in output_batcher::split_events(batch, max_event_bytes.load(Ordering::Relaxed)) {
+                            let _ = handle.emit("pty-output", PtyOutput {
                                 id: session_id.clone(),
-                                code: None,
+                                data: OutputData::encode(piece, base64),
                             });
                         }
-                        break;
-                    }
-                    Ok(n) => {
-                        if let Some(handle) = &app_handle {
-                            let _ = handle.emit("pty-output", PtyOutput {
+
+                        if bell_detector.scan(batch) {
+                            let _ = handle.emit("pty-bell", PtyBell {
+                                id: session_id.clone(),
+                            });
+                        }
+
+                        if detect_queries.load(Ordering::Relaxed) {
+                            for query in query_scanner.scan(batch) {
+                                let _ = handle.emit("terminal-query", PtyTerminalQuery {
+                                    id: session_id.clone(),
+                                    query,
+                                });
+                            }
+                        } else {
+                            query_scanner.reset();
+                        }
+
+                        for event in osc_scanner.scan(&osc_utf8.push(batch)) {
+                            match event {
+                                // A directory reported from another machine (e.g. over ssh) isn't ours to open
+                                OscEvent::WorkingDirectory { host, path } => {
+                                    if is_local_host(host.as_deref(), hostname.as_deref()) {
+                                        if path.is_absolute() {
+                                            let _ = cwd_tx.send(path.clone());
+                                        }
+                                        handle.state::<SharedPtyManager>().lock().set_live_cwd(&session_id, path);
+                                    }
+                                }
+                                OscEvent::Title(title) => {
+                                    let renamed = handle
+                                        .state::<SharedPtyManager>()
+                                        .lock()
+                                        .set_title_name(&session_id, &title);
+                                    if let Some(name) = renamed {
+                                        let _ = handle.emit("session-renamed", SessionRenamed {
+                                            id: session_id.clone(),
+                                            name,
+                                        });
+                                    }
+                                }
+                            }
+                        }
+
+                        // Programs flip echo right before prompting, so check after output
+                        let echo = echo_probe.state();
+                        if echo != last_echo {
+                            last_echo = echo;
+                            let _ = handle.emit("echo-changed", PtyEchoChanged {
                                 id: session_id.clone(),
-                                data: buffer[..n].to_vec(),
+                                echo,
                             });
                         }
+                    });
+                });
+                (tx, emitter)
+            });
+
+            let mut paste_mode = PasteModeScanner::new();
+            let mut buffer = [0u8; 4096];
+            let status = loop {
+                match reader.read(&mut buffer) {
+                    // EOF - session ended
+                    Ok(0) => break SessionStatus::Stopped,
+                    Ok(n) => {
+                        if let Some(enabled) = paste_mode.scan(&buffer[..n]) {
+                            reader_bracketed_paste.store(enabled, Ordering::Relaxed);
+                        }
+                        output_stats.record(n);
+                        scrollback.push(&buffer[..n]);
+                        reader_sinks.dispatch(&buffer[..n]);
+                        if let Some((tx, _)) = &emitter {
+                            let _ = tx.send(buffer[..n].to_vec());
+                        }
                     }
                     Err(e) => {
                         eprintln!("Read error for session {}: {}", session_id, e);
-                        break;
+                        break SessionStatus::Error(format!("Read error: {}", e));
+                    }
+                }
+            };
+
+            // Let the final output reach the frontend before the exit event
+            if let Some((tx, emitter)) = emitter {
+                drop(tx);
+                let _ = emitter.join();
+            }
+
+            let code = reader_exit.wait_timeout(EXIT_STATUS_TIMEOUT);
+            // ssh exits 255 when it can't connect; its stderr went to the PTY
+            let status = match status {
+                SessionStatus::Stopped if is_ssh && code == Some(SSH_ERROR_EXIT_CODE) => {
+                    SessionStatus::Error(ssh_error_message(&scrollback.tail(SSH_ERROR_TAIL_BYTES)))
+                }
+                status => status,
+            };
+            // Record how the session ended before anyone hears about it; `None`
+            // if it has been killed and removed
+            let entry = reader_sessions.read().get(&session_id).cloned();
+            let info = match entry {
+                Some(entry) => {
+                    let mut session = entry.lock();
+                    // `finish_restart` already put a new shell in its place
+                    if !session.process.as_ref().is_some_and(|process| Arc::ptr_eq(&process.exit, &reader_exit)) {
+                        return;
                     }
+                    session.info.status = status;
+                    session.info.pid = None;
+                    session.info.exit_code = code;
+                    Some(session.info())
+                }
+                None => None,
+            };
+
+            if let Some(handle) = &app_handle {
+                let manager = handle.state::<SharedPtyManager>();
+                let _ = handle.emit("pty-exit", PtyExit {
+                    id: session_id.clone(),
+                    code,
+                });
+
+                // Scratch sessions go away entirely once their shell exits
+                if info.as_ref().is_some_and(|info| info.ephemeral) {
+                    let _ = manager.lock().kill_session(&session_id);
+                }
+                // Don't leave the UI focused on a dead session
+                if let Some(info) = info {
+                    crate::commands::reconcile_active_session(handle, &info);
                 }
             }
         });
 
-        let active_session = ActiveSession {
-            info: session_info.clone(),
+        let writer: SharedWriter = Arc::new(Mutex::new(writer));
+        let input_pacer = info
+            .input_rate_limit
+            .map(|rate| InputPacer::new(writer.clone(), rate));
+
+        Ok(SessionProcess {
             master: pair.master,
-            child,
+            killer,
+            pid,
+            exit,
             writer,
-        };
-
-        self.sessions.lock().insert(id, active_session);
-
-        Ok(session_info)
+            bracketed_paste,
+            input_pacer,
+            cgroup,
+        })
     }
 
     /// Write input data to a session
     pub fn write_to_session(&self, id: &str, data: &[u8]) -> Result<(), String> {
-        let mut sessions = self.sessions.lock();
-        let session = sessions
-            .get_mut(id)
-            .ok_or_else(|| format!("Session not found: {}", id))?;
+        self.write_chunks_to_session(id, &[data])
+    }
 
-        session
-            .writer
-            .write_all(data)
-            .map_err(|e| format!("Write error: {}", e))?;
+    /// Write several chunks (e.g. a large paste) in order, flushing once at the end
+    pub fn write_chunks_to_session(&self, id: &str, chunks: &[&[u8]]) -> Result<(), String> {
+        self.session_input(id)?.write(chunks)
+    }
 
-        session
-            .writer
-            .flush()
-            .map_err(|e| format!("Flush error: {}", e))?;
+    /// A running session's input path, to write through after letting go of the manager.
+    ///
+    /// Writes can block while the shell isn't reading, so callers that hold the
+    /// shared manager lock should take this and drop the lock before writing;
+    /// writes to one session then never wait on another's.
+    pub fn session_input(&self, id: &str) -> Result<SessionInput, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
 
-        Ok(())
+        // Don't write into a dead (or not yet spawned) PTY; callers can offer a restart instead
+        let process = match &session.process {
+            Some(process) if process.exit.get().is_none() => process,
+            _ => return Err(format!("Session not running: {}", id)),
+        };
+
+        Ok(SessionInput {
+            writer: process.writer.clone(),
+            pacer: process.input_pacer.as_ref().map(InputPacer::queue),
+            bracketed_paste: process.bracketed_paste.clone(),
+        })
     }
 
-    /// Resize a session's PTY
-    pub fn resize_session(&self, id: &str, rows: u16, cols: u16) -> Result<(), String> {
-        let sessions = self.sessions.lock();
-        let session = sessions
-            .get(id)
-            .ok_or_else(|| format!("Session not found: {}", id))?;
+    /// Set or clear a session's input rate limit (bytes per second)
+    pub fn set_input_rate_limit(&self, id: &str, bytes_per_sec: Option<u64>) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
 
-        session
-            .master
+        // A lazy session picks the limit up when it's spawned
+        if let Some(process) = &mut session.process {
+            match bytes_per_sec {
+                Some(rate) => match &process.input_pacer {
+                    Some(pacer) => pacer.set_rate(rate),
+                    None => {
+                        process.input_pacer = Some(InputPacer::new(process.writer.clone(), rate));
+                    }
+                },
+                None => {
+                    if let Some(pacer) = process.input_pacer.take() {
+                        // Flush anything still queued so no input is lost
+                        let pending = pacer.stop();
+                        if !pending.is_empty() {
+                            write_and_flush(&process.writer, &pending)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        session.info.input_rate_limit = bytes_per_sec;
+        Ok(())
+    }
+
+    /// Get input pacing statistics for a session
+    pub fn get_input_pacing(&self, id: &str) -> Result<InputPacingStats, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+
+        Ok(match session.process.as_ref().and_then(|p| p.input_pacer.as_ref()) {
+            Some(pacer) => pacer.stats(),
+            None => InputPacingStats {
+                limit_bytes_per_sec: None,
+                pending_bytes: 0,
+                paced_bytes_total: 0,
+                active: false,
+            },
+        })
+    }
+
+    /// Drop a session's queued-but-unwritten input; returns the number of bytes dropped
+    pub fn discard_pending_input(&self, id: &str) -> Result<usize, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+
+        // Only paced sessions queue input; everything else is written immediately
+        Ok(session
+            .process
+            .as_ref()
+            .and_then(|p| p.input_pacer.as_ref())
+            .map_or(0, |pacer| pacer.discard()))
+    }
+
+    /// Resize a session's PTY.
+    ///
+    /// A resize after a quiet spell is applied at once; further ones within
+    /// `RESIZE_DEBOUNCE` of it are held, and only the last size is applied when
+    /// the window ends, so dragging a divider doesn't issue an ioctl per pixel.
+    pub fn resize_session(&self, id: &str, rows: u16, cols: u16) -> Result<(), String> {
+        if !self.sessions.read().contains_key(id) {
+            return Err(format!("Session not found: {}", id));
+        }
+        let Some(handle) = self.app_handle.clone() else {
+            return self.apply_resize(id, rows, cols);
+        };
+
+        {
+            let mut pending = self.pending_resizes.lock();
+            if let Some(size) = pending.get_mut(id) {
+                *size = Some((rows, cols));
+                return Ok(());
+            }
+            pending.insert(id.to_string(), None);
+        }
+        let result = self.apply_resize(id, rows, cols);
+
+        // Apply whatever arrives during the window, until a window passes with nothing new
+        let pending = self.pending_resizes.clone();
+        let id = id.to_string();
+        thread::spawn(move || loop {
+            thread::sleep(RESIZE_DEBOUNCE);
+            let size = {
+                let mut pending = pending.lock();
+                match pending.get_mut(&id).and_then(Option::take) {
+                    Some(size) => size,
+                    None => {
+                        pending.remove(&id);
+                        return;
+                    }
+                }
+            };
+            let (rows, cols) = size;
+            if let Err(e) = handle.state::<SharedPtyManager>().lock().apply_resize(&id, rows, cols) {
+                eprintln!("Deferred resize for session {} failed: {}", id, e);
+            }
+        });
+        result
+    }
+
+    fn apply_resize(&self, id: &str, rows: u16, cols: u16) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+        session.info.rows = rows;
+        session.info.cols = cols;
+
+        // A lazy session gets its size when it's spawned
+        let Some(process) = &session.process else {
+            return Ok(());
+        };
+
+        process
+            .master
             .resize(PtySize {
                 rows,
                 cols,
@@ -211,85 +1171,1337 @@ impl PtyManager {
             })
             .map_err(|e| format!("Resize error: {}", e))?;
 
+        if let Some(recording) = &session.recording {
+            recording.lock().resize(cols, rows);
+        }
         Ok(())
     }
 
+    /// Tee a session's raw output to an extra sink until `untap_session` is called
+    pub fn tap_session(&self, id: &str, target: TapTarget) -> Result<TapInfo, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+
+        let tap_id = uuid::Uuid::new_v4().to_string();
+        let (sink, path): (Box<dyn OutputSink>, Option<PathBuf>) = match target {
+            TapTarget::File => {
+                let path = std::env::temp_dir().join(format!("shelltree-tap-{}.log", tap_id));
+                let file = std::fs::File::create(&path)
+                    .map_err(|e| format!("Failed to create tap file: {}", e))?;
+                (Box::new(file), Some(path))
+            }
+            TapTarget::Event => {
+                let handle = self
+                    .app_handle
+                    .clone()
+                    .ok_or_else(|| "Event taps need the app handle".to_string())?;
+                let sink = EventTap {
+                    handle,
+                    tap_id: tap_id.clone(),
+                    session_id: id.to_string(),
+                };
+                (Box::new(sink), None)
+            }
+        };
+
+        session.sinks.add(tap_id.clone(), sink);
+        Ok(TapInfo {
+            tap_id,
+            session_id: id.to_string(),
+            path,
+        })
+    }
+
+    /// Remove a tap registered with `tap_session`
+    pub fn untap_session(&self, tap_id: &str) -> Result<(), String> {
+        if self.entries().iter().any(|entry| entry.lock().sinks.remove(tap_id)) {
+            Ok(())
+        } else {
+            Err(format!("Tap not found: {}", tap_id))
+        }
+    }
+
+    /// Append a session's raw output to `path` until `stop_logging` is called.
+    ///
+    /// A write error stops logging and emits `session-log-error`; the session
+    /// itself carries on.
+    pub fn start_logging(&self, id: &str, path: PathBuf) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        let sink_id = log_sink_id(id);
+        if let Some(current) = &session.log {
+            // A log that failed already lost its sink and can be replaced
+            if session.sinks.contains(&sink_id) {
+                return Err(format!("Session already logging to {}", current.lock().path().display()));
+            }
+        }
+
+        let (max_bytes, max_files) = self.log_limits();
+        let log = SessionLog::open(path, max_bytes, max_files)
+            .map_err(|e| format!("Failed to open log file: {}", e))?;
+        let log = Arc::new(Mutex::new(log));
+        let sink = LogSink {
+            log: log.clone(),
+            session_id: id.to_string(),
+            handle: self.app_handle.clone(),
+        };
+        session.sinks.add(sink_id, Box::new(sink));
+        session.log = Some(log);
+        Ok(())
+    }
+
+    /// Stop appending a session's output to its log file
+    pub fn stop_logging(&self, id: &str) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session
+            .log
+            .take()
+            .ok_or_else(|| format!("Session is not logging: {}", id))?;
+        session.sinks.remove(&log_sink_id(id));
+        Ok(())
+    }
+
+    /// Rotate a session's log file now, whatever its size
+    pub fn rotate_session_log(&self, id: &str) -> Result<(), String> {
+        let log = {
+            let entry = self.session(id)?;
+            let session = entry.lock();
+            session
+                .log
+                .clone()
+                .ok_or_else(|| format!("Session is not logging: {}", id))?
+        };
+        let mut log = log.lock();
+        log.rotate().map_err(|e| format!("Failed to rotate log {}: {}", log.path().display(), e))
+    }
+
+    /// Set when session logs rotate and how many old ones are kept, for
+    /// current and future logs
+    pub fn set_log_rotation(&mut self, max_bytes: Option<u64>, max_files: Option<usize>) {
+        self.settings.log_max_bytes = max_bytes;
+        self.settings.log_max_files = max_files;
+        let (max_bytes, max_files) = self.log_limits();
+        for entry in self.entries() {
+            if let Some(log) = &entry.lock().log {
+                log.lock().set_limits(max_bytes, max_files);
+            }
+        }
+    }
+
+    fn log_limits(&self) -> (Option<u64>, usize) {
+        (
+            self.settings.log_max_bytes,
+            self.settings.log_max_files.unwrap_or(DEFAULT_LOG_MAX_FILES),
+        )
+    }
+
+    /// Start capturing a session's output, with timings, for an asciinema cast
+    pub fn start_recording(&self, id: &str) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        if session.recording.is_some() {
+            return Err(format!("Session already recording: {}", id));
+        }
+        let process = session
+            .process
+            .as_ref()
+            .ok_or_else(|| format!("Session not running: {}", id))?;
+        let size = process
+            .master
+            .get_size()
+            .map_err(|e| format!("Failed to get terminal size: {}", e))?;
+
+        let term = session.info.term_override.as_deref().unwrap_or(DEFAULT_TERM);
+        let recording = Arc::new(Mutex::new(Recording::new(size.cols, size.rows, &session.info.shell, term)));
+        session
+            .sinks
+            .add(recording_sink_id(id), Box::new(RecordingSink(recording.clone())));
+        session.recording = Some(recording);
+        Ok(())
+    }
+
+    /// Stop recording a session and write what was captured to `path` as an asciinema v2 cast
+    pub fn stop_recording(&self, id: &str, path: &std::path::Path) -> Result<(), String> {
+        let recording = {
+            let entry = self.session(id)?;
+            let mut session = entry.lock();
+            let recording = session
+                .recording
+                .take()
+                .ok_or_else(|| format!("Session is not recording: {}", id))?;
+            session.sinks.remove(&recording_sink_id(id));
+            recording
+        };
+
+        let recording = recording.lock();
+        recording
+            .write_cast(path)
+            .map_err(|e| format!("Failed to write recording: {}", e))
+    }
+
+    /// Render a session's retained output, colors included, to an HTML page at `path`
+    pub fn export_session_html(&self, id: &str, path: &std::path::Path, theme: HtmlTheme) -> Result<(), String> {
+        let (output, name) = {
+            let entry = self.session(id)?;
+            let session = entry.lock();
+            (session.scrollback.contents(), session.info().name)
+        };
+        let html = html_export::render_html(&output, &name, theme);
+        std::fs::write(path, html).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Serve a session's live output to clients of a Unix socket at `path`
+    #[cfg(unix)]
+    pub fn attach_session_socket(&self, id: &str, path: PathBuf) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        if let Some(socket) = &session.socket {
+            return Err(format!("Session already has a socket at {}", socket.path().display()));
+        }
+
+        let sink_id = format!("socket-{}", id);
+        let (socket, sink) = SessionSocket::bind(path, sink_id.clone())?;
+        session.sinks.add(sink_id, Box::new(sink));
+        session.socket = Some(socket);
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn attach_session_socket(&self, _id: &str, _path: PathBuf) -> Result<(), String> {
+        Err("Unix sockets are not supported on this platform".to_string())
+    }
+
+    /// Stop serving a session's output and remove its socket file
+    #[cfg(unix)]
+    pub fn detach_session_socket(&self, id: &str) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        let socket = session
+            .socket
+            .take()
+            .ok_or_else(|| format!("Session has no socket: {}", id))?;
+        session.sinks.remove(socket.sink_id());
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn detach_session_socket(&self, _id: &str) -> Result<(), String> {
+        Err("Unix sockets are not supported on this platform".to_string())
+    }
+
+    /// Have a session's shell print a unique sentinel and watch the output for it.
+    ///
+    /// The sentinel is an OSC sequence terminals ignore, so only the shell's
+    /// real output can match (the echoed command shows it as plain text). The
+    /// same printf moves up and erases the echoed command line to keep it out of
+    /// the scrollback. Needs the shell itself at the prompt.
+    pub fn start_ping(&self, id: &str) -> Result<PendingPing, String> {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let sink_id = format!("ping-{}", token);
+        let entry = self.session(id)?;
+        let session = entry.lock();
+
+        let spawned = session
+            .process
+            .as_ref()
+            .ok_or_else(|| format!("Session not running: {}", id))?;
+
+        // Anything else in the foreground would receive the printf as input
+        if let (Some(fg), Some(shell)) = (process::foreground_pid(&*spawned.master), spawned.pid) {
+            if fg != shell {
+                return Err(format!("Session is busy: {}", id));
+            }
+        }
+
+        let needle = format!("\x1b]777;shelltree-ping;{}\x07", token).into_bytes();
+        let (sink, received) = SentinelSink::new(needle);
+        session.sinks.add(sink_id.clone(), Box::new(sink));
+        drop(session);
+
+        // Leading space keeps it out of shell history where ignorespace is set
+        let probe = format!(" printf '\\033[1A\\r\\033[2K\\033]777;shelltree-ping;%s\\007' {}\n", token);
+        let sent_at = Instant::now();
+        if let Err(e) = self.write_to_session(id, probe.as_bytes()) {
+            let _ = self.untap_session(&sink_id);
+            return Err(e);
+        }
+
+        Ok(PendingPing {
+            sink_id,
+            sent_at,
+            received,
+        })
+    }
+
+    /// Restore sane terminal state without touching the shell: reset the frontend
+    /// terminal's modes and the PTY's line discipline, then have the shell redraw.
+    /// Returns whether the line discipline could be reset.
+    pub fn reset_session(&self, id: &str) -> Result<bool, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+        let process = session
+            .process
+            .as_ref()
+            .ok_or_else(|| format!("Session not running: {}", id))?;
+
+        let termios_reset = reset_termios(&*process.master)?;
+        // The reset sequence turns bracketed paste off without passing through the reader
+        process.bracketed_paste.store(false, Ordering::Relaxed);
+
+        // The sequence goes to the terminal, as if the program had printed it
+        session.sinks.dispatch(TERMINAL_RESET_SEQUENCE);
+        if let Some(handle) = &self.app_handle {
+            let _ = handle.emit("pty-output", PtyOutput {
+                id: id.to_string(),
+                data: OutputData::encode(TERMINAL_RESET_SEQUENCE, self.base64_output.load(Ordering::Relaxed)),
+            });
+        }
+
+        // Ctrl-L makes an idle shell redraw its prompt on the cleared screen;
+        // anything else in the foreground shouldn't get stray input
+        let shell_in_foreground = match (process::foreground_pid(&*process.master), process.pid) {
+            (Some(fg), Some(shell)) => fg == shell,
+            _ => false,
+        };
+        if shell_in_foreground {
+            write_and_flush(&process.writer, b"\x0c")?;
+        }
+
+        Ok(termios_reset)
+    }
+
+    /// Get whether a session's PTY currently echoes input
+    pub fn get_session_echo(&self, id: &str) -> Result<EchoState, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+
+        Ok(match &session.process {
+            Some(process) => echo_state(&*process.master),
+            None => EchoState::Unknown,
+        })
+    }
+
+    /// Get the program currently in the foreground of a session, if known
+    pub fn get_foreground_process(&self, id: &str) -> Result<Option<ForegroundProcess>, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+
+        Ok(foreground_process(&session))
+    }
+
+    /// All processes descended from a session's shell
+    pub fn get_session_process_tree(&self, id: &str) -> Result<Vec<ProcessNode>, String> {
+        let pid = {
+            let entry = self.session(id)?;
+            let session = entry.lock();
+            session.process.as_ref().and_then(|p| p.pid)
+        };
+
+        // Walk the process table without holding the sessions lock
+        Ok(pid.map(process::descendants).unwrap_or_default())
+    }
+
+    /// CPU and memory used by a session's shell and its descendants; `None` where unsupported
+    pub fn get_session_resource_usage(&self, id: &str) -> Result<Option<ResourceUsage>, String> {
+        let pid = {
+            let entry = self.session(id)?;
+            let session = entry.lock();
+            session.process.as_ref().and_then(|p| p.pid)
+        };
+        let Some(pid) = pid else {
+            return Ok(None);
+        };
+
+        let previous = match self.usage_samples.lock().get(id) {
+            Some(sample) if sample.taken_at.elapsed() < USAGE_CACHE_TTL => {
+                return Ok(Some(sample.usage.clone()));
+            }
+            Some(sample) => Some((sample.taken_at, sample.cpu_seconds)),
+            None => None,
+        };
+
+        // Walk the process table without holding any lock
+        let Some(tree) = process::sample_tree(pid) else {
+            return Ok(None);
+        };
+        let taken_at = Instant::now();
+        let cpu_percent = previous.map(|(at, cpu_seconds)| {
+            let elapsed = taken_at.duration_since(at).as_secs_f64();
+            // Exited children take their CPU time with them, so the total can drop
+            ((tree.cpu_seconds - cpu_seconds) / elapsed * 100.0).max(0.0)
+        });
+        let usage = ResourceUsage {
+            cpu_percent,
+            rss_bytes: tree.rss_bytes,
+            process_count: tree.processes,
+        };
+
+        self.usage_samples.lock().insert(
+            id.to_string(),
+            UsageSample {
+                taken_at,
+                cpu_seconds: tree.cpu_seconds,
+                usage: usage.clone(),
+            },
+        );
+        Ok(Some(usage))
+    }
+
+    /// Ids of sessions whose foreground process basename matches `name` (case-insensitive)
+    pub fn find_sessions_by_process(&self, name: &str) -> Vec<String> {
+        let wanted = process::basename(name.trim());
+        self.entries()
+            .iter()
+            .map(|entry| entry.lock())
+            .filter(|session| {
+                foreground_process(session).is_some_and(|p| p.name.eq_ignore_ascii_case(wanted))
+            })
+            .map(|session| session.info.id.clone())
+            .collect()
+    }
+
+    /// Send a signal to whatever is running in a session, leaving the session itself up.
+    ///
+    /// Like Ctrl-C, the signal goes to the PTY's foreground process group, so a
+    /// running command gets it rather than the shell waiting on it.
+    pub fn signal_session(&self, id: &str, signal: Signal) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+        let process = match &session.process {
+            Some(process) if process.exit.get().is_none() => process,
+            _ => return Err(format!("Session not running: {}", id)),
+        };
+        let pgid = process::foreground_pid(&*process.master)
+            .or(process.pid)
+            .ok_or_else(|| format!("No process to signal in session: {}", id))?;
+        process::signal_process_group(pgid, signal)
+    }
+
+    /// Send end-of-file (Ctrl-D) through the session's input, e.g. to finish
+    /// feeding `cat` or leave a REPL. Like typing it, this only ends input at
+    /// the start of a line.
+    #[allow(dead_code)]
+    pub fn send_eof(&self, id: &str) -> Result<(), String> {
+        self.session_input(id)?.send_eof()
+    }
+
+    /// Ids of the sessions in group `group_id` in tab order; ungrouped sessions never match
+    pub fn find_sessions_in_group(&self, group_id: &str) -> Vec<String> {
+        ordered_siblings(&self.sessions.read(), Some(group_id))
+    }
+
+    pub fn get_session_metrics(&self, id: &str) -> Result<SessionMetrics, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+        Ok(session_metrics(id, &session.output_stats))
+    }
+
+    /// Output metrics for every session
+    pub fn get_all_metrics(&self) -> Vec<SessionMetrics> {
+        self.entries()
+            .iter()
+            .map(|entry| {
+                let session = entry.lock();
+                session_metrics(&session.info.id, &session.output_stats)
+            })
+            .collect()
+    }
+
+    /// Ids of the sessions that have produced no output for more than `idle_ms`
+    pub fn get_idle_sessions(&self, idle_ms: u64) -> Vec<String> {
+        let cutoff = chrono::Utc::now().timestamp_millis() - idle_ms as i64;
+        self.entries()
+            .iter()
+            .map(|entry| entry.lock())
+            .filter(|session| session.output_stats.last_activity() < cutoff)
+            .map(|session| session.info.id.clone())
+            .collect()
+    }
+
     /// Kill and remove a session
     pub fn kill_session(&self, id: &str) -> Result<(), String> {
-        let mut sessions = self.sessions.lock();
-        if let Some(mut session) = sessions.remove(id) {
-            // Kill the child process
-            let _ = session.child.kill();
+        self.usage_samples.lock().remove(id);
+        let entry = self.sessions.write().remove(id);
+        if let Some(process) = entry.and_then(|entry| entry.lock().process.take()) {
+            stop_process(process);
+        }
+        Ok(())
+    }
+
+    /// Stop every session's shell and forget all sessions, e.g. when the app quits.
+    ///
+    /// Shells get SIGHUP and SIGTERM (interactive shells ignore SIGTERM but
+    /// exit on SIGHUP) and `SHUTDOWN_GRACE` to exit; any still running after
+    /// that are sent SIGKILL. Reader threads end on their own once the PTYs close.
+    pub fn shutdown_all(&self) {
+        self.usage_samples.lock().clear();
+        let mut processes: Vec<SessionProcess> = self
+            .sessions
+            .write()
+            .drain()
+            .filter_map(|(_, entry)| entry.lock().process.take())
+            .collect();
+
+        for process in &mut processes {
+            process.exit.killed.store(true, Ordering::Relaxed);
+            let _ = process.killer.kill();
+            if let Some(pid) = process.pid {
+                let _ = process::signal_process_group(pid, Signal::Terminate);
+            }
+        }
+
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        for process in &mut processes {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if process.exit.wait_timeout(remaining).is_none() {
+                if let Some(pid) = process.pid {
+                    let _ = process::signal_process_group(pid, Signal::Kill);
+                }
+            }
+            if let Some(cgroup) = process.cgroup.take() {
+                resource_limits::release(&cgroup);
+            }
+        }
+    }
+
+    /// Start restarting a session (running, exited or never spawned): detach its
+    /// shell, to be stopped with `PendingRestart::stop_old` and replaced by
+    /// `finish_restart`.
+    ///
+    /// Stopping waits up to `EXIT_STATUS_TIMEOUT` for the old shell to exit, so
+    /// callers holding the shared manager lock should drop it in between.
+    pub fn begin_restart(&self, id: &str) -> Result<PendingRestart, String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+        let old = session.process.take();
+        let (rows, cols) = old
+            .as_ref()
+            .and_then(|process| process.master.get_size().ok())
+            .map_or((session.info.rows, session.info.cols), |size| (size.rows, size.cols));
+        let restart = PendingRestart {
+            old,
+            info: session.info.clone(),
+            sinks: session.sinks.clone(),
+            scrollback: session.scrollback.clone(),
+            output_stats: session.output_stats.clone(),
+            rows,
+            cols,
+        };
+        drop(session);
+        self.usage_samples.lock().remove(id);
+        Ok(restart)
+    }
+
+    /// Give a session detached by `begin_restart` a fresh shell, keeping its id,
+    /// name, group, order and config; the PTY keeps its size. Fails if the
+    /// session was killed in the meantime.
+    pub fn finish_restart(&self, mut restart: PendingRestart) -> Result<SessionInfo, String> {
+        // A no-op unless the caller skipped it
+        restart.stop_old();
+        let PendingRestart {
+            mut info,
+            sinks,
+            scrollback,
+            output_stats,
+            rows,
+            cols,
+            ..
+        } = restart;
+        let entry = self.session(&info.id)?;
+        let process = self.spawn_process(&mut info, sinks, scrollback, output_stats, rows, cols);
+        let mut session = entry.lock();
+        match process {
+            Ok(process) => {
+                // A concurrent restart of the same session got there first
+                if let Some(displaced) = session.process.replace(process) {
+                    stop_process(displaced);
+                }
+                session.info = info.clone();
+                // The new shell starts back in the configured cwd, untitled
+                session.live_cwd = None;
+                session.title = None;
+            }
+            Err(e) => {
+                session.info.status = SessionStatus::Error(e.clone());
+                session.info.pid = None;
+                return Err(e);
+            }
+        }
+        drop(session);
+
+        if let Some(handle) = &self.app_handle {
+            let _ = handle.emit("session-restarted", info.clone());
+        }
+        Ok(info)
+    }
+
+    /// The session's retained output, oldest first
+    pub fn get_session_scrollback(&self, id: &str) -> Result<Vec<u8>, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+        Ok(session.scrollback.contents())
+    }
+
+    /// Replace the retained output of a session that isn't running, e.g. to show
+    /// a restored session's saved output before it's respawned. The output is
+    /// followed by an SGR reset and, unless it ends a line, a newline, so live
+    /// output after a respawn starts on a clean line.
+    pub fn set_session_output(&self, id: &str, data: &[u8]) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+        if session.process.as_ref().is_some_and(|process| process.exit.get().is_none()) {
+            return Err(format!("Session is running: {}", id));
+        }
+        session.scrollback.clear();
+        session.scrollback.push(data);
+        session.scrollback.push(b"\x1b[0m");
+        if !data.is_empty() && !data.ends_with(b"\n") {
+            session.scrollback.push(b"\r\n");
+        }
+        Ok(())
+    }
+
+    /// A session's scrollback, for waiting on its output without holding the lock
+    pub fn session_scrollback(&self, id: &str) -> Result<Arc<Scrollback>, String> {
+        let entry = self.session(id)?;
+        let scrollback = entry.lock().scrollback.clone();
+        Ok(scrollback)
+    }
+
+    /// The newest `count` lines of a session's retained output as plain text,
+    /// with carriage-return redraws collapsed
+    pub fn get_session_lines(&self, id: &str, count: usize) -> Result<Vec<String>, String> {
+        let entry = self.session(id)?;
+        let session = entry.lock();
+        Ok(session.scrollback.lines(count))
+    }
+
+    /// Wipe a session's retained output and clear its terminal: erase saved
+    /// lines, home the cursor, erase the screen. Works on exited sessions too.
+    ///
+    /// The clear sequence goes out as output, the way `clear` would print it;
+    /// written to the shell's input it would just be typed at the prompt.
+    pub fn clear_scrollback(&self, id: &str) -> Result<(), String> {
+        const CLEAR_SEQUENCE: &[u8] = b"\x1b[3J\x1b[H\x1b[2J";
+
+        let entry = self.session(id)?;
+        let session = entry.lock();
+        session.scrollback.clear();
+        drop(session);
+
+        if let Some(handle) = &self.app_handle {
+            let _ = handle.emit("pty-output", PtyOutput {
+                id: id.to_string(),
+                data: OutputData::encode(CLEAR_SEQUENCE, self.base64_output.load(Ordering::Relaxed)),
+            });
+            let _ = handle.emit("session-cleared", SessionCleared { id: id.to_string() });
         }
         Ok(())
     }
 
+    /// Every session's name and scrollback, for searching without holding the lock
+    pub fn search_targets(&self) -> Vec<SearchTarget> {
+        self.entries()
+            .iter()
+            .map(|entry| {
+                let session = entry.lock();
+                SearchTarget {
+                    id: session.info.id.clone(),
+                    name: session.info.name.clone(),
+                    scrollback: session.scrollback.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Change how much output is retained per session, for existing and new sessions
+    pub fn set_scrollback_limit(&mut self, bytes: Option<usize>) {
+        self.settings.scrollback_bytes = bytes;
+        let capacity = bytes.unwrap_or(DEFAULT_SCROLLBACK_BYTES);
+        for entry in self.entries() {
+            entry.lock().scrollback.set_capacity(capacity);
+        }
+    }
+
+    /// Change how many lines of output are retained per session; `None` removes the line cap
+    pub fn set_scrollback_line_limit(&mut self, lines: Option<usize>) {
+        self.settings.scrollback_lines = lines;
+        for entry in self.entries() {
+            entry.lock().scrollback.set_max_lines(lines);
+        }
+    }
+
+    /// Cap how many sessions can have a shell at once; `None` removes the cap.
+    /// Sessions already over a lowered cap keep running.
+    pub fn set_max_sessions(&mut self, max: Option<usize>) {
+        self.settings.max_sessions = max;
+    }
+
+    /// Check whether a session's child process has exited (or the session is gone)
+    pub fn has_child_exited(&self, id: &str) -> bool {
+        let Ok(entry) = self.session(id) else {
+            return true;
+        };
+        let session = entry.lock();
+        match &session.process {
+            Some(process) => process.exit.get().is_some(),
+            None => true,
+        }
+    }
+
+    /// Update a session's status; returns the updated info, or `None` if the session is gone
+    fn set_session_status(&self, id: &str, status: SessionStatus) -> Option<SessionInfo> {
+        let entry = self.session(id).ok()?;
+        let mut session = entry.lock();
+        session.info.status = status;
+        Some(session.info())
+    }
+
+    /// Track the directory the shell reports it has moved to (OSC 7).
+    /// The configured cwd is kept for the layout.
+    fn set_live_cwd(&self, id: &str, cwd: PathBuf) {
+        if let Ok(entry) = self.session(id) {
+            if cwd.is_absolute() {
+                entry.lock().live_cwd = Some(cwd);
+            }
+        }
+    }
+
+    /// Name a session after its terminal title unless the user has named it;
+    /// returns the new name if it changed
+    fn set_title_name(&self, id: &str, title: &str) -> Option<String> {
+        let title = title.trim();
+        let entry = self.session(id).ok()?;
+        let mut session = entry.lock();
+        if session.info.name_is_custom || title.is_empty() || session.info().name == title {
+            return None;
+        }
+        session.title = Some(title.to_string());
+        session.title.clone()
+    }
+
     /// Get session info
     pub fn get_session_info(&self, id: &str) -> Option<SessionInfo> {
-        self.sessions.lock().get(id).map(|s| s.info.clone())
+        self.session(id).ok().map(|entry| entry.lock().info())
     }
 
     /// Get all session infos
     pub fn get_all_sessions(&self) -> Vec<SessionInfo> {
-        self.sessions
-            .lock()
-            .values()
-            .map(|s| s.info.clone())
-            .collect()
+        self.entries().iter().map(|entry| entry.lock().info()).collect()
+    }
+
+    /// Get all session infos as they should be saved: configured cwd and
+    /// name, with runtime state (status, pid, exit code, activity) reset
+    pub fn get_layout_sessions(&self) -> Vec<SessionInfo> {
+        self.entries().iter().map(|entry| entry.lock().layout_info()).collect()
     }
 
     /// Update session name
     pub fn rename_session(&self, id: &str, name: String) -> Result<(), String> {
-        let mut sessions = self.sessions.lock();
-        let session = sessions
-            .get_mut(id)
-            .ok_or_else(|| format!("Session not found: {}", id))?;
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
 
         session.info.name = name;
+        session.info.name_is_custom = true;
+        session.title = None;
         Ok(())
     }
 
     /// Update session group
     pub fn set_session_group(&self, id: &str, group_id: Option<String>) -> Result<(), String> {
-        let mut sessions = self.sessions.lock();
-        let session = sessions
-            .get_mut(id)
+        // Held for reading throughout so no session joins or leaves mid-renumber
+        let sessions = self.sessions.read();
+        let entry = sessions
+            .get(id)
             .ok_or_else(|| format!("Session not found: {}", id))?;
+        let old_group = entry.lock().info.group_id.clone();
+        if old_group == group_id {
+            return Ok(());
+        }
+
+        // Join the new group at the end and close the gap left in the old one
+        let order = next_order(&sessions, group_id.as_deref());
+        {
+            let mut session = entry.lock();
+            session.info.group_id = group_id;
+            session.info.order = order;
+        }
+        let siblings = ordered_siblings(&sessions, old_group.as_deref());
+        renumber(&sessions, &siblings);
+        Ok(())
+    }
+
+    /// Move every session in group `from` to the end of group `to`, keeping their
+    /// relative order; returns the moved ids in that order
+    pub fn move_group_sessions(&self, from: &str, to: &str) -> Vec<String> {
+        if from == to {
+            return Vec::new();
+        }
+        let sessions = self.sessions.read();
+        let moved = ordered_siblings(&sessions, Some(from));
+        let start = next_order(&sessions, Some(to));
+        for (index, id) in moved.iter().enumerate() {
+            if let Some(entry) = sessions.get(id) {
+                let mut session = entry.lock();
+                session.info.group_id = Some(to.to_string());
+                session.info.order = start + index as i32;
+            }
+        }
+        moved
+    }
+
+    /// Place a session directly after `after` in their shared group
+    pub fn move_session_after(&self, id: &str, after: &str) -> Result<(), String> {
+        let sessions = self.sessions.read();
+        let group_id = sessions
+            .get(after)
+            .ok_or_else(|| format!("Session not found: {}", after))?
+            .lock()
+            .info
+            .group_id
+            .clone();
+        if sessions.get(id).map(|entry| entry.lock().info.group_id.clone()) != Some(group_id.clone()) {
+            return Err(format!("Session {} is not in the same group as {}", id, after));
+        }
+
+        let mut siblings = ordered_siblings(&sessions, group_id.as_deref());
+        siblings.retain(|sibling| sibling != id);
+        let index = siblings.iter().position(|sibling| sibling == after).map_or(siblings.len(), |i| i + 1);
+        siblings.insert(index, id.to_string());
+        renumber(&sessions, &siblings);
+        Ok(())
+    }
+
+    /// Order for a session appended to `group_id`
+    pub fn next_session_order(&self, group_id: Option<&str>) -> i32 {
+        next_order(&self.sessions.read(), group_id)
+    }
+
+    /// Move a session to position `new_order` within its group, renumbering its
+    /// siblings to stay contiguous; returns the group's sessions in their new order
+    pub fn reorder_session(&self, id: &str, new_order: i32) -> Result<Vec<SessionInfo>, String> {
+        let sessions = self.sessions.read();
+        let group_id = sessions
+            .get(id)
+            .ok_or_else(|| format!("Session not found: {}", id))?
+            .lock()
+            .info
+            .group_id
+            .clone();
+
+        let mut siblings = ordered_siblings(&sessions, group_id.as_deref());
+        siblings.retain(|sibling| sibling != id);
+        let index = usize::try_from(new_order).unwrap_or(0).min(siblings.len());
+        siblings.insert(index, id.to_string());
+        renumber(&sessions, &siblings);
+
+        Ok(siblings
+            .iter()
+            .filter_map(|id| sessions.get(id).map(|entry| entry.lock().info()))
+            .collect())
+    }
+
+    /// Set the startup commands for a session (to run on restore)
+    pub fn set_startup_commands(
+        &self,
+        id: &str,
+        commands: Vec<String>,
+        delay_ms: Option<u64>,
+    ) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session.info.startup_commands = commands;
+        session.info.startup_delay_ms = delay_ms;
+        Ok(())
+    }
+
+    /// Set a session's hotkey label (uniqueness is checked by the caller)
+    pub fn set_session_hotkey(&self, id: &str, hotkey: Option<String>) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session.info.hotkey = hotkey;
+        Ok(())
+    }
+
+    /// Replace a session's tags (normalized by the caller)
+    pub fn set_session_tags(&self, id: &str, tags: Vec<String>) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
 
-        session.info.group_id = group_id;
+        session.info.tags = tags;
         Ok(())
     }
 
-    /// Set startup command for a session (to run on restore)
-    pub fn set_startup_command(&self, id: &str, command: Option<String>) -> Result<(), String> {
-        let mut sessions = self.sessions.lock();
-        let session = sessions
-            .get_mut(id)
+    /// Pin or unpin a session; returns whether it's now pinned
+    pub fn toggle_session_pinned(&self, id: &str) -> Result<bool, String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session.info.pinned = !session.info.pinned;
+        Ok(session.info.pinned)
+    }
+
+    /// Make a session start after another on restore; rejects links that would form a cycle
+    pub fn set_session_dependency(&self, id: &str, depends_on: Option<String>) -> Result<(), String> {
+        let sessions = self.sessions.read();
+        let entry = sessions
+            .get(id)
             .ok_or_else(|| format!("Session not found: {}", id))?;
+        if let Some(dependency) = &depends_on {
+            if !sessions.contains_key(dependency) {
+                return Err(format!("Session not found: {}", dependency));
+            }
+            // Walk the chain as it would be with the new link in place
+            let links = |from: &str| {
+                if from == id {
+                    return depends_on.clone();
+                }
+                sessions.get(from).and_then(|entry| entry.lock().info.depends_on.clone())
+            };
+            if let Err(cycle) = dependency::dependency_chain(id, links) {
+                let name = |id: &str| sessions.get(id).map_or_else(|| id.to_string(), |entry| entry.lock().info.name.clone());
+                return Err(dependency::describe_cycle(&cycle, name));
+            }
+        }
+
+        entry.lock().info.depends_on = depends_on;
+        Ok(())
+    }
+
+    /// Sessions carrying `tag`, compared ignoring case
+    pub fn get_sessions_by_tag(&self, tag: &str) -> Vec<SessionInfo> {
+        let tag = tag.trim().to_lowercase();
+        self.entries()
+            .iter()
+            .map(|entry| entry.lock())
+            .filter(|session| session.info.tags.iter().any(|t| t.to_lowercase() == tag))
+            .map(|session| session.info())
+            .collect()
+    }
+
+    /// Set how a session is closed by `delete_session`
+    pub fn set_close_behavior(&self, id: &str, behavior: CloseBehavior) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session.info.close_behavior = behavior;
+        Ok(())
+    }
+
+    /// Set or clear a session's color
+    pub fn set_session_color(&self, id: &str, color: Option<String>) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session.info.color = color;
+        Ok(())
+    }
+
+    /// Get a session's color, deriving and storing one from its project root if none is set
+    pub fn derive_session_color(&self, id: &str) -> Result<String, String> {
+        let entry = self.session(id)?;
+        let session = &mut *entry.lock();
 
-        session.info.startup_command = command;
+        let color = session
+            .info
+            .color
+            .get_or_insert_with(|| color::color_for_path(&color::project_root(&session.info.cwd)));
+        Ok(color.clone())
+    }
+
+    /// Set the TERM a session advertises; applies the next time its shell is spawned
+    pub fn set_session_term(&self, id: &str, term: Option<String>) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session.info.term_override = term;
+        Ok(())
+    }
+
+    /// Store the frontend's scroll position for a session
+    pub fn set_session_scroll(&self, id: &str, position: Option<serde_json::Value>) -> Result<(), String> {
+        let entry = self.session(id)?;
+        let mut session = entry.lock();
+
+        session.info.scroll_position = position;
         Ok(())
     }
 
-    /// Run a command in a session (used for startup commands)
+    /// Run a command in a session. Callers holding the shared manager lock
+    /// should use `session_input(id)?.run_command(..)` after releasing it.
+    #[allow(dead_code)]
     pub fn run_command(&self, id: &str, command: &str) -> Result<(), String> {
-        // Write the command followed by Enter
-        let command_with_newline = format!("{}\n", command);
-        self.write_to_session(id, command_with_newline.as_bytes())
+        self.session_input(id)?.run_command(command)
     }
 
-    /// Check if a session exists and is running
+    /// Check if a session exists and its shell is still alive. This asks the
+    /// child's waiter rather than `status`, which only changes once the PTY
+    /// closes (a background job can hold it open after the shell exits).
     #[allow(dead_code)]
     pub fn is_session_running(&self, id: &str) -> bool {
-        let sessions = self.sessions.lock();
-        if let Some(session) = sessions.get(id) {
-            matches!(session.info.status, SessionStatus::Running)
+        self.session(id).is_ok_and(|entry| {
+            entry
+                .lock()
+                .process
+                .as_ref()
+                .is_some_and(|process| process.exit.get().is_none())
+        })
+    }
+}
+
+/// Ids of the sessions in `group_id`, by order with creation time and id as tie-breakers
+fn ordered_siblings(sessions: &HashMap<String, SessionHandle>, group_id: Option<&str>) -> Vec<String> {
+    let mut siblings: Vec<(i32, i64, String)> = sessions
+        .values()
+        .map(|entry| entry.lock())
+        .filter(|session| session.info.group_id.as_deref() == group_id)
+        .map(|session| (session.info.order, session.info.created_at, session.info.id.clone()))
+        .collect();
+    siblings.sort();
+    siblings.into_iter().map(|(_, _, id)| id).collect()
+}
+
+fn next_order(sessions: &HashMap<String, SessionHandle>, group_id: Option<&str>) -> i32 {
+    sessions
+        .values()
+        .map(|entry| entry.lock())
+        .filter(|session| session.info.group_id.as_deref() == group_id)
+        .map(|session| session.info.order + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Number the given sessions 0..n in the order listed
+fn renumber(sessions: &HashMap<String, SessionHandle>, ids: &[String]) {
+    for (index, id) in ids.iter().enumerate() {
+        if let Some(entry) = sessions.get(id) {
+            entry.lock().info.order = index as i32;
+        }
+    }
+}
+
+fn session_metrics(id: &str, stats: &OutputStats) -> SessionMetrics {
+    SessionMetrics {
+        id: id.to_string(),
+        total_bytes: stats.total_bytes(),
+        bytes_per_sec: stats.bytes_per_sec(),
+        last_activity: stats.last_activity(),
+    }
+}
+
+fn log_sink_id(session_id: &str) -> String {
+    format!("log-{}", session_id)
+}
+
+fn recording_sink_id(session_id: &str) -> String {
+    format!("recording-{}", session_id)
+}
+
+/// Kill a session's shell and clean up after it once it has gone
+fn stop_process(mut process: SessionProcess) {
+    process.exit.killed.store(true, Ordering::Relaxed);
+    let _ = process.killer.kill();
+
+    if let Some(cgroup) = process.cgroup.take() {
+        // The cgroup can only be removed once the killed process has left it
+        thread::spawn(move || {
+            for _ in 0..20 {
+                thread::sleep(std::time::Duration::from_millis(100));
+                if !cgroup.exists() {
+                    break;
+                }
+                resource_limits::release(&cgroup);
+            }
+        });
+    }
+}
+
+/// Whether an OSC 7 host names this machine; shells often report a bare or
+/// `.local` variant of the hostname
+fn is_local_host(host: Option<&str>, hostname: Option<&str>) -> bool {
+    let short = |name: &str| name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+    match (host, hostname) {
+        (None, _) => true,
+        (Some(host), _) if host.eq_ignore_ascii_case("localhost") => true,
+        (Some(host), Some(hostname)) => short(host) == short(hostname),
+        (Some(_), None) => false,
+    }
+}
+
+/// `ssh` arguments that connect to `host` and run `command` there, if any
+fn ssh_args(
+    host: &str,
+    user: Option<&str>,
+    port: Option<u16>,
+    identity_file: Option<&std::path::Path>,
+    command: Option<&SessionCommand>,
+) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(port) = port {
+        args.push("-p".to_string());
+        args.push(port.to_string());
+    }
+    if let Some(identity_file) = identity_file {
+        args.push("-i".to_string());
+        args.push(identity_file.to_string_lossy().into_owned());
+    }
+    // ssh only allocates a remote tty by itself when there's no command
+    if command.is_some() {
+        args.push("-t".to_string());
+    }
+    args.push(match user {
+        Some(user) => format!("{}@{}", user, host),
+        None => host.to_string(),
+    });
+    if let Some(command) = command {
+        args.push(if command.then_shell {
+            format!("{}; exec \"$SHELL\" -l", command.command)
         } else {
-            false
+            command.command.clone()
+        });
+    }
+    args
+}
+
+/// Last non-empty line of an ssh session's output, stripped of escape sequences
+fn ssh_error_message(output: &[u8]) -> String {
+    search::strip_escapes(output)
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .unwrap_or("ssh could not connect")
+        .to_string()
+}
+
+/// Shell arguments for sessions that don't set their own
+fn default_shell_args() -> Vec<String> {
+    // A login shell loads the user's profile (.zprofile, .bash_profile, etc.)
+    #[cfg(unix)]
+    {
+        vec!["-l".to_string()]
+    }
+    // Windows shells have no login mode and load their profiles anyway
+    #[cfg(not(unix))]
+    {
+        Vec::new()
+    }
+}
+
+/// Arguments, after the shell's own, that make it run `command`
+fn command_args(shell: &str, shell_args: &[String], command: &SessionCommand) -> Vec<String> {
+    #[cfg(unix)]
+    {
+        if command.then_shell {
+            // Fall back to an interactive shell, with the same arguments, once the command exits
+            let shell: Vec<String> = std::iter::once(shell)
+                .chain(shell_args.iter().map(String::as_str))
+                .map(shell_quote)
+                .collect();
+            vec!["-c".to_string(), format!("{}; exec {}", command.command, shell.join(" "))]
+        } else {
+            vec!["-c".to_string(), command.command.clone()]
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = shell_args;
+        let name = std::path::Path::new(shell)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        // cmd's /K and PowerShell's -NoExit keep the shell open after the command
+        let mut args: Vec<String> = match (name.as_str(), command.then_shell) {
+            ("cmd", false) => vec!["/C".into()],
+            ("cmd", true) => vec!["/K".into()],
+            (_, false) => vec!["-Command".into()],
+            (_, true) => vec!["-NoExit".into(), "-Command".into()],
+        };
+        args.push(command.command.clone());
+        args
+    }
+}
+
+/// Quote a string for use as a single POSIX shell word
+#[cfg_attr(not(unix), allow(dead_code))]
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Ensure a session's working directory exists and can be entered
+fn check_cwd(cwd: &std::path::Path) -> Result<(), String> {
+    match std::fs::read_dir(cwd) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(format!("Directory not found: {}", cwd.display()))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotADirectory => {
+            Err(format!("Not a directory: {}", cwd.display()))
+        }
+        Err(e) => Err(format!("Cannot access directory: {} ({})", cwd.display(), e)),
+    }
+}
+
+/// Ensure the shell resolves (like the spawn will) to an executable file
+fn check_shell(shell: &str, cwd: &std::path::Path, path: Option<&std::ffi::OsStr>) -> Result<(), String> {
+    let candidates: Vec<PathBuf> = if shell.contains('/') || shell.contains(std::path::MAIN_SEPARATOR) {
+        vec![cwd.join(shell)]
+    } else {
+        path.into_iter()
+            .flat_map(std::env::split_paths)
+            .flat_map(|dir| executable_names(shell).into_iter().map(move |name| dir.join(name)))
+            .collect()
+    };
+
+    let mut found = false;
+    for candidate in &candidates {
+        let Ok(metadata) = std::fs::metadata(candidate) else {
+            continue;
+        };
+        found = true;
+        if metadata.is_file() && is_executable(&metadata) {
+            return Ok(());
+        }
+    }
+
+    if found {
+        Err(format!("Shell not executable: {}", shell))
+    } else {
+        Err(format!("Shell not found: {}", shell))
+    }
+}
+
+/// File names a bare command can resolve to on PATH
+fn executable_names(command: &str) -> Vec<String> {
+    // Windows runs `pwsh` as `pwsh.exe`
+    #[cfg(not(unix))]
+    if std::path::Path::new(command).extension().is_none() {
+        return vec![command.to_string(), format!("{}.exe", command)];
+    }
+    vec![command.to_string()]
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// Turn a spawn failure into a message naming what's wrong, where the cause is known
+fn spawn_error(io_error: Option<&std::io::Error>, message: &str, shell: &str) -> String {
+    match io_error.map(|e| e.kind()) {
+        Some(std::io::ErrorKind::NotFound) => format!("Shell not found: {}", shell),
+        Some(std::io::ErrorKind::PermissionDenied) => format!("Shell not executable: {}", shell),
+        _ => format!("Failed to spawn shell: {}", message),
+    }
+}
+
+/// Combine the configured PATH directories with the inherited PATH
+fn build_path(settings: &Settings, inherited: Option<std::ffi::OsString>) -> Option<std::ffi::OsString> {
+    if settings.path_dirs.is_empty() {
+        return None;
+    }
+
+    let home = dirs::home_dir();
+    let extra: Vec<PathBuf> = settings
+        .path_dirs
+        .iter()
+        .map(|dir| match (dir.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(dir),
+        })
+        .collect();
+    let inherited: Vec<PathBuf> = inherited
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+
+    let combined: Vec<PathBuf> = match settings.path_mode {
+        PathMode::Prepend => extra.into_iter().chain(inherited).collect(),
+        PathMode::Append => inherited.into_iter().chain(extra).collect(),
+        PathMode::Replace => extra,
+    };
+    std::env::join_paths(combined).ok()
+}
+
+fn foreground_process(session: &ActiveSession) -> Option<ForegroundProcess> {
+    let pid = process::foreground_pid(&*session.process.as_ref()?.master)?;
+    let name = process::process_name(pid)?;
+    Some(ForegroundProcess { pid, name })
+}
+
+/// Where a session's input goes: its PTY writer, or its pacer while rate-limited
+pub struct SessionInput {
+    writer: SharedWriter,
+    pacer: Option<PacerQueue>,
+    bracketed_paste: Arc<AtomicBool>,
+}
+
+impl SessionInput {
+    /// Write pasted text, wrapped in bracketed-paste markers if the program
+    /// asked for them; returns whether it was wrapped
+    pub fn paste(&self, data: &[u8]) -> Result<bool, String> {
+        if !self.bracketed_paste.load(Ordering::Relaxed) {
+            self.write(&[data])?;
+            return Ok(false);
         }
+        // An end marker inside the text would let the rest run as typed input
+        let data = bracketed_paste::strip_paste_end(data);
+        self.write(&[PASTE_START, &data, PASTE_END])?;
+        Ok(true)
+    }
+
+    /// Type a command followed by Enter
+    pub fn run_command(&self, command: &str) -> Result<(), String> {
+        self.write(&[command.as_bytes(), b"\n"])
+    }
+
+    /// Type end-of-file (Ctrl-D)
+    pub fn send_eof(&self) -> Result<(), String> {
+        self.write(&[&[0x04]])
+    }
+
+    /// Write chunks in order with one flush; blocks only on this session's writer
+    pub fn write(&self, chunks: &[&[u8]]) -> Result<(), String> {
+        // Rate-limited sessions pace input out on their own thread; if the limit
+        // was lifted since this was taken, write directly instead
+        if self.pacer.as_ref().is_some_and(|pacer| pacer.enqueue(chunks)) {
+            return Ok(());
+        }
+        write_chunks_and_flush(&self.writer, chunks)
     }
 }
 
+/// Write all bytes to a PTY writer and flush
+fn write_and_flush(writer: &SharedWriter, data: &[u8]) -> Result<(), String> {
+    write_chunks_and_flush(writer, &[data])
+}
+
+/// Write every chunk in full under one lock, then flush once
+fn write_chunks_and_flush(writer: &SharedWriter, chunks: &[&[u8]]) -> Result<(), String> {
+    let mut writer = writer.lock();
+    for chunk in chunks {
+        // write_all retries short and interrupted writes, so nothing is dropped
+        writer
+            .write_all(chunk)
+            .map_err(|e| format!("Write error: {}", e))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("Flush error: {}", e))
+}
+
 impl Default for PtyManager {
     fn default() -> Self {
         Self::new()
@@ -302,3 +2514,307 @@ pub type SharedPtyManager = Arc<Mutex<PtyManager>>;
 pub fn create_shared_manager() -> SharedPtyManager {
     Arc::new(Mutex::new(PtyManager::new()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(manager: &PtyManager, name: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let info = SessionInfo::new(id.clone(), name.to_string(), "/bin/sh".to_string(), PathBuf::from("/tmp"));
+        manager.add_pending_session(info);
+        id
+    }
+
+    /// Spawn `program` with `args` in a session of its own
+    fn spawn(manager: &PtyManager, program: &str, args: &[&str]) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut info = SessionInfo::new(id.clone(), program.to_string(), program.to_string(), PathBuf::from("/tmp"));
+        info.shell_args = Some(args.iter().map(|arg| arg.to_string()).collect());
+        manager.spawn_session(info, 24, 80).unwrap();
+        id
+    }
+
+    /// Poll a session's scrollback until `done` accepts it; panics after a few seconds
+    fn wait_for_output(manager: &PtyManager, id: &str, done: impl Fn(&str) -> bool) -> String {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let output = String::from_utf8_lossy(&manager.get_session_scrollback(id).unwrap()).into_owned();
+            if done(&output) {
+                return output;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for output: {:?}", output);
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// Poll until a session's shell has ended and its status says so
+    fn wait_for_exit(manager: &PtyManager, id: &str) -> SessionInfo {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let info = manager.get_session_info(id).unwrap();
+            if info.status != SessionStatus::Running {
+                return info;
+            }
+            assert!(Instant::now() < deadline, "session never exited");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exited_session_is_stopped_with_its_code() {
+        let manager = PtyManager::new();
+        let id = spawn(&manager, "/bin/echo", &["hi"]);
+        let info = wait_for_exit(&manager, &id);
+        assert_eq!(info.status, SessionStatus::Stopped);
+        assert_eq!(info.exit_code, Some(0));
+        assert_eq!(info.pid, None);
+        wait_for_output(&manager, &id, |output| output.contains("hi"));
+
+        let id = spawn(&manager, "/bin/sh", &["-c", "exit 3"]);
+        assert_eq!(wait_for_exit(&manager, &id).exit_code, Some(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writing_to_exited_session_is_refused() {
+        let manager = PtyManager::new();
+        let id = spawn(&manager, "/bin/sh", &["-c", "exit 0"]);
+        wait_for_exit(&manager, &id);
+        assert_eq!(manager.write_to_session(&id, b"ls\n"), Err(format!("Session not running: {}", id)));
+        assert!(manager.session_input(&id).is_err());
+        assert_eq!(manager.write_to_session("missing", b"ls\n"), Err("Session not found: missing".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spawn_errors_name_the_problem() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let manager = PtyManager::new();
+        let spawn_shell = |shell: &str, cwd: PathBuf| {
+            let info = SessionInfo::new(uuid::Uuid::new_v4().to_string(), "bad".to_string(), shell.to_string(), cwd);
+            manager.spawn_session(info, 24, 80).map(|_| ())
+        };
+
+        let missing = "/nonexistent/shelltree-shell";
+        assert_eq!(spawn_shell(missing, PathBuf::from("/tmp")), Err(format!("Shell not found: {}", missing)));
+
+        let script = std::env::temp_dir().join(format!("shelltree-noexec-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let shell = script.to_string_lossy().into_owned();
+        assert_eq!(spawn_shell(&shell, PathBuf::from("/tmp")), Err(format!("Shell not executable: {}", shell)));
+        let _ = std::fs::remove_file(&script);
+
+        let cwd = PathBuf::from("/nonexistent/shelltree-cwd");
+        assert_eq!(spawn_shell("/bin/sh", cwd.clone()), Err(format!("Directory not found: {}", cwd.display())));
+        assert!(manager.get_all_sessions().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn eof_ends_cat() {
+        let manager = PtyManager::new();
+        let id = spawn(&manager, "/bin/cat", &[]);
+        manager.write_to_session(&id, b"hello\n").unwrap();
+        wait_for_output(&manager, &id, |output| output.matches("hello").count() >= 2);
+        manager.send_eof(&id).unwrap();
+        let info = wait_for_exit(&manager, &id);
+        assert_eq!(info.status, SessionStatus::Stopped);
+        assert_eq!(info.exit_code, Some(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parallel_writes_reach_their_sessions() {
+        const SESSIONS: usize = 8;
+        const LINES: usize = 50;
+
+        let manager = Arc::new(PtyManager::new());
+        let ids: Vec<String> = (0..SESSIONS).map(|_| spawn(&manager, "/bin/cat", &[])).collect();
+
+        let writers: Vec<_> = ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| {
+                let manager = manager.clone();
+                let id = id.clone();
+                thread::spawn(move || {
+                    for line in 0..LINES {
+                        manager
+                            .write_to_session(&id, format!("s{}-line{}\n", index, line).as_bytes())
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        for (index, id) in ids.iter().enumerate() {
+            let last = format!("s{}-line{}", index, LINES - 1);
+            let output = wait_for_output(&manager, id, |output| output.matches(&last).count() >= 2);
+            for line in 0..LINES {
+                assert!(output.contains(&format!("s{}-line{}\r\n", index, line)), "session {} lost line {}", index, line);
+            }
+            // Nothing written for another session turned up here
+            let other = (index + 1) % SESSIONS;
+            assert!(!output.contains(&format!("s{}-", other)));
+        }
+        manager.shutdown_all();
+    }
+
+    /// Time 1 MB of input through `cat` into a file, written with one
+    /// `write_to_session` per chunk or all at once with `write_chunks_to_session`;
+    /// checks every byte arrived
+    #[cfg(unix)]
+    fn time_large_write(bulk: bool) -> Duration {
+        const TOTAL: usize = 1024 * 1024;
+        const CHUNK: usize = 4096;
+
+        let path = std::env::temp_dir().join(format!("shelltree-bulk-{}", uuid::Uuid::new_v4()));
+        let script = format!("stty raw -echo; printf ready; exec cat > '{}'", path.display());
+        let manager = PtyManager::new();
+        let id = spawn(&manager, "/bin/sh", &["-c", &script]);
+        wait_for_output(&manager, &id, |output| output.contains("ready"));
+
+        let data: Vec<u8> = (0..TOTAL).map(|i| b'a' + (i % 26) as u8).collect();
+        let chunks: Vec<&[u8]> = data.chunks(CHUNK).collect();
+        let started = Instant::now();
+        if bulk {
+            manager.write_chunks_to_session(&id, &chunks).unwrap();
+        } else {
+            for chunk in &chunks {
+                manager.write_to_session(&id, chunk).unwrap();
+            }
+        }
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while std::fs::metadata(&path).map_or(0, |m| m.len() as usize) < TOTAL {
+            assert!(Instant::now() < deadline, "input never all arrived");
+            thread::sleep(Duration::from_millis(5));
+        }
+        let elapsed = started.elapsed();
+
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        manager.kill_session(&id).unwrap();
+        let _ = std::fs::remove_file(&path);
+        elapsed
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[ignore = "timing comparison; run with --ignored --nocapture"]
+    fn bulk_write_timing() {
+        let single = time_large_write(false);
+        let bulk = time_large_write(true);
+        println!("1 MB as single writes: {:?}, in bulk: {:?}", single, bulk);
+    }
+
+    #[test]
+    fn base64_output_is_smaller_for_4kb() {
+        let data: Vec<u8> = (0..4096).map(|i| (i * 7 % 256) as u8).collect();
+        let size = |base64: bool| {
+            let event = PtyOutput {
+                id: "session".to_string(),
+                data: OutputData::encode(&data, base64),
+            };
+            serde_json::to_vec(&event).unwrap().len()
+        };
+        let (raw, base64) = (size(false), size(true));
+        // Numbers average well over two characters plus a comma; base64 is 4/3 of the bytes
+        assert!(raw > 3 * 4096, "raw payload was {} bytes", raw);
+        assert!(base64 < 4096 * 4 / 3 + 64, "base64 payload was {} bytes", base64);
+        assert!(base64 * 2 < raw);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn seeded_output_comes_before_live_output() {
+        let manager = PtyManager::new();
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut info = SessionInfo::new(id.clone(), "seeded".to_string(), "/bin/sh".to_string(), PathBuf::from("/tmp"));
+        info.shell_args = Some(vec!["-c".to_string(), "echo live; sleep 5".to_string()]);
+        manager.add_pending_session(info);
+
+        manager.set_session_output(&id, b"\x1b[31msaved").unwrap();
+        assert_eq!(manager.get_session_scrollback(&id).unwrap(), b"\x1b[31msaved\x1b[0m\r\n");
+
+        manager.spawn_pending_session(&id, 24, 80).unwrap();
+        let output = wait_for_output(&manager, &id, |output| output.contains("live"));
+        assert!(output.starts_with("\x1b[31msaved\x1b[0m\r\nlive"), "{:?}", output);
+        assert_eq!(manager.set_session_output(&id, b"x"), Err(format!("Session is running: {}", id)));
+        manager.kill_session(&id).unwrap();
+    }
+
+    #[test]
+    fn group_sessions_come_in_tab_order() {
+        let manager = PtyManager::new();
+        let ids: Vec<String> = [2, 0, 1]
+            .into_iter()
+            .map(|order| {
+                let id = uuid::Uuid::new_v4().to_string();
+                let mut info = SessionInfo::new(id.clone(), "tab".to_string(), "/bin/sh".to_string(), PathBuf::from("/tmp"));
+                info.group_id = Some("g".to_string());
+                info.order = order;
+                manager.add_pending_session(info);
+                id
+            })
+            .collect();
+        pending(&manager, "ungrouped");
+        assert_eq!(manager.find_sessions_in_group("g"), [ids[1].clone(), ids[2].clone(), ids[0].clone()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restart_replaces_the_shell_and_fails_once_killed() {
+        let manager = PtyManager::new();
+        let id = spawn(&manager, "/bin/sh", &["-c", "echo first; sleep 30"]);
+        wait_for_output(&manager, &id, |output| output.contains("first"));
+        let old_pid = manager.get_session_info(&id).unwrap().pid;
+
+        let mut restart = manager.begin_restart(&id).unwrap();
+        restart.stop_old();
+        let info = manager.finish_restart(restart).unwrap();
+        assert_eq!(info.status, SessionStatus::Running);
+        assert_ne!(info.pid, old_pid);
+        wait_for_output(&manager, &id, |output| output.matches("first").count() == 2);
+
+        let mut restart = manager.begin_restart(&id).unwrap();
+        restart.stop_old();
+        manager.kill_session(&id).unwrap();
+        assert_eq!(manager.finish_restart(restart).map(|_| ()), Err(format!("Session not found: {}", id)));
+    }
+
+    #[test]
+    fn layout_info_keeps_configured_cwd_and_name() {
+        let manager = PtyManager::new();
+        let id = pending(&manager, "shell");
+        manager.set_live_cwd(&id, PathBuf::from("/usr"));
+        assert_eq!(manager.set_title_name(&id, "vim"), Some("vim".to_string()));
+        manager.session(&id).unwrap().lock().output_stats.record(16);
+
+        let live = manager.get_session_info(&id).unwrap();
+        assert_eq!(live.cwd, PathBuf::from("/usr"));
+        assert_eq!(live.name, "vim");
+
+        let saved = manager.get_layout_sessions().remove(0);
+        assert_eq!(saved.cwd, PathBuf::from("/tmp"));
+        assert_eq!(saved.name, "shell");
+        assert_eq!(saved.status, SessionStatus::Stopped);
+        assert_eq!(saved.pid, None);
+        assert_eq!(saved.last_activity, saved.created_at * 1000);
+    }
+
+    #[test]
+    fn custom_name_ignores_titles() {
+        let manager = PtyManager::new();
+        let id = pending(&manager, "shell");
+        manager.rename_session(&id, "build".to_string()).unwrap();
+        assert_eq!(manager.set_title_name(&id, "vim"), None);
+        assert_eq!(manager.get_session_info(&id).unwrap().name, "build");
+        assert_eq!(manager.get_layout_sessions()[0].name, "build");
+    }
+}
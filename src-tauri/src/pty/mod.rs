@@ -1,5 +1,32 @@
+pub mod bell;
+pub mod bracketed_paste;
+pub mod color;
+pub mod dependency;
+pub mod dotenv;
+pub mod git;
+pub mod html_export;
+pub mod input_pacer;
 pub mod manager;
+pub mod osc;
+pub mod output_batcher;
+pub mod output_sinks;
+pub mod output_stats;
+pub mod process;
+pub mod recording;
+pub mod resource_limits;
+pub mod scrollback;
+pub mod search;
+pub mod self_test;
 pub mod session;
+pub mod session_log;
+#[cfg(unix)]
+pub mod socket_sink;
+pub mod terminal_query;
+pub mod termios;
+pub mod utf8;
 
 pub use manager::{create_shared_manager, SharedPtyManager};
-pub use session::{AppState, SessionGroup, SessionInfo};
+pub use session::{
+    ActiveSessionFallback, AppState, CloseBehavior, PathMode, Profile, SessionCommand,
+    SessionGroup, SessionInfo, SessionKind, SessionOrigin, SessionStatus, Settings, SortKey, STATE_VERSION,
+};
@@ -0,0 +1,171 @@
+use std::path::PathBuf;
+
+/// Longest partial OSC sequence kept between reads; anything longer is dropped
+const MAX_CARRY: usize = 4096;
+
+/// An OSC sequence the backend acts on
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscEvent {
+    /// OSC 7 (`ESC ] 7 ; file://host/path BEL`): the shell's current directory
+    WorkingDirectory { host: Option<String>, path: PathBuf },
+    /// OSC 0 or 2: a new window title
+    Title(String),
+}
+
+/// Scans PTY output for OSC sequences, handling sequences split across reads
+#[derive(Default)]
+pub struct OscScanner {
+    carry: Vec<u8>,
+}
+
+impl OscScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan a chunk of output and return the events it completes
+    pub fn scan(&mut self, data: &[u8]) -> Vec<OscEvent> {
+        let mut buf = std::mem::take(&mut self.carry);
+        buf.extend_from_slice(data);
+
+        let mut events = Vec::new();
+        let mut i = 0;
+        while i < buf.len() {
+            if buf[i] != 0x1b {
+                i += 1;
+                continue;
+            }
+            if i + 1 >= buf.len() {
+                self.hold(&buf[i..]);
+                break;
+            }
+            if buf[i + 1] != b']' {
+                i += 1;
+                continue;
+            }
+
+            // Terminated by BEL or ST (`ESC \`)
+            let start = i + 2;
+            let end = (start..buf.len()).find(|&j| buf[j] == 0x07 || (buf[j] == 0x1b && buf.get(j + 1) == Some(&b'\\')));
+            let Some(end) = end else {
+                self.hold(&buf[i..]);
+                break;
+            };
+            if let Some(event) = classify(&buf[start..end]) {
+                events.push(event);
+            }
+            i = if buf[end] == 0x07 { end + 1 } else { end + 2 };
+        }
+        events
+    }
+
+    fn hold(&mut self, partial: &[u8]) {
+        if partial.len() <= MAX_CARRY {
+            self.carry.extend_from_slice(partial);
+        }
+    }
+}
+
+fn classify(body: &[u8]) -> Option<OscEvent> {
+    let split = body.iter().position(|&b| b == b';')?;
+    let (code, payload) = (&body[..split], &body[split + 1..]);
+    match code {
+        b"7" => parse_file_url(payload),
+        b"0" | b"2" => Some(OscEvent::Title(String::from_utf8_lossy(payload).into_owned())),
+        _ => None,
+    }
+}
+
+/// `file://host/path`, with the path percent-encoded
+fn parse_file_url(url: &[u8]) -> Option<OscEvent> {
+    let rest = url.strip_prefix(b"file://")?;
+    let slash = rest.iter().position(|&b| b == b'/')?;
+    let host = String::from_utf8_lossy(&rest[..slash]).into_owned();
+    let path = percent_decode(&rest[slash..]);
+    Some(OscEvent::WorkingDirectory {
+        host: (!host.is_empty()).then_some(host),
+        path: PathBuf::from(String::from_utf8_lossy(&path).into_owned()),
+    })
+}
+
+fn percent_decode(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let hex = input
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (input[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cwd(host: Option<&str>, path: &str) -> OscEvent {
+        OscEvent::WorkingDirectory {
+            host: host.map(str::to_string),
+            path: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn parses_working_directory() {
+        let mut scanner = OscScanner::new();
+        assert_eq!(scanner.scan(b"\x1b]7;file://mac.local/Users/me\x07$ "), [cwd(Some("mac.local"), "/Users/me")]);
+        assert_eq!(scanner.scan(b"\x1b]7;file:///tmp\x1b\\"), [cwd(None, "/tmp")]);
+        assert_eq!(scanner.scan(b"\x1b]7;http://host/tmp\x07"), []);
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        let mut scanner = OscScanner::new();
+        assert_eq!(
+            scanner.scan(b"\x1b]7;file://h/My%20Docs/caf%C3%A9/100%\x07"),
+            [cwd(Some("h"), "/My Docs/café/100%")]
+        );
+        assert_eq!(percent_decode(b"%2f%zz%4"), b"/%zz%4");
+    }
+
+    #[test]
+    fn joins_sequences_split_across_reads() {
+        let mut scanner = OscScanner::new();
+        assert_eq!(scanner.scan(b"output\x1b"), []);
+        assert_eq!(scanner.scan(b"]7;file://h/ho"), []);
+        assert_eq!(scanner.scan(b"me/me\x1b"), []);
+        assert_eq!(scanner.scan(b"\\more"), [cwd(Some("h"), "/home/me")]);
+    }
+
+    #[test]
+    fn parses_titles() {
+        let mut scanner = OscScanner::new();
+        assert_eq!(
+            scanner.scan(b"\x1b]0;vim README\x07\x1b]2;main \xe2\x80\x94 repo\x1b\\\x1b]1;icon\x07"),
+            [OscEvent::Title("vim README".to_string()), OscEvent::Title("main \u{2014} repo".to_string())]
+        );
+        assert_eq!(scanner.scan(b"\x1b]2;"), []);
+        assert_eq!(scanner.scan(b"split\x07"), [OscEvent::Title("split".to_string())]);
+    }
+
+    #[test]
+    fn drops_overlong_partial_sequences() {
+        let mut scanner = OscScanner::new();
+        let mut long = b"\x1b]7;file://h/".to_vec();
+        long.resize(MAX_CARRY + 16, b'a');
+        assert_eq!(scanner.scan(&long), []);
+        assert_eq!(scanner.scan(b"\x07"), []);
+        assert_eq!(scanner.scan(b"\x1b]7;file://h/ok\x07"), [cwd(Some("h"), "/ok")]);
+    }
+}
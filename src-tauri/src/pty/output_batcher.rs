@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long output is held to coalesce it into one event, unless changed
+pub const DEFAULT_FLUSH_INTERVAL_MS: u64 = 8;
+
+/// A batch is flushed early once it reaches this size
+pub const MAX_BATCH_BYTES: usize = 16 * 1024;
+
+/// Largest `pty-output` payload (before encoding) unless changed
+pub const DEFAULT_MAX_EVENT_BYTES: usize = 64 * 1024;
+
+/// Split a batch into pieces of at most `max` bytes (4 at least), ending each
+/// piece before a UTF-8 character rather than inside one where the data allows
+pub fn split_events(data: &[u8], max: usize) -> Vec<&[u8]> {
+    let max = max.max(4);
+    let mut pieces = Vec::new();
+    let mut rest = data;
+    while rest.len() > max {
+        // A character is at most 4 bytes, so one of these starts the next one
+        let end = (max - 3..=max)
+            .rev()
+            .find(|&i| rest[i] & 0xc0 != 0x80)
+            .unwrap_or(max);
+        pieces.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    pieces.push(rest);
+    pieces
+}
+
+/// Coalesce chunks from `chunks` into batches and hand each one to `flush`.
+///
+/// A chunk that arrives after a quiet period goes out immediately, so echoed
+/// keystrokes aren't delayed; chunks that follow within the interval are held
+/// until it runs out or `MAX_BATCH_BYTES` build up. An interval of 0 flushes
+/// every chunk. Returns once the sender is dropped, after flushing what's left.
+pub fn run(chunks: Receiver<Vec<u8>>, interval_ms: Arc<AtomicU64>, mut flush: impl FnMut(&[u8])) {
+    let mut pending: Vec<u8> = Vec::new();
+    let mut last_flush: Option<Instant> = None;
+
+    loop {
+        let interval = Duration::from_millis(interval_ms.load(Ordering::Relaxed));
+        let next = if pending.is_empty() {
+            chunks.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            let due = last_flush.map_or(Instant::now(), |at| at + interval);
+            chunks.recv_timeout(due.saturating_duration_since(Instant::now()))
+        };
+
+        match next {
+            Ok(chunk) => {
+                pending.extend_from_slice(&chunk);
+                let quiet = last_flush.is_none_or(|at| at.elapsed() >= interval);
+                if quiet || pending.len() >= MAX_BATCH_BYTES {
+                    flush(&pending);
+                    pending.clear();
+                    last_flush = Some(Instant::now());
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                flush(&pending);
+                pending.clear();
+                last_flush = Some(Instant::now());
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                if !pending.is_empty() {
+                    flush(&pending);
+                }
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_batches_stay_whole() {
+        assert_eq!(split_events(b"hello", 64), [b"hello"]);
+    }
+
+    #[test]
+    fn splits_at_the_cap() {
+        let data = [b'x'; 10];
+        let sizes: Vec<usize> = split_events(&data, 4).iter().map(|piece| piece.len()).collect();
+        assert_eq!(sizes, [4, 4, 2]);
+    }
+
+    #[test]
+    fn keeps_characters_whole() {
+        let text = "aé€😀".repeat(50);
+        let pieces = split_events(text.as_bytes(), 7);
+        assert!(pieces.iter().all(|piece| piece.len() <= 7 && std::str::from_utf8(piece).is_ok()));
+        assert_eq!(pieces.concat(), text.as_bytes());
+    }
+
+    #[test]
+    fn cuts_invalid_data_at_the_cap() {
+        let data = [0x80; 9];
+        let sizes: Vec<usize> = split_events(&data, 4).iter().map(|piece| piece.len()).collect();
+        assert_eq!(sizes, [4, 4, 1]);
+    }
+}
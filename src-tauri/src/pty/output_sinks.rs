@@ -0,0 +1,104 @@
+use parking_lot::Mutex;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::Instant;
+
+/// Something that receives a copy of a session's raw output
+pub trait OutputSink: Send {
+    fn write(&mut self, data: &[u8]) -> io::Result<()>;
+}
+
+impl OutputSink for std::fs::File {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        io::Write::write_all(self, data)
+    }
+}
+
+/// Reports the moment a byte sequence first shows up in the output
+pub struct SentinelSink {
+    needle: Vec<u8>,
+    /// Tail of earlier output, in case the needle straddles two reads
+    carry: Vec<u8>,
+    seen: Option<mpsc::Sender<Instant>>,
+}
+
+impl SentinelSink {
+    pub fn new(needle: Vec<u8>) -> (Self, mpsc::Receiver<Instant>) {
+        let (tx, rx) = mpsc::channel();
+        let sink = Self {
+            needle,
+            carry: Vec::new(),
+            seen: Some(tx),
+        };
+        (sink, rx)
+    }
+}
+
+impl OutputSink for SentinelSink {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.seen.is_none() {
+            return Ok(());
+        }
+        self.carry.extend_from_slice(data);
+        if self.carry.windows(self.needle.len()).any(|w| w == self.needle) {
+            if let Some(tx) = self.seen.take() {
+                let _ = tx.send(Instant::now());
+            }
+            self.carry.clear();
+            return Ok(());
+        }
+        let keep = self.needle.len().saturating_sub(1).min(self.carry.len());
+        self.carry.drain(..self.carry.len() - keep);
+        Ok(())
+    }
+}
+
+/// Extra output sinks attached to a session, fed by its reader thread
+#[derive(Default)]
+pub struct SessionSinks {
+    sinks: Mutex<Vec<(String, Box<dyn OutputSink>)>>,
+    /// Mirrors `sinks.len()` so the reader can skip the lock when nothing is attached
+    count: AtomicUsize,
+}
+
+impl SessionSinks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&self, id: String, sink: Box<dyn OutputSink>) {
+        let mut sinks = self.sinks.lock();
+        sinks.push((id, sink));
+        self.count.store(sinks.len(), Ordering::Release);
+    }
+
+    /// Detach a sink; returns false if no sink had that id
+    pub fn remove(&self, id: &str) -> bool {
+        let mut sinks = self.sinks.lock();
+        let before = sinks.len();
+        sinks.retain(|(sink_id, _)| sink_id != id);
+        self.count.store(sinks.len(), Ordering::Release);
+        sinks.len() != before
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.sinks.lock().iter().any(|(sink_id, _)| sink_id == id)
+    }
+
+    /// Copy output to every sink, dropping any that fail
+    pub fn dispatch(&self, data: &[u8]) {
+        if self.count.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        let mut sinks = self.sinks.lock();
+        sinks.retain_mut(|(id, sink)| match sink.write(data) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Dropping output sink {}: {}", id, e);
+                false
+            }
+        });
+        self.count.store(sinks.len(), Ordering::Release);
+    }
+}
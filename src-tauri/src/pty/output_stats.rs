@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+/// Length of the windows the output rate is estimated over, in ms
+const WINDOW_MS: i64 = 1000;
+
+/// Output counters for one session.
+///
+/// Only the session's reader thread records into these, so plain atomics
+/// suffice; readers may see a value a read or so out of date.
+pub struct OutputStats {
+    total_bytes: AtomicU64,
+    /// Unix ms of the last output (or the session's creation, before any)
+    last_activity: AtomicI64,
+    /// Unix ms the current rate window began
+    window_start: AtomicI64,
+    window_bytes: AtomicU64,
+    previous_window_bytes: AtomicU64,
+}
+
+impl OutputStats {
+    pub fn new(last_activity: i64) -> Self {
+        Self {
+            total_bytes: AtomicU64::new(0),
+            last_activity: AtomicI64::new(last_activity),
+            window_start: AtomicI64::new(now_ms()),
+            window_bytes: AtomicU64::new(0),
+            previous_window_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a read of `bytes` bytes
+    pub fn record(&self, bytes: usize) {
+        let now = now_ms();
+        let bytes = bytes as u64;
+        self.total_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.last_activity.store(now, Ordering::Relaxed);
+
+        let elapsed = now - self.window_start.load(Ordering::Relaxed);
+        if elapsed >= WINDOW_MS {
+            // Windows with no output in between leave nothing to carry over
+            let finished = self.window_bytes.swap(0, Ordering::Relaxed);
+            let previous = if elapsed < 2 * WINDOW_MS { finished } else { 0 };
+            self.previous_window_bytes.store(previous, Ordering::Relaxed);
+            self.window_start
+                .fetch_add(elapsed - elapsed % WINDOW_MS, Ordering::Relaxed);
+        }
+        self.window_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn last_activity(&self) -> i64 {
+        self.last_activity.load(Ordering::Relaxed)
+    }
+
+    /// Bytes per second over roughly the last second (a sliding window
+    /// estimated from the current and previous fixed windows)
+    pub fn bytes_per_sec(&self) -> f64 {
+        let elapsed = now_ms() - self.window_start.load(Ordering::Relaxed);
+        let current = self.window_bytes.load(Ordering::Relaxed) as f64;
+        let previous = self.previous_window_bytes.load(Ordering::Relaxed) as f64;
+        let window = WINDOW_MS as f64;
+        match elapsed {
+            e if e >= 2 * WINDOW_MS => 0.0,
+            // The current window has ended without new output; it is now the previous one
+            e if e >= WINDOW_MS => current * (2.0 * window - e as f64) / window,
+            e => previous * (window - e as f64) / window + current,
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
@@ -0,0 +1,285 @@
+use serde::Serialize;
+use portable_pty::MasterPty;
+
+/// Pid of the PTY's foreground process group leader (the program the user is running)
+pub fn foreground_pid(master: &dyn MasterPty) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        master
+            .process_group_leader()
+            .and_then(|pid| u32::try_from(pid).ok())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = master;
+        None
+    }
+}
+
+/// Basename of a process's executable, if it can be determined on this platform
+pub fn process_name(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "comm=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let comm = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        // Login shells show up as "-zsh"
+        let name = basename(&comm).trim_start_matches('-').to_string();
+        (!name.is_empty()).then_some(name)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// A signal `PtyManager::signal_session` can deliver
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub enum Signal {
+    /// SIGINT, as sent by Ctrl-C
+    Interrupt,
+    Terminate,
+    Hangup,
+    /// SIGQUIT, as sent by Ctrl-\
+    Quit,
+    /// SIGKILL, which can't be caught or ignored
+    Kill,
+}
+
+/// Send `signal` to every process in the process group `pgid`
+pub fn signal_process_group(pgid: u32, signal: Signal) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{killpg, Signal as NixSignal};
+        let signo = match signal {
+            Signal::Interrupt => NixSignal::SIGINT,
+            Signal::Terminate => NixSignal::SIGTERM,
+            Signal::Hangup => NixSignal::SIGHUP,
+            Signal::Quit => NixSignal::SIGQUIT,
+            Signal::Kill => NixSignal::SIGKILL,
+        };
+        let pgid = i32::try_from(pgid).map_err(|_| format!("Invalid process group: {}", pgid))?;
+        killpg(nix::unistd::Pid::from_raw(pgid), signo)
+            .map_err(|e| format!("Failed to send {:?}: {}", signal, e))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (pgid, signal);
+        Err("Signals are not supported on this platform".to_string())
+    }
+}
+
+/// Shells whose prompts we know how to add integration markers to
+const INTEGRATION_SHELLS: &[&str] = &["zsh", "bash", "fish"];
+
+/// Whether `shell` (a path or bare name) is one shell integration supports
+pub fn shell_supports_integration(shell: &str) -> bool {
+    // Login shells can be reported as "-zsh"
+    let name = basename(shell.trim()).trim_start_matches('-');
+    INTEGRATION_SHELLS.contains(&name)
+}
+
+/// Last path component of a command (`/usr/local/bin/node` → `node`)
+pub fn basename(command: &str) -> &str {
+    command.rsplit('/').next().unwrap_or(command)
+}
+
+/// One process in a session's process tree
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessNode {
+    pub pid: u32,
+    pub ppid: u32,
+    pub command: String,
+}
+
+/// Every descendant of `root` (not including `root` itself), parents before children.
+/// Empty where the process table can't be read.
+pub fn descendants(root: u32) -> Vec<ProcessNode> {
+    let table = process_table();
+    let mut tree = Vec::new();
+    let mut frontier = vec![root];
+    while let Some(parent) = frontier.pop() {
+        for node in table.iter().filter(|node| node.ppid == parent && node.pid != root) {
+            frontier.push(node.pid);
+            tree.push(node.clone());
+        }
+    }
+    tree
+}
+
+/// Snapshot of (pid, ppid, command) for every visible process
+#[cfg(target_os = "linux")]
+fn process_table() -> Vec<ProcessNode> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            // The command name in `stat` is parenthesised and may itself contain
+            // spaces or ')', so the fields we want start after the last ')'
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+            let ppid = stat[stat.rfind(')')? + 1..]
+                .split_whitespace()
+                .nth(1)?
+                .parse()
+                .ok()?;
+            let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+            let command = cmdline
+                .split(|&b| b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect::<Vec<_>>()
+                .join(" ");
+            // Kernel threads and zombies have no cmdline
+            let command = if command.is_empty() {
+                process_name(pid).unwrap_or_default()
+            } else {
+                command
+            };
+            Some(ProcessNode { pid, ppid, command })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn process_table() -> Vec<ProcessNode> {
+    let Ok(output) = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,command="])
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            let command = fields.collect::<Vec<_>>().join(" ");
+            Some(ProcessNode { pid, ppid, command })
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn process_table() -> Vec<ProcessNode> {
+    Vec::new()
+}
+
+/// CPU time and resident memory summed over a process and its descendants
+#[derive(Debug, Clone, Copy)]
+pub struct TreeSample {
+    /// Cumulative CPU time (user + system) of the processes alive right now
+    pub cpu_seconds: f64,
+    pub rss_bytes: u64,
+    pub processes: usize,
+}
+
+/// Sample `root` and everything under it; `None` where usage can't be read
+pub fn sample_tree(root: u32) -> Option<TreeSample> {
+    let mut pids = vec![root];
+    pids.extend(descendants(root).into_iter().map(|node| node.pid));
+    sample_pids(&pids)
+}
+
+#[cfg(target_os = "linux")]
+fn sample_pids(pids: &[u32]) -> Option<TreeSample> {
+    // SAFETY: sysconf only reads configuration values
+    let (ticks_per_sec, page_size) = unsafe {
+        (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE))
+    };
+    if ticks_per_sec <= 0 || page_size <= 0 {
+        return None;
+    }
+
+    let mut sample = TreeSample {
+        cpu_seconds: 0.0,
+        rss_bytes: 0,
+        processes: 0,
+    };
+    for &pid in pids {
+        // Processes can exit mid-walk; just leave them out
+        let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+            continue;
+        };
+        let Some(end) = stat.rfind(')') else {
+            continue;
+        };
+        // Fields after the command name start at `state` (field 3), so utime
+        // (field 14) and stime (field 15) are at offsets 11 and 12
+        let fields: Vec<&str> = stat[end + 1..].split_whitespace().collect();
+        let ticks: u64 = [11, 12]
+            .iter()
+            .filter_map(|&i| fields.get(i)?.parse::<u64>().ok())
+            .sum();
+        let resident_pages: u64 = std::fs::read_to_string(format!("/proc/{}/statm", pid))
+            .ok()
+            .and_then(|statm| statm.split_whitespace().nth(1)?.parse().ok())
+            .unwrap_or(0);
+
+        sample.cpu_seconds += ticks as f64 / ticks_per_sec as f64;
+        sample.rss_bytes += resident_pages * page_size as u64;
+        sample.processes += 1;
+    }
+    (sample.processes > 0).then_some(sample)
+}
+
+#[cfg(target_os = "macos")]
+fn sample_pids(pids: &[u32]) -> Option<TreeSample> {
+    let pid_list = pids.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+    let output = std::process::Command::new("ps")
+        .args(["-o", "time=,rss=", "-p", &pid_list])
+        .output()
+        .ok()?;
+
+    let mut sample = TreeSample {
+        cpu_seconds: 0.0,
+        rss_bytes: 0,
+        processes: 0,
+    };
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(time), Some(rss)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some(cpu_seconds) = parse_cpu_time(time) else {
+            continue;
+        };
+        // ps reports RSS in KiB
+        sample.cpu_seconds += cpu_seconds;
+        sample.rss_bytes += rss.parse::<u64>().unwrap_or(0) * 1024;
+        sample.processes += 1;
+    }
+    (sample.processes > 0).then_some(sample)
+}
+
+/// Parse ps's `[[dd-]hh:]mm:ss.ss` CPU time into seconds
+#[cfg(target_os = "macos")]
+fn parse_cpu_time(time: &str) -> Option<f64> {
+    let (days, rest) = match time.split_once('-') {
+        Some((days, rest)) => (days.parse::<f64>().ok()?, rest),
+        None => (0.0, time),
+    };
+    let mut seconds = 0.0;
+    for part in rest.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(days * 86_400.0 + seconds)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn sample_pids(_pids: &[u32]) -> Option<TreeSample> {
+    None
+}
@@ -0,0 +1,133 @@
+use parking_lot::Mutex;
+use serde_json::json;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::output_sinks::OutputSink;
+use super::utf8::Utf8Carry;
+
+/// A session's output captured with timings, for writing out as an asciinema v2 cast
+pub struct Recording {
+    width: u16,
+    height: u16,
+    started: Instant,
+    /// Unix seconds at the start, for the cast header
+    timestamp: i64,
+    env: Vec<(&'static str, String)>,
+    /// (seconds since start, event code, data)
+    events: Vec<(f64, &'static str, String)>,
+    utf8: Utf8Carry,
+}
+
+impl Recording {
+    pub fn new(width: u16, height: u16, shell: &str, term: &str) -> Self {
+        Self {
+            width,
+            height,
+            started: Instant::now(),
+            timestamp: chrono::Utc::now().timestamp(),
+            env: vec![("SHELL", shell.to_string()), ("TERM", term.to_string())],
+            events: Vec::new(),
+            utf8: Utf8Carry::new(),
+        }
+    }
+
+    /// Record output; casts hold text, so bytes are decoded as UTF-8 (lossily)
+    pub fn output(&mut self, data: &[u8]) {
+        let bytes = self.utf8.push(data);
+        if !bytes.is_empty() {
+            let text = String::from_utf8_lossy(&bytes).into_owned();
+            self.push("o", text);
+        }
+    }
+
+    /// Record a terminal resize
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.push("r", format!("{}x{}", cols, rows));
+    }
+
+    fn push(&mut self, code: &'static str, data: String) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        self.events.push((elapsed, code, data));
+    }
+
+    /// Write the recording to `path` as an asciinema v2 cast
+    pub fn write_cast(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(std::fs::File::create(path)?);
+        let env: serde_json::Map<String, serde_json::Value> = self
+            .env
+            .iter()
+            .map(|(key, value)| (key.to_string(), json!(value)))
+            .collect();
+        let header = json!({
+            "version": 2,
+            "width": self.width,
+            "height": self.height,
+            "timestamp": self.timestamp,
+            "env": env,
+        });
+        writeln!(out, "{}", header)?;
+        for (elapsed, code, data) in &self.events {
+            // Rounded to microseconds like asciinema itself writes them
+            let elapsed = (elapsed * 1_000_000.0).round() / 1_000_000.0;
+            writeln!(out, "{}", json!([elapsed, code, data]))?;
+        }
+        out.flush()
+    }
+}
+
+/// Sink feeding a session's output into its recording
+pub struct RecordingSink(pub Arc<Mutex<Recording>>);
+
+impl OutputSink for RecordingSink {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.0.lock().output(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn writes_asciicast_v2() {
+        let mut recording = Recording::new(80, 24, "/bin/zsh", "xterm-256color");
+        recording.output(b"$ echo caf\xc3");
+        recording.output(b"\xa9\r\n");
+        recording.resize(100, 30);
+        recording.output(b"\x1b[1mdone\x1b[0m");
+
+        let path = std::env::temp_dir().join(format!("shelltree-cast-{}.cast", uuid::Uuid::new_v4()));
+        recording.write_cast(&path).unwrap();
+        let cast = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let mut lines = cast.lines().map(|line| serde_json::from_str::<Value>(line).unwrap());
+        let header = lines.next().unwrap();
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 80);
+        assert_eq!(header["height"], 24);
+        assert!(header["timestamp"].is_i64());
+        assert_eq!(header["env"]["SHELL"], "/bin/zsh");
+        assert_eq!(header["env"]["TERM"], "xterm-256color");
+
+        let events: Vec<Value> = lines.collect();
+        let parts: Vec<(&str, &str)> = events
+            .iter()
+            .map(|event| (event[1].as_str().unwrap(), event[2].as_str().unwrap()))
+            .collect();
+        // The é split across reads comes out whole
+        assert_eq!(parts, [
+            ("o", "$ echo caf"),
+            ("o", "é\r\n"),
+            ("r", "100x30"),
+            ("o", "\u{1b}[1mdone\u{1b}[0m"),
+        ]);
+        let times: Vec<f64> = events.iter().map(|event| event[0].as_f64().unwrap()).collect();
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Smallest memory limit we accept; below this a shell can't even start
+const MIN_MEMORY_LIMIT: u64 = 4 * 1024 * 1024;
+
+/// CPU/memory caps for a session's process tree
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResourceLimits {
+    /// CPU limit in cores (e.g. 1.5 = one and a half cores)
+    #[serde(default)]
+    pub cpu_limit: Option<f64>,
+    /// Memory limit in bytes
+    #[serde(default)]
+    pub memory_limit: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Build limits from optional parameters; `None` when neither is set
+    pub fn from_params(cpu_limit: Option<f64>, memory_limit: Option<u64>) -> Result<Option<Self>, String> {
+        if cpu_limit.is_none() && memory_limit.is_none() {
+            return Ok(None);
+        }
+        let limits = Self {
+            cpu_limit,
+            memory_limit,
+        };
+        limits.validate()?;
+        Ok(Some(limits))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(cpu) = self.cpu_limit {
+            if !cpu.is_finite() || cpu <= 0.0 {
+                return Err(format!("Invalid CPU limit: {} (expected a positive number of cores)", cpu));
+            }
+        }
+        if let Some(memory) = self.memory_limit {
+            if memory < MIN_MEMORY_LIMIT {
+                return Err(format!(
+                    "Invalid memory limit: {} bytes (minimum is {} bytes)",
+                    memory, MIN_MEMORY_LIMIT
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Place a running process under the given limits.
+///
+/// On Linux this creates a cgroup v2 child of the app's own cgroup and moves
+/// the process into it. `portable_pty` has no pre-exec hook, so the move
+/// happens right after spawn. Returns the cgroup path so it can be removed
+/// when the session ends.
+#[cfg(target_os = "linux")]
+pub fn apply(session_id: &str, pid: u32, limits: &ResourceLimits) -> Result<Option<PathBuf>, String> {
+    use std::fs;
+    use std::path::Path;
+
+    let root = Path::new("/sys/fs/cgroup");
+    if !root.join("cgroup.controllers").exists() {
+        return Err("cgroup v2 is not available".to_string());
+    }
+
+    let own = fs::read_to_string("/proc/self/cgroup")
+        .map_err(|e| format!("Failed to read own cgroup: {}", e))?;
+    let own_path = own
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or_else(|| "App is not in a cgroup v2 hierarchy".to_string())?;
+    let parent = root.join(own_path.trim_start_matches('/'));
+    let dir = parent.join(format!("shelltree-{}", session_id));
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create cgroup {}: {}", dir.display(), e))?;
+    // May already be enabled (or not delegated to us); the limit writes below will tell
+    let _ = fs::write(parent.join("cgroup.subtree_control"), "+cpu +memory");
+
+    let write = |file: &str, value: String| {
+        fs::write(dir.join(file), value).map_err(|e| format!("Failed to set {}: {}", file, e))
+    };
+    if let Some(cpu) = limits.cpu_limit {
+        const PERIOD_US: f64 = 100_000.0;
+        write("cpu.max", format!("{} {}", (cpu * PERIOD_US).round() as u64, PERIOD_US as u64))?;
+    }
+    if let Some(memory) = limits.memory_limit {
+        write("memory.max", memory.to_string())?;
+    }
+    write("cgroup.procs", pid.to_string())?;
+
+    Ok(Some(dir))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_session_id: &str, _pid: u32, _limits: &ResourceLimits) -> Result<Option<PathBuf>, String> {
+    Err("Resource limits are not supported on this platform".to_string())
+}
+
+/// Remove a session's cgroup once its processes are gone
+pub fn release(path: &std::path::Path) {
+    let _ = std::fs::remove_dir(path);
+}
@@ -0,0 +1,283 @@
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::search::strip_escapes;
+
+/// Scrollback kept per session when `Settings::scrollback_bytes` isn't set
+pub const DEFAULT_SCROLLBACK_BYTES: usize = 256 * 1024;
+
+/// The most recent output of a session, capped at a fixed number of bytes
+/// and optionally a number of lines.
+///
+/// Once full, the oldest bytes are dropped to make room for new ones. Lines
+/// end at `\n` only, so a progress bar redrawn with `\r` is a single line.
+pub struct Scrollback {
+    inner: Mutex<Ring>,
+    /// Signalled on every push, for `read_since` callers waiting on output
+    pushed: Condvar,
+}
+
+/// Output returned by `read_since`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionRead {
+    pub data: Vec<u8>,
+    /// Stream offset just past `data`, to pass as `since_offset` next time
+    pub offset: u64,
+    /// Bytes after `since_offset` that were no longer retained
+    pub skipped: u64,
+}
+
+struct Ring {
+    bytes: VecDeque<u8>,
+    capacity: usize,
+    max_lines: Option<usize>,
+    /// `\n` bytes in `bytes`, kept up to date so line counts don't rescan
+    newlines: usize,
+    /// Bytes ever pushed; the stream offset just past the newest byte
+    total: u64,
+}
+
+impl Ring {
+    fn drain_front(&mut self, count: usize) {
+        let newlines = self.bytes.drain(..count).filter(|&b| b == b'\n').count();
+        self.newlines -= newlines;
+    }
+
+    fn line_count(&self) -> usize {
+        self.newlines + usize::from(self.bytes.back().is_some_and(|&b| b != b'\n'))
+    }
+
+    /// Drop the oldest lines until at most `max_lines` are left
+    fn trim_lines(&mut self) {
+        let Some(max) = self.max_lines else {
+            return;
+        };
+        let excess = self.line_count().saturating_sub(max);
+        if excess == 0 {
+            return;
+        }
+        // Fewer newlines than that means a cap of 0 on an unterminated line
+        match self.bytes.iter().enumerate().filter(|(_, &b)| b == b'\n').nth(excess - 1) {
+            Some((end, _)) => self.drain_front(end + 1),
+            None => self.drain_front(self.bytes.len()),
+        }
+    }
+}
+
+impl Scrollback {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Ring {
+                bytes: VecDeque::new(),
+                capacity,
+                max_lines: None,
+                newlines: 0,
+                total: 0,
+            }),
+            pushed: Condvar::new(),
+        }
+    }
+
+    /// Append output, trimming from the front to stay within capacity
+    pub fn push(&self, data: &[u8]) {
+        let mut ring = self.inner.lock();
+        ring.total += data.len() as u64;
+        let capacity = ring.capacity;
+        let data = &data[data.len().saturating_sub(capacity)..];
+        let overflow = (ring.bytes.len() + data.len()).saturating_sub(capacity);
+        ring.drain_front(overflow);
+        ring.bytes.extend(data);
+        ring.newlines += data.iter().filter(|&&b| b == b'\n').count();
+        ring.trim_lines();
+        drop(ring);
+        self.pushed.notify_all();
+    }
+
+    /// Change the cap; shrinking drops the oldest bytes right away
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut ring = self.inner.lock();
+        ring.capacity = capacity;
+        let overflow = ring.bytes.len().saturating_sub(capacity);
+        ring.drain_front(overflow);
+    }
+
+    /// Change the line cap; `None` keeps as many lines as fit in the byte cap
+    pub fn set_max_lines(&self, max_lines: Option<usize>) {
+        let mut ring = self.inner.lock();
+        ring.max_lines = max_lines;
+        ring.trim_lines();
+    }
+
+    /// Drop everything retained
+    pub fn clear(&self) {
+        let mut ring = self.inner.lock();
+        ring.bytes.clear();
+        ring.newlines = 0;
+    }
+
+    /// Copy of the retained output, oldest first
+    pub fn contents(&self) -> Vec<u8> {
+        self.inner.lock().bytes.iter().copied().collect()
+    }
+
+    /// Copy of at most the newest `max` bytes
+    pub fn tail(&self, max: usize) -> Vec<u8> {
+        let ring = self.inner.lock();
+        let skip = ring.bytes.len().saturating_sub(max);
+        ring.bytes.iter().skip(skip).copied().collect()
+    }
+
+    /// Output pushed after stream offset `since`, waiting up to `max_wait` for
+    /// some if there's none yet. Offsets count every byte ever pushed, so they
+    /// stay valid as old output is dropped or cleared.
+    pub fn read_since(&self, since: u64, max_wait: Duration) -> SessionRead {
+        let deadline = Instant::now() + max_wait;
+        let mut ring = self.inner.lock();
+        // An offset past the end reads whatever comes next
+        let since = since.min(ring.total);
+        while ring.total <= since && !self.pushed.wait_until(&mut ring, deadline).timed_out() {}
+
+        let oldest = ring.total - ring.bytes.len() as u64;
+        let from = since.max(oldest);
+        SessionRead {
+            data: ring.bytes.range((from - oldest) as usize..).copied().collect(),
+            offset: ring.total,
+            skipped: from - since,
+        }
+    }
+
+    /// The newest `count` lines as plain text, oldest first, each showing
+    /// only what was left after its last carriage-return redraw
+    pub fn lines(&self, count: usize) -> Vec<String> {
+        let ring = self.inner.lock();
+        if count == 0 || ring.bytes.is_empty() {
+            return Vec::new();
+        }
+        // A trailing newline ends the last line rather than starting an empty one
+        let terminated = ring.bytes.back() == Some(&b'\n');
+        let start = ring
+            .bytes
+            .range(..ring.bytes.len() - usize::from(terminated))
+            .enumerate()
+            .rev()
+            .filter(|(_, &b)| b == b'\n')
+            .nth(count - 1)
+            .map_or(0, |(at, _)| at + 1);
+        let bytes: Vec<u8> = ring.bytes.range(start..).copied().collect();
+        drop(ring);
+        let mut lines: Vec<String> = strip_escapes(&bytes).split('\n').map(str::to_string).collect();
+        if terminated {
+            lines.pop();
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_bar_is_one_line() {
+        let scrollback = Scrollback::new(DEFAULT_SCROLLBACK_BYTES);
+        scrollback.push(b"Downloading\r\n");
+        for percent in 0..=100 {
+            for _ in 0..20 {
+                scrollback.push(format!("\r[{:<50}] {}%", "#".repeat(percent / 2), percent).as_bytes());
+            }
+        }
+        scrollback.push(b"\r\ndone\r\n");
+        assert_eq!(scrollback.lines(usize::MAX).len(), 3);
+        assert_eq!(scrollback.lines(10), [
+            "Downloading".to_string(),
+            format!("[{}] 100%", "#".repeat(50)),
+            "done".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn redraw_takes_effect_once_written() {
+        let scrollback = Scrollback::new(DEFAULT_SCROLLBACK_BYTES);
+        scrollback.push(b"\x1b[32mspinner |\r");
+        assert_eq!(scrollback.lines(1), ["spinner |"]);
+        scrollback.push(b"spinner /");
+        assert_eq!(scrollback.lines(1), ["spinner /"]);
+    }
+
+    #[test]
+    fn lines_returns_the_newest() {
+        let scrollback = Scrollback::new(DEFAULT_SCROLLBACK_BYTES);
+        scrollback.push(b"one\ntwo\nthree");
+        assert_eq!(scrollback.lines(2), ["two", "three"]);
+        assert_eq!(scrollback.lines(0), Vec::<String>::new());
+        scrollback.push(b"\n");
+        assert_eq!(scrollback.lines(5), ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn reads_since_an_offset() {
+        let scrollback = Scrollback::new(8);
+        scrollback.push(b"hello");
+        let read = scrollback.read_since(0, Duration::ZERO);
+        assert_eq!((read.data.as_slice(), read.offset, read.skipped), (&b"hello"[..], 5, 0));
+
+        scrollback.push(b" world");
+        let read = scrollback.read_since(read.offset, Duration::ZERO);
+        assert_eq!((read.data.as_slice(), read.offset, read.skipped), (&b" world"[..], 11, 0));
+
+        // Only the newest 8 bytes are still there
+        let read = scrollback.read_since(0, Duration::ZERO);
+        assert_eq!((read.data.as_slice(), read.offset, read.skipped), (&b"lo world"[..], 11, 3));
+
+        scrollback.clear();
+        let read = scrollback.read_since(11, Duration::ZERO);
+        assert!(read.data.is_empty());
+        assert_eq!(read.offset, 11);
+    }
+
+    #[test]
+    fn read_waits_for_output() {
+        let scrollback = std::sync::Arc::new(Scrollback::new(DEFAULT_SCROLLBACK_BYTES));
+        let writer = std::thread::spawn({
+            let scrollback = scrollback.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(50));
+                scrollback.push(b"late");
+            }
+        });
+        let read = scrollback.read_since(0, Duration::from_secs(10));
+        assert_eq!(read.data, b"late");
+        writer.join().unwrap();
+
+        let started = Instant::now();
+        assert!(scrollback.read_since(read.offset, Duration::from_millis(50)).data.is_empty());
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn max_lines_drops_the_oldest() {
+        let scrollback = Scrollback::new(DEFAULT_SCROLLBACK_BYTES);
+        scrollback.set_max_lines(Some(2));
+        scrollback.push(b"one\ntwo\nthree\n");
+        assert_eq!(scrollback.contents(), b"two\nthree\n");
+        for _ in 0..1000 {
+            scrollback.push(b"\rbar");
+        }
+        assert_eq!(scrollback.lines(usize::MAX).len(), 2);
+        assert_eq!(scrollback.lines(2), ["three", "bar"]);
+
+        scrollback.set_max_lines(Some(0));
+        assert!(scrollback.contents().is_empty());
+    }
+
+    #[test]
+    fn byte_cap_keeps_line_count_right() {
+        let scrollback = Scrollback::new(8);
+        scrollback.push(b"a\nb\nc\nd\ne\n");
+        assert_eq!(scrollback.contents(), b"b\nc\nd\ne\n");
+        assert_eq!(scrollback.lines(usize::MAX).len(), 4);
+        scrollback.set_capacity(3);
+        assert_eq!(scrollback.lines(usize::MAX).len(), 2);
+    }
+}
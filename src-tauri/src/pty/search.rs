@@ -0,0 +1,266 @@
+use regex::{Regex, RegexBuilder};
+use std::sync::Arc;
+use std::thread;
+
+use super::scrollback::Scrollback;
+
+/// Only the newest this-many bytes of each session's scrollback are searched
+pub const MAX_SEARCH_BYTES: usize = 256 * 1024;
+
+/// Snippets longer than this are cut down around the match
+const MAX_SNIPPET_CHARS: usize = 160;
+
+/// Most matches `search_all` returns across all sessions
+pub const MAX_TOTAL_MATCHES: usize = 1000;
+
+/// A session matched by `search`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub name_matched: bool,
+    /// The newest output line containing the query, without escape sequences
+    pub snippet: Option<String>,
+}
+
+/// One match found by `search_all`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OutputMatch {
+    /// Line of the searched output, counted from the oldest retained line
+    pub line: usize,
+    /// Character offset of the match in that line
+    pub column: usize,
+    /// The line around the match, without escape sequences
+    pub snippet: String,
+}
+
+/// A session's matches from `search_all`, oldest first
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionMatches {
+    pub id: String,
+    pub matches: Vec<OutputMatch>,
+    /// Older matches were left out to stay within `MAX_TOTAL_MATCHES`
+    pub truncated: bool,
+}
+
+/// What `search` needs from each session, gathered while the manager is locked
+pub struct SearchTarget {
+    pub id: String,
+    pub name: String,
+    pub scrollback: Arc<Scrollback>,
+}
+
+/// Case-insensitively find `query` in session names and retained output
+pub fn search(targets: Vec<SearchTarget>, query: &str) -> Vec<SearchHit> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits: Vec<SearchHit> = targets
+        .into_iter()
+        .filter_map(|target| {
+            let name_matched = target.name.to_lowercase().contains(&query);
+            let output = target.scrollback.tail(MAX_SEARCH_BYTES);
+            let snippet = strip_escapes(&output)
+                .lines()
+                .rev()
+                .find_map(|line| snippet(line, &query));
+            (name_matched || snippet.is_some()).then_some(SearchHit {
+                id: target.id,
+                name_matched,
+                snippet,
+            })
+        })
+        .collect();
+    // Name matches are the likelier jump targets
+    hits.sort_by_key(|hit| !hit.name_matched);
+    hits
+}
+
+/// Find `pattern` in every session's retained output, as text
+/// (case-insensitively) or as a regex, searching sessions in parallel.
+///
+/// Sessions without matches are left out. Past `MAX_TOTAL_MATCHES`, the
+/// oldest matches of the later sessions are dropped.
+pub fn search_all(targets: Vec<SearchTarget>, pattern: &str, regex: bool) -> Result<Vec<SessionMatches>, String> {
+    if pattern.is_empty() {
+        return Ok(Vec::new());
+    }
+    let matcher = if regex {
+        Regex::new(pattern)
+    } else {
+        RegexBuilder::new(&regex::escape(pattern)).case_insensitive(true).build()
+    }
+    .map_err(|e| format!("Invalid pattern: {}", e))?;
+
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(targets.len().max(1));
+    let chunk = targets.len().div_ceil(workers).max(1);
+    let found: Vec<Vec<OutputMatch>> = thread::scope(|scope| {
+        let handles: Vec<_> = targets
+            .chunks(chunk)
+            .map(|chunk| {
+                let matcher = &matcher;
+                scope.spawn(move || chunk.iter().map(|target| find_matches(target, matcher)).collect::<Vec<_>>())
+            })
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("search worker panicked")).collect()
+    });
+
+    let mut budget = MAX_TOTAL_MATCHES;
+    let mut results = Vec::new();
+    for (target, mut matches) in targets.into_iter().zip(found) {
+        if matches.is_empty() || budget == 0 {
+            continue;
+        }
+        let truncated = matches.len() > budget;
+        matches.drain(..matches.len().saturating_sub(budget));
+        budget -= matches.len();
+        results.push(SessionMatches {
+            id: target.id,
+            matches,
+            truncated,
+        });
+    }
+    Ok(results)
+}
+
+/// The newest `MAX_TOTAL_MATCHES` matches in one session's output
+fn find_matches(target: &SearchTarget, matcher: &Regex) -> Vec<OutputMatch> {
+    let output = strip_escapes(&target.scrollback.tail(MAX_SEARCH_BYTES));
+    let mut matches = Vec::new();
+    for (index, line) in output.lines().enumerate() {
+        for found in matcher.find_iter(line) {
+            let column = line[..found.start()].chars().count();
+            matches.push(OutputMatch {
+                line: index,
+                column,
+                snippet: excerpt(line, column),
+            });
+        }
+    }
+    matches.drain(..matches.len().saturating_sub(MAX_TOTAL_MATCHES));
+    matches
+}
+
+/// `line`, trimmed to an excerpt around the match, if it contains `query`
+fn snippet(line: &str, query: &str) -> Option<String> {
+    let line = line.trim();
+    let lower = line.to_lowercase();
+    let at = lower.find(query)?;
+    // Lowercasing can change byte offsets, so place the window by characters
+    Some(excerpt(line, lower[..at].chars().count()))
+}
+
+/// `line`, cut down to a window around the match starting at character `column`
+fn excerpt(line: &str, column: usize) -> String {
+    if line.chars().count() <= MAX_SNIPPET_CHARS {
+        return line.to_string();
+    }
+    let start = column.saturating_sub(MAX_SNIPPET_CHARS / 2);
+    line.chars().skip(start).take(MAX_SNIPPET_CHARS).collect()
+}
+
+/// Output as plain text: escape sequences and control characters other than
+/// newlines removed, and a carriage return discarding what it overwrites
+pub fn strip_escapes(data: &[u8]) -> String {
+    let text = String::from_utf8_lossy(data);
+    let mut out = String::with_capacity(text.len());
+    let mut line_start = 0;
+    // A carriage return was seen; the line is redrawn once something is written
+    let mut redraw = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters up to a final byte
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC/DCS/APC/PM: up to BEL or ST
+                Some(']' | 'P' | '_' | '^') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\n' => {
+                out.push('\n');
+                line_start = out.len();
+                redraw = false;
+            }
+            '\r' => redraw = true,
+            c if c.is_control() && c != '\t' => {}
+            c => {
+                if redraw {
+                    out.truncate(line_start);
+                    redraw = false;
+                }
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(id: &str, output: &[u8]) -> SearchTarget {
+        let scrollback = Arc::new(Scrollback::new(MAX_SEARCH_BYTES));
+        scrollback.push(output);
+        SearchTarget {
+            id: id.to_string(),
+            name: id.to_string(),
+            scrollback,
+        }
+    }
+
+    #[test]
+    fn groups_matches_by_session() {
+        let targets = vec![
+            target("a", b"ok\r\n\x1b[31mError\x1b[0m: disk full\r\nerror again\r\n"),
+            target("b", b"nothing here\r\n"),
+            target("c", b"no ERROR"),
+        ];
+        let results = search_all(targets, "error", false).unwrap();
+        let ids: Vec<&str> = results.iter().map(|session| session.id.as_str()).collect();
+        assert_eq!(ids, ["a", "c"]);
+        let found = |session: &SessionMatches| -> Vec<(usize, usize, String)> {
+            session.matches.iter().map(|m| (m.line, m.column, m.snippet.clone())).collect()
+        };
+        assert_eq!(found(&results[0]), [(1, 0, "Error: disk full".to_string()), (2, 0, "error again".to_string())]);
+        assert_eq!(found(&results[1]), [(0, 3, "no ERROR".to_string())]);
+    }
+
+    #[test]
+    fn searches_by_regex() {
+        let targets = vec![target("a", b"exit 1\nexit 23\nexit x\n")];
+        let results = search_all(targets, r"exit \d+$", true).unwrap();
+        let lines: Vec<usize> = results[0].matches.iter().map(|m| m.line).collect();
+        assert_eq!(lines, [0, 1]);
+
+        // Text searches don't treat the pattern as a regex
+        assert!(search_all(vec![target("a", b"exit 1")], r"\d", false).unwrap().is_empty());
+        assert!(search_all(vec![target("a", b"x")], "(", true).unwrap_err().starts_with("Invalid pattern"));
+    }
+
+    #[test]
+    fn bounds_the_total_matches() {
+        let many = "hit\n".repeat(MAX_TOTAL_MATCHES);
+        let targets = vec![target("a", b"hit\n"), target("b", many.as_bytes()), target("c", b"hit\n")];
+        let results = search_all(targets, "hit", false).unwrap();
+        let counts: Vec<(&str, usize, bool)> =
+            results.iter().map(|s| (s.id.as_str(), s.matches.len(), s.truncated)).collect();
+        assert_eq!(counts, [("a", 1, false), ("b", MAX_TOTAL_MATCHES - 1, true)]);
+        // The newest are kept
+        assert_eq!(results[1].matches.last().unwrap().line, MAX_TOTAL_MATCHES - 1);
+    }
+}
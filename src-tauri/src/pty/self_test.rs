@@ -0,0 +1,159 @@
+use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the echoed marker before failing the read step
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Output the test waits for. The command splits it with quotes so the
+/// shell's echo of the typed command can't match.
+const MARKER: &str = "shelltree-ok";
+const MARKER_COMMAND: &str = "echo shelltree-''ok\n";
+
+/// Outcome of one step of the PTY self-test
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStep {
+    pub name: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Report returned by the PTY self-test
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub success: bool,
+    pub shell: String,
+    pub steps: Vec<SelfTestStep>,
+}
+
+struct Steps(Vec<SelfTestStep>);
+
+impl Steps {
+    /// Time a step and record its outcome; returns the value on success
+    fn run<T>(&mut self, name: &str, f: impl FnOnce() -> Result<T, String>) -> Option<T> {
+        let start = Instant::now();
+        let result = f();
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let (value, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+        self.0.push(SelfTestStep {
+            name: name.to_string(),
+            success: error.is_none(),
+            duration_ms,
+            error,
+        });
+        value
+    }
+}
+
+/// Spawn a throwaway shell and exercise spawn → write → read → resize → kill.
+///
+/// Runs on its own PTY, outside `PtyManager`, and never touches persisted state.
+pub fn run_self_test() -> SelfTestReport {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+    let mut steps = Steps(Vec::new());
+    let success = run_steps(&shell, &mut steps).is_some();
+
+    SelfTestReport {
+        success,
+        shell,
+        steps: steps.0,
+    }
+}
+
+fn run_steps(shell: &str, steps: &mut Steps) -> Option<()> {
+    let pair = steps.run("open_pty", || {
+        native_pty_system()
+            .openpty(PtySize {
+                rows: 24,
+                cols: 80,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| format!("Failed to open PTY: {}", e))
+    })?;
+
+    let mut child = steps.run("spawn_shell", || {
+        let mut cmd = CommandBuilder::new(shell);
+        cmd.env("TERM", "xterm-256color");
+        pair.slave
+            .spawn_command(cmd)
+            .map_err(|e| format!("Failed to spawn shell: {}", e))
+    })?;
+
+    // Run the remaining steps, then always clean up the child
+    let result = (|| {
+        let rx = steps.run("write", || {
+            let mut reader = pair
+                .master
+                .try_clone_reader()
+                .map_err(|e| format!("Failed to clone reader: {}", e))?;
+            let (tx, rx) = mpsc::channel::<Vec<u8>>();
+            thread::spawn(move || {
+                let mut buffer = [0u8; 4096];
+                while let Ok(n) = reader.read(&mut buffer) {
+                    if n == 0 || tx.send(buffer[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let mut writer = pair
+                .master
+                .take_writer()
+                .map_err(|e| format!("Failed to get writer: {}", e))?;
+            writer
+                .write_all(MARKER_COMMAND.as_bytes())
+                .and_then(|_| writer.flush())
+                .map_err(|e| format!("Write error: {}", e))?;
+            Ok(rx)
+        })?;
+
+        steps.run("read", || {
+            let deadline = Instant::now() + READ_TIMEOUT;
+            let mut output = Vec::new();
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match rx.recv_timeout(remaining) {
+                    Ok(chunk) => {
+                        output.extend_from_slice(&chunk);
+                        if String::from_utf8_lossy(&output).contains(MARKER) {
+                            return Ok(());
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        return Err(format!("Timed out waiting for '{}' in output", MARKER));
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        return Err("Shell closed the PTY before printing the marker".to_string());
+                    }
+                }
+            }
+        })?;
+
+        steps.run("resize", || {
+            pair.master
+                .resize(PtySize {
+                    rows: 40,
+                    cols: 120,
+                    pixel_width: 0,
+                    pixel_height: 0,
+                })
+                .map_err(|e| format!("Resize error: {}", e))
+        })
+    })();
+
+    let killed = steps.run("kill", || {
+        child.kill().map_err(|e| format!("Kill error: {}", e))?;
+        child.wait().map_err(|e| format!("Wait error: {}", e))?;
+        Ok(())
+    });
+
+    result.and(killed)
+}
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+use super::dotenv::EnvFile;
+use super::resource_limits::ResourceLimits;
+
 /// Status of a terminal session
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type", content = "message")]
@@ -10,19 +14,156 @@ pub enum SessionStatus {
     Error(String),
 }
 
+/// A command a session runs in place of a plain interactive shell
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionCommand {
+    pub command: String,
+    /// Drop to an interactive shell when the command exits instead of ending the session
+    #[serde(default)]
+    pub then_shell: bool,
+}
+
+/// What a session's PTY is connected to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(tag = "type")]
+pub enum SessionKind {
+    /// The local shell
+    #[default]
+    Local,
+    /// An `ssh` client, run locally over the PTY
+    Ssh {
+        host: String,
+        #[serde(default)]
+        user: Option<String>,
+        #[serde(default)]
+        port: Option<u16>,
+        #[serde(default)]
+        identity_file: Option<PathBuf>,
+    },
+}
+
+/// How a session came to exist
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(tag = "type", content = "source")]
+pub enum SessionOrigin {
+    /// Created directly by the user
+    #[default]
+    Manual,
+    /// Respawned from persisted state
+    Restored,
+    /// Expanded from a named template/profile
+    Template(String),
+    /// Opened from an SSH profile (profile id)
+    SshProfile(String),
+    /// Copied from another session (source session id)
+    Duplicate(String),
+}
+
+/// How `delete_session` closes a session
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum CloseBehavior {
+    /// Kill the shell immediately
+    #[default]
+    Kill,
+    /// Send `exit` and give the shell time to log out before killing it
+    Logout,
+    /// Refuse to close without an explicit force flag
+    Confirm,
+}
+
 /// A terminal session's metadata (serializable for persistence)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionInfo {
     pub id: String,
     pub name: String,
+    /// Set once the user renames the session; until then terminal titles rename it
+    #[serde(default)]
+    pub name_is_custom: bool,
     pub group_id: Option<String>,
+    /// Position among the sessions sharing `group_id` (ungrouped sessions count as one group)
+    #[serde(default)]
+    pub order: i32,
+    /// Local shell; `Ssh` sessions run the `ssh` client in its place
     pub shell: String,
+    #[serde(default)]
+    pub kind: SessionKind,
+    /// Working directory; follows the shell's OSC 7 reports while it runs
     pub cwd: PathBuf,
+    /// Arguments passed to the shell verbatim; `None` means `-l` (login shell)
+    /// on Unix and no arguments on Windows, and `Some(vec![])` starts a bare shell
+    #[serde(default)]
+    pub shell_args: Option<Vec<String>>,
     pub status: SessionStatus,
+    /// OS pid of the session's shell while it runs; `None` before spawn, after
+    /// exit, or where the platform doesn't report one
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// Last PTY size, so a restored session comes back at the size it had
+    #[serde(default = "default_rows")]
+    pub rows: u16,
+    #[serde(default = "default_cols")]
+    pub cols: u16,
+    /// Exit code of the shell once it has exited; cleared when it is spawned again
+    #[serde(default)]
+    pub exit_code: Option<u32>,
     pub created_at: i64,
-    /// Optional startup command to run when session is created/restored (e.g., "ssh user@host")
+    /// Unix ms of the session's last output (or its creation, before any)
+    #[serde(default)]
+    pub last_activity: i64,
+    /// Commands typed into the shell, in order, when the session is created/restored (e.g., "ssh user@host")
+    #[serde(default)]
+    pub startup_commands: Vec<String>,
+    /// How long to let the shell start before the first startup command; unset means 500ms
     #[serde(default)]
-    pub startup_command: Option<String>,
+    pub startup_delay_ms: Option<u64>,
+    /// Opaque scroll position owned by the frontend, round-tripped through persistence
+    #[serde(default)]
+    pub scroll_position: Option<serde_json::Value>,
+    /// Optional input rate limit in bytes per second (None = unlimited)
+    #[serde(default)]
+    pub input_rate_limit: Option<u64>,
+    /// Opaque keyboard-shortcut label interpreted by the frontend (e.g. "Cmd+1")
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    /// CPU/memory caps, re-applied when the session is spawned again
+    #[serde(default)]
+    pub resource_limits: Option<ResourceLimits>,
+    #[serde(default)]
+    pub close_behavior: CloseBehavior,
+    #[serde(default)]
+    pub origin: SessionOrigin,
+    /// Command run by the shell at spawn (vs. `startup_commands`, which are typed into it)
+    #[serde(default)]
+    pub command: Option<SessionCommand>,
+    /// TERM to advertise instead of the default. A running process can't see a
+    /// changed TERM, so this only takes effect when the shell is next spawned.
+    #[serde(default)]
+    pub term_override: Option<String>,
+    /// Dotenv file loaded into the environment at each spawn
+    #[serde(default)]
+    pub env_file: Option<EnvFile>,
+    /// Variables set for this session on top of everything else, including TERM
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// CSS color for the session's tab; a hex color set explicitly, or derived from its project
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Free-form labels for filtering, trimmed and unique
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Listed ahead of unpinned sessions by `get_all_sessions_sorted`
+    #[serde(default)]
+    pub pinned: bool,
+    /// Session that restore starts (and gives a moment to come up) before this one;
+    /// a dependency that no longer exists is ignored
+    #[serde(default)]
+    pub depends_on: Option<String>,
+    /// Scratch session: never persisted, and removed rather than kept when it exits
+    #[serde(default)]
+    pub ephemeral: bool,
+    /// Loaded from another machine's state and its cwd or shell is missing here
+    #[serde(default)]
+    pub needs_review: bool,
 }
 
 impl SessionInfo {
@@ -30,16 +171,63 @@ impl SessionInfo {
         Self {
             id,
             name,
+            name_is_custom: false,
             group_id: None,
+            order: 0,
             shell,
+            kind: SessionKind::default(),
             cwd,
+            shell_args: None,
             status: SessionStatus::Running,
+            pid: None,
+            rows: default_rows(),
+            cols: default_cols(),
+            exit_code: None,
             created_at: chrono::Utc::now().timestamp(),
-            startup_command: None,
+            last_activity: chrono::Utc::now().timestamp_millis(),
+            startup_commands: Vec::new(),
+            startup_delay_ms: None,
+            scroll_position: None,
+            input_rate_limit: None,
+            hotkey: None,
+            resource_limits: None,
+            close_behavior: CloseBehavior::default(),
+            origin: SessionOrigin::default(),
+            command: None,
+            term_override: None,
+            env_file: None,
+            env: BTreeMap::new(),
+            color: None,
+            tags: Vec::new(),
+            pinned: false,
+            depends_on: None,
+            ephemeral: false,
+            needs_review: false,
         }
     }
 }
 
+fn default_rows() -> u16 {
+    24
+}
+
+fn default_cols() -> u16 {
+    80
+}
+
+/// Keys for server-side session sorting
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SortKey {
+    Name,
+    CreatedAt,
+    /// By the time of the last output, oldest first
+    LastActivity,
+    /// By the owning group's order, then by order within the group; ungrouped sessions sort last
+    Group,
+    /// By shell pid; sessions without one sort last
+    Pid,
+}
+
 /// A group for organizing terminal sessions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionGroup {
@@ -47,6 +235,20 @@ pub struct SessionGroup {
     pub name: String,
     pub collapsed: bool,
     pub order: i32,
+    /// Opaque keyboard-shortcut label interpreted by the frontend (e.g. "Cmd+1")
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    /// Startup command for new sessions in this group that don't specify one
+    #[serde(default)]
+    pub default_startup_command: Option<String>,
+    /// Opaque display-layout preference stored for the frontend (e.g. "grid")
+    #[serde(default)]
+    pub layout_hint: Option<String>,
+    /// Hex color for the group's header
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl SessionGroup {
@@ -57,14 +259,103 @@ impl SessionGroup {
             name,
             collapsed: false,
             order: 0,
+            hotkey: None,
+            default_startup_command: None,
+            layout_hint: None,
+            color: None,
+            tags: Vec::new(),
         }
     }
 }
 
+/// A reusable session configuration, expanded by `create_session_from_profile`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    /// `None` uses the default shell
+    #[serde(default)]
+    pub shell: Option<String>,
+    /// Same meaning as `SessionInfo::shell_args`
+    #[serde(default)]
+    pub args: Option<Vec<String>>,
+    /// `None` uses the default working directory
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub startup_commands: Vec<String>,
+}
+
+/// What happens to the active session pointer when that session dies
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum ActiveSessionFallback {
+    /// Leave nothing active
+    Clear,
+    /// Move focus to the previous (or next) session in the same group
+    #[default]
+    Neighbor,
+}
+
+/// How `Settings::path_dirs` are combined with the inherited PATH
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum PathMode {
+    #[default]
+    Prepend,
+    Append,
+    Replace,
+}
+
+/// User-configurable backend settings
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Settings {
+    #[serde(default)]
+    pub active_session_fallback: ActiveSessionFallback,
+    /// Extra PATH directories for spawned sessions (fixes minimal PATHs when launched from the GUI)
+    #[serde(default)]
+    pub path_dirs: Vec<String>,
+    #[serde(default)]
+    pub path_mode: PathMode,
+    /// Output kept per session for `get_session_scrollback`; unset means
+    /// `DEFAULT_SCROLLBACK_BYTES`, 0 keeps none
+    #[serde(default)]
+    pub scrollback_bytes: Option<usize>,
+    /// Lines of output kept per session, a `\r`-redrawn line counting once;
+    /// unset means only `scrollback_bytes` applies
+    #[serde(default)]
+    pub scrollback_lines: Option<usize>,
+    /// Size a session log may reach before it's rotated; unset means logs aren't rotated
+    #[serde(default)]
+    pub log_max_bytes: Option<u64>,
+    /// Rotated logs kept per session; unset means `DEFAULT_LOG_MAX_FILES`
+    #[serde(default)]
+    pub log_max_files: Option<usize>,
+    /// Most sessions that may have a shell spawned at once; unset means no limit
+    #[serde(default)]
+    pub max_sessions: Option<usize>,
+    /// Listen for scripted commands on a Unix socket in the app data dir
+    #[serde(default)]
+    pub control_socket: bool,
+}
+
+/// Schema version written into `AppState::version`
+pub const STATE_VERSION: u32 = 2;
+
 /// The persisted application state
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppState {
+    /// Schema version the state was written with; files from before versioning are 0
+    #[serde(default)]
+    pub version: u32,
     pub sessions: Vec<SessionInfo>,
     pub groups: Vec<SessionGroup>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
     pub active_session_id: Option<String>,
+    #[serde(default)]
+    pub settings: Settings,
+    /// Machine the state was saved on
+    #[serde(default)]
+    pub hostname: Option<String>,
 }
@@ -0,0 +1,153 @@
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Rotated logs kept per session when `Settings::log_max_files` isn't set
+pub const DEFAULT_LOG_MAX_FILES: usize = 5;
+
+/// A session's transcript file. Once it would grow past `max_bytes` it's
+/// rotated: `log` becomes `log.1`, `log.1` becomes `log.2` and so on, keeping
+/// at most `max_files` old logs.
+pub struct SessionLog {
+    path: PathBuf,
+    file: File,
+    /// Size of the current file
+    written: u64,
+    /// Unset means the file grows without rotating
+    max_bytes: Option<u64>,
+    max_files: usize,
+}
+
+impl SessionLog {
+    /// Open `path` for appending, counting what's already in it toward the cap
+    pub fn open(path: PathBuf, max_bytes: Option<u64>, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_bytes,
+            max_files,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Change the limits; they apply from the next write
+    pub fn set_limits(&mut self, max_bytes: Option<u64>, max_files: usize) {
+        self.max_bytes = max_bytes;
+        self.max_files = max_files;
+    }
+
+    /// Append output, rotating first if it would take the file past the cap.
+    /// A single write bigger than the cap still goes into one file.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self
+            .max_bytes
+            .is_some_and(|max| self.written > 0 && self.written + data.len() as u64 > max)
+        {
+            self.rotate()?;
+        }
+        self.file.write_all(data)?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+
+    /// Move the current file aside and start an empty one; with `max_files`
+    /// of 0 the current file is just emptied
+    pub fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files > 0 {
+            remove_if_exists(&rotated_path(&self.path, self.max_files))?;
+            for n in (1..self.max_files).rev() {
+                rename_if_exists(&rotated_path(&self.path, n), &rotated_path(&self.path, n + 1))?;
+            }
+            rename_if_exists(&self.path, &rotated_path(&self.path, 1))?;
+        }
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// `path` with `.n` appended to its file name
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A log path in a fresh directory under the system temp dir
+    fn log_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shelltree-log-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("session.log")
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn rotates_past_the_cap() {
+        let path = log_path();
+        let mut log = SessionLog::open(path.clone(), Some(10), 2).unwrap();
+        for chunk in ["aaaaaa", "bbbbbb", "cccccc", "dddddd"] {
+            log.write(chunk.as_bytes()).unwrap();
+        }
+        assert_eq!(read(&path), "dddddd");
+        assert_eq!(read(&rotated_path(&path, 1)), "cccccc");
+        assert_eq!(read(&rotated_path(&path, 2)), "bbbbbb");
+        assert!(!rotated_path(&path, 3).exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn counts_existing_content() {
+        let path = log_path();
+        fs::write(&path, "12345678").unwrap();
+        let mut log = SessionLog::open(path.clone(), Some(10), 1).unwrap();
+        log.write(b"abc").unwrap();
+        assert_eq!(read(&path), "abc");
+        assert_eq!(read(&rotated_path(&path, 1)), "12345678");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rotates_on_request() {
+        let path = log_path();
+        let mut log = SessionLog::open(path.clone(), None, DEFAULT_LOG_MAX_FILES).unwrap();
+        log.write(b"first").unwrap();
+        log.rotate().unwrap();
+        log.write(b"second").unwrap();
+        assert_eq!(read(&path), "second");
+        assert_eq!(read(&rotated_path(&path, 1)), "first");
+
+        log.set_limits(None, 0);
+        log.rotate().unwrap();
+        assert_eq!(read(&path), "");
+        assert_eq!(read(&rotated_path(&path, 1)), "first");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,94 @@
+use parking_lot::Mutex;
+use std::io::{self, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use super::output_sinks::OutputSink;
+
+/// A Unix socket serving a session's live output to any connected client
+pub struct SessionSocket {
+    path: PathBuf,
+    sink_id: String,
+    stopped: Arc<AtomicBool>,
+}
+
+impl SessionSocket {
+    /// Listen on `path`; returns the socket and the sink that feeds its clients
+    pub fn bind(path: PathBuf, sink_id: String) -> Result<(Self, SocketSink), String> {
+        // A socket file left by a crashed run can't be bound again; only
+        // replace it if nothing is listening there
+        if path.exists() && UnixStream::connect(&path).is_err() {
+            let _ = std::fs::remove_file(&path);
+        }
+        let listener = UnixListener::bind(&path)
+            .map_err(|e| format!("Failed to bind socket {}: {}", path.display(), e))?;
+
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let stopped = Arc::new(AtomicBool::new(false));
+        thread::spawn({
+            let clients = clients.clone();
+            let stopped = stopped.clone();
+            move || accept_clients(listener, clients, stopped)
+        });
+
+        let socket = Self {
+            path,
+            sink_id,
+            stopped,
+        };
+        Ok((socket, SocketSink { clients }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Id of the sink registered for this socket in the session's `SessionSinks`
+    pub fn sink_id(&self) -> &str {
+        &self.sink_id
+    }
+}
+
+impl Drop for SessionSocket {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // Wake the accept loop so it sees the flag
+        let _ = UnixStream::connect(&self.path);
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn accept_clients(listener: UnixListener, clients: Arc<Mutex<Vec<UnixStream>>>, stopped: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if stopped.load(Ordering::Relaxed) {
+            break;
+        }
+        match stream {
+            // Non-blocking so a stalled client can never hold up the reader thread
+            Ok(stream) => {
+                if stream.set_nonblocking(true).is_ok() {
+                    clients.lock().push(stream);
+                }
+            }
+            Err(e) => eprintln!("Socket accept error: {}", e),
+        }
+    }
+}
+
+/// Output sink that copies output to every connected socket client
+pub struct SocketSink {
+    clients: Arc<Mutex<Vec<UnixStream>>>,
+}
+
+impl OutputSink for SocketSink {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        // Clients that disconnect or can't keep up are dropped; the session carries on
+        self.clients
+            .lock()
+            .retain_mut(|client| client.write_all(data).is_ok());
+        Ok(())
+    }
+}
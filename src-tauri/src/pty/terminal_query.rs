@@ -0,0 +1,97 @@
+use serde::Serialize;
+
+/// Longest partial escape sequence kept between reads
+const MAX_CARRY: usize = 32;
+
+/// A request a program sent to the terminal that expects a reply on stdin
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum TerminalQuery {
+    /// DSR 5 (`CSI 5 n`)
+    DeviceStatus,
+    /// DSR 6 / DECXCPR (`CSI 6 n`, `CSI ? 6 n`)
+    CursorPosition,
+    /// Primary DA (`CSI c`, `CSI 0 c`)
+    PrimaryDeviceAttributes,
+    /// Secondary DA (`CSI > c`, `CSI > 0 c`)
+    SecondaryDeviceAttributes,
+    /// XTWINOPS 14 (`CSI 14 t`)
+    WindowSizePixels,
+    /// XTWINOPS 18 (`CSI 18 t`)
+    WindowSizeChars,
+    /// XTWINOPS 19 (`CSI 19 t`)
+    ScreenSizeChars,
+}
+
+/// Scans PTY output for terminal queries, handling sequences split across reads
+#[derive(Default)]
+pub struct QueryScanner {
+    carry: Vec<u8>,
+}
+
+impl QueryScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop any partial sequence (e.g. when detection is switched off)
+    pub fn reset(&mut self) {
+        self.carry.clear();
+    }
+
+    /// Scan a chunk of output and return the queries it completes
+    pub fn scan(&mut self, data: &[u8]) -> Vec<TerminalQuery> {
+        let mut buf = std::mem::take(&mut self.carry);
+        buf.extend_from_slice(data);
+
+        let mut queries = Vec::new();
+        let mut i = 0;
+        while i < buf.len() {
+            if buf[i] != 0x1b {
+                i += 1;
+                continue;
+            }
+            if i + 1 >= buf.len() {
+                self.hold(&buf[i..]);
+                break;
+            }
+            if buf[i + 1] != b'[' {
+                i += 1;
+                continue;
+            }
+
+            // Parameter and intermediate bytes, then a single final byte
+            let mut j = i + 2;
+            while j < buf.len() && (0x20..=0x3f).contains(&buf[j]) {
+                j += 1;
+            }
+            if j >= buf.len() {
+                self.hold(&buf[i..]);
+                break;
+            }
+            if let Some(query) = classify(&buf[i + 2..j], buf[j]) {
+                queries.push(query);
+            }
+            i = j + 1;
+        }
+        queries
+    }
+
+    fn hold(&mut self, partial: &[u8]) {
+        if partial.len() <= MAX_CARRY {
+            self.carry.extend_from_slice(partial);
+        }
+    }
+}
+
+fn classify(params: &[u8], final_byte: u8) -> Option<TerminalQuery> {
+    match (final_byte, params) {
+        (b'n', b"5") => Some(TerminalQuery::DeviceStatus),
+        (b'n', b"6") | (b'n', b"?6") => Some(TerminalQuery::CursorPosition),
+        (b'c', b"") | (b'c', b"0") => Some(TerminalQuery::PrimaryDeviceAttributes),
+        (b'c', b">") | (b'c', b">0") => Some(TerminalQuery::SecondaryDeviceAttributes),
+        (b't', b"14") => Some(TerminalQuery::WindowSizePixels),
+        (b't', b"18") => Some(TerminalQuery::WindowSizeChars),
+        (b't', b"19") => Some(TerminalQuery::ScreenSizeChars),
+        _ => None,
+    }
+}
@@ -0,0 +1,119 @@
+use portable_pty::MasterPty;
+use serde::Serialize;
+
+/// Whether the PTY line discipline currently echoes input
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum EchoState {
+    On,
+    Off,
+    /// Not available on this platform or the PTY is gone
+    Unknown,
+}
+
+/// Read the echo state straight from a session's master PTY
+pub fn echo_state(master: &dyn MasterPty) -> EchoState {
+    #[cfg(unix)]
+    {
+        use nix::sys::termios::LocalFlags;
+        match master.get_termios() {
+            Some(termios) if termios.local_flags.contains(LocalFlags::ECHO) => EchoState::On,
+            Some(_) => EchoState::Off,
+            None => EchoState::Unknown,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = master;
+        EchoState::Unknown
+    }
+}
+
+/// Watches a PTY's echo flag from the reader thread.
+///
+/// Holds its own duplicate of the master fd so it stays valid even after the
+/// session (and its `MasterPty`) has been dropped.
+pub struct EchoProbe {
+    #[cfg(unix)]
+    fd: Option<std::os::unix::io::RawFd>,
+}
+
+impl EchoProbe {
+    pub fn new(master: &dyn MasterPty) -> Self {
+        #[cfg(unix)]
+        {
+            let fd = master
+                .as_raw_fd()
+                .and_then(|fd| nix::unistd::dup(fd).ok());
+            Self { fd }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = master;
+            Self {}
+        }
+    }
+
+    pub fn state(&self) -> EchoState {
+        #[cfg(unix)]
+        {
+            use nix::sys::termios::{tcgetattr, LocalFlags};
+            match self.fd.map(tcgetattr) {
+                Some(Ok(termios)) if termios.local_flags.contains(LocalFlags::ECHO) => EchoState::On,
+                Some(Ok(_)) => EchoState::Off,
+                _ => EchoState::Unknown,
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            EchoState::Unknown
+        }
+    }
+}
+
+impl Drop for EchoProbe {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(fd) = self.fd.take() {
+            let _ = nix::unistd::close(fd);
+        }
+    }
+}
+
+/// Put a PTY's line discipline back into `stty sane` shape (echo, canonical
+/// input, signals, CR/NL translation). Returns false where termios isn't available.
+pub fn reset_termios(master: &dyn MasterPty) -> Result<bool, String> {
+    #[cfg(unix)]
+    {
+        use nix::sys::termios::{tcsetattr, InputFlags, LocalFlags, OutputFlags, SetArg};
+        let (Some(fd), Some(mut termios)) = (master.as_raw_fd(), master.get_termios()) else {
+            return Ok(false);
+        };
+
+        termios.input_flags.insert(InputFlags::BRKINT | InputFlags::ICRNL | InputFlags::IMAXBEL | InputFlags::IXON);
+        termios.input_flags.remove(
+            InputFlags::IGNBRK | InputFlags::INLCR | InputFlags::IGNCR | InputFlags::IXOFF | InputFlags::IXANY,
+        );
+        termios.output_flags.insert(OutputFlags::OPOST | OutputFlags::ONLCR);
+        termios.output_flags.remove(OutputFlags::OCRNL | OutputFlags::ONOCR | OutputFlags::ONLRET);
+        termios.local_flags.insert(
+            LocalFlags::ISIG
+                | LocalFlags::ICANON
+                | LocalFlags::IEXTEN
+                | LocalFlags::ECHO
+                | LocalFlags::ECHOE
+                | LocalFlags::ECHOK
+                | LocalFlags::ECHOCTL
+                | LocalFlags::ECHOKE,
+        );
+        termios.local_flags.remove(LocalFlags::ECHONL | LocalFlags::NOFLSH | LocalFlags::TOSTOP);
+
+        tcsetattr(fd, SetArg::TCSANOW, &termios)
+            .map(|_| true)
+            .map_err(|e| format!("Failed to reset terminal modes: {}", e))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = master;
+        Ok(false)
+    }
+}
@@ -0,0 +1,81 @@
+/// Holds back a UTF-8 character split across reads until the rest of it arrives.
+///
+/// Raw output goes to the frontend as read; only consumers that treat output
+/// as text run it through this first.
+#[derive(Default)]
+pub struct Utf8Carry {
+    partial: Vec<u8>,
+}
+
+impl Utf8Carry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prepend any held bytes to `data` and return everything up to an
+    /// unfinished character at the end, which is kept for the next call.
+    /// Invalid bytes elsewhere are passed through for the caller to deal with.
+    pub fn push(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut bytes = std::mem::take(&mut self.partial);
+        bytes.extend_from_slice(data);
+        let keep = incomplete_tail(&bytes);
+        self.partial = bytes.split_off(bytes.len() - keep);
+        bytes
+    }
+}
+
+/// Length of an unfinished multi-byte character at the end of `bytes`, if any
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    for len in 1..=3.min(bytes.len()) {
+        let start = bytes.len() - len;
+        if let Err(e) = std::str::from_utf8(&bytes[start..]) {
+            if e.valid_up_to() == 0 && e.error_len().is_none() {
+                return len;
+            }
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `text` split at every possible point and check it comes back whole
+    fn roundtrip_every_split(text: &str) {
+        let bytes = text.as_bytes();
+        for split in 0..=bytes.len() {
+            let mut carry = Utf8Carry::new();
+            let first = carry.push(&bytes[..split]);
+            assert!(std::str::from_utf8(&first).is_ok(), "split at {} gave {:?}", split, first);
+            let mut out = first;
+            out.extend(carry.push(&bytes[split..]));
+            assert_eq!(out, bytes);
+        }
+    }
+
+    #[test]
+    fn holds_back_split_characters() {
+        roundtrip_every_split("é");
+        roundtrip_every_split("a€b");
+        roundtrip_every_split("🦀 x");
+    }
+
+    #[test]
+    fn joins_a_character_fed_byte_by_byte() {
+        let mut carry = Utf8Carry::new();
+        let crab = "🦀".as_bytes();
+        for &byte in &crab[..3] {
+            assert_eq!(carry.push(&[byte]), b"");
+        }
+        assert_eq!(carry.push(&crab[3..]), crab);
+    }
+
+    #[test]
+    fn passes_invalid_bytes_through() {
+        let mut carry = Utf8Carry::new();
+        assert_eq!(carry.push(b"a\xffb"), b"a\xffb");
+        // A stray continuation byte can't start a character, so it isn't held
+        assert_eq!(carry.push(b"x\x80"), b"x\x80");
+    }
+}
@@ -2,7 +2,7 @@ import { useEffect, useRef, useCallback } from "react";
 import { Terminal } from "@xterm/xterm";
 import { FitAddon } from "@xterm/addon-fit";
 import { WebglAddon } from "@xterm/addon-webgl";
-import { writeToSession, resizeSession, onPtyOutput, onPtyExit } from "../../lib/tauri";
+import { writeToSession, resizeSession, onPtyOutput, onPtyExit, outputBytes } from "../../lib/tauri";
 import { useSessionStore } from "../../stores/sessionStore";
 
 export interface UseTerminalOptions {
@@ -111,7 +111,7 @@ export function useTerminal({ sessionId, onReady }: UseTerminalOptions) {
 
     onPtyOutput((output) => {
       if (output.id === sessionId && terminalRef.current) {
-        terminalRef.current.write(new Uint8Array(output.data));
+        terminalRef.current.write(outputBytes(output));
       }
     }).then((fn) => {
       unlisten = fn;
@@ -7,40 +7,370 @@ export type SessionStatus =
   | { type: "Stopped" }
   | { type: "Error"; message: string };
 
+export interface SessionCommand {
+  command: string;
+  then_shell: boolean;
+}
+
+export interface EnvFile {
+  path: string;
+  required: boolean;
+}
+
+export type SessionOrigin =
+  | { type: "Manual" }
+  | { type: "Restored" }
+  | { type: "Template"; source: string }
+  | { type: "SshProfile"; source: string }
+  | { type: "Duplicate"; source: string };
+
+export type CloseBehavior = "Kill" | "Logout" | "Confirm";
+
+export type SessionKind =
+  | { type: "Local" }
+  | {
+      type: "Ssh";
+      host: string;
+      user: string | null;
+      port: number | null;
+      identity_file: string | null;
+    };
+
+// error is null when the session was deleted
+export interface SessionDeleteResult {
+  id: string;
+  error: string | null;
+}
+
+export type DeleteOutcome =
+  | { type: "Deleted" }
+  | { type: "LoggingOut" }
+  | { type: "NeedsConfirmation" };
+
+export interface ResourceLimits {
+  cpu_limit: number | null;
+  memory_limit: number | null;
+}
+
 export interface SessionInfo {
   id: string;
   name: string;
+  name_is_custom: boolean;
   group_id: string | null;
+  order: number;
   shell: string;
+  kind: SessionKind;
   cwd: string;
+  shell_args: string[] | null;
   status: SessionStatus;
+  pid: number | null;
+  // Last PTY size
+  rows: number;
+  cols: number;
+  exit_code: number | null;
   created_at: number;
-  startup_command: string | null;
+  // Unix ms of the last output
+  last_activity: number;
+  startup_commands: string[];
+  startup_delay_ms: number | null;
+  scroll_position: unknown | null;
+  input_rate_limit: number | null;
+  hotkey: string | null;
+  resource_limits: ResourceLimits | null;
+  close_behavior: CloseBehavior;
+  origin: SessionOrigin;
+  command: SessionCommand | null;
+  term_override: string | null;
+  env_file: EnvFile | null;
+  env: Record<string, string>;
+  color: string | null;
+  tags: string[];
+  pinned: boolean;
+  // Restore starts this session after the one it depends on
+  depends_on: string | null;
+  ephemeral: boolean;
+  needs_review: boolean;
 }
 
+export type SortKey = "Name" | "CreatedAt" | "LastActivity" | "Group" | "Pid";
+
 export interface SessionGroup {
   id: string;
   name: string;
   collapsed: boolean;
   order: number;
+  hotkey: string | null;
+  default_startup_command: string | null;
+  layout_hint: string | null;
+  color: string | null;
+  tags: string[];
+}
+
+export interface Profile {
+  id: string;
+  name: string;
+  shell: string | null;
+  args: string[] | null;
+  cwd: string | null;
+  env: Record<string, string>;
+  startup_commands: string[];
+}
+
+// Per-invocation changes to a profile; env is merged over the profile's
+export interface ProfileOverrides {
+  name?: string;
+  shell?: string;
+  args?: string[];
+  cwd?: string;
+  env?: Record<string, string>;
+  startup_commands?: string[];
+  group_id?: string;
+}
+
+export type ActiveSessionFallback = "Clear" | "Neighbor";
+
+export type PathMode = "Prepend" | "Append" | "Replace";
+
+export interface Settings {
+  active_session_fallback: ActiveSessionFallback;
+  path_dirs: string[];
+  path_mode: PathMode;
+  scrollback_bytes: number | null;
+  scrollback_lines: number | null;
+  log_max_bytes: number | null;
+  log_max_files: number | null;
+  max_sessions: number | null;
+  control_socket: boolean;
 }
 
 export interface AppState {
+  version: number;
   sessions: SessionInfo[];
   groups: SessionGroup[];
+  profiles: Profile[];
   active_session_id: string | null;
+  settings: Settings;
+  hostname: string | null;
 }
 
-export interface PtyOutput {
+export interface PathCheck {
+  path: string;
+  exists: boolean;
+}
+
+// active: the workspace last saved or loaded
+export interface Workspace {
+  name: string;
+  saved_at: number;
+  active: boolean;
+}
+
+export interface LayoutPreview {
+  session_count: number;
+  group_count: number;
+  shells: PathCheck[];
+  directories: PathCheck[];
+  hostname: string | null;
+  warnings: string[];
+}
+
+export interface PersistenceHealth {
+  data_dir: string;
+  exists: boolean;
+  writable: boolean;
+  error: string | null;
+}
+
+export interface PersistenceError {
+  error: string;
+}
+
+export interface StateBackup {
+  name: string;
+  created_at: number;
+  size: number;
+}
+
+export type TapTarget = "File" | "Event";
+
+export interface TapInfo {
+  tap_id: string;
+  session_id: string;
+  path: string | null;
+}
+
+export interface PtyTapOutput {
+  tap_id: string;
   id: string;
   data: number[];
 }
 
+export interface ForegroundProcess {
+  pid: number;
+  name: string;
+}
+
+export interface ProcessNode {
+  pid: number;
+  ppid: number;
+  command: string;
+}
+
+export interface SessionMetrics {
+  id: string;
+  total_bytes: number;
+  bytes_per_sec: number;
+  // Unix ms of the last output
+  last_activity: number;
+}
+
+export interface ResourceUsage {
+  cpu_percent: number | null;
+  rss_bytes: number;
+  process_count: number;
+}
+
+export type EchoState = "On" | "Off" | "Unknown";
+
+export type Signal = "Interrupt" | "Terminate" | "Hangup" | "Quit" | "Kill";
+
+export interface PtyEchoChanged {
+  id: string;
+  echo: EchoState;
+}
+
+export interface PtyBell {
+  id: string;
+}
+
+// snippet is the newest matching output line, if the output matched
+export interface SearchHit {
+  id: string;
+  name_matched: boolean;
+  snippet: string | null;
+}
+
+export interface OutputMatch {
+  line: number;
+  column: number;
+  snippet: string;
+}
+
+// truncated means older matches were dropped to bound the result size
+export interface SessionMatches {
+  id: string;
+  matches: OutputMatch[];
+  truncated: boolean;
+}
+
+export interface SessionRead {
+  data: number[];
+  // Pass as sinceOffset to get only what follows
+  offset: number;
+  // Output after sinceOffset that had already been dropped from the scrollback
+  skipped: number;
+}
+
+export interface SessionLogError {
+  id: string;
+  path: string;
+  error: string;
+}
+
+export interface SessionCleared {
+  id: string;
+}
+
+export interface SessionRenamed {
+  id: string;
+  name: string;
+}
+
+// root is null once the shell has left every repository; branch is null for a detached HEAD
+export interface RepoContextChanged {
+  id: string;
+  root: string | null;
+  branch: string | null;
+}
+
+export interface InputPacingStats {
+  limit_bytes_per_sec: number | null;
+  pending_bytes: number;
+  paced_bytes_total: number;
+  active: boolean;
+}
+
+export interface SelfTestStep {
+  name: string;
+  success: boolean;
+  duration_ms: number;
+  error: string | null;
+}
+
+export interface SelfTestReport {
+  success: boolean;
+  shell: string;
+  steps: SelfTestStep[];
+}
+
+export interface StateHost {
+  saved_on: string | null;
+  current: string | null;
+  same_host: boolean;
+}
+
+export interface RestoreError {
+  session_id: string | null;
+  error: string;
+}
+
+export interface RestoreReport {
+  sessions_restored: number;
+  errors: RestoreError[];
+  elapsed_ms: number;
+}
+
+export interface GroupRestartReport {
+  restarted: SessionInfo[];
+  errors: RestoreError[];
+}
+
+export interface PingResult {
+  latency_ms: number;
+}
+
+export interface StateDirtyReport {
+  dirty: boolean;
+  changes: string[];
+}
+
+export type OutputEncoding = "Raw" | "Base64";
+
+export interface PtyOutput {
+  id: string;
+  // A base64 string when the output encoding is "Base64"
+  data: number[] | string;
+}
+
 export interface PtyExit {
   id: string;
   code: number | null;
 }
 
+export type TerminalQuery =
+  | "DeviceStatus"
+  | "CursorPosition"
+  | "PrimaryDeviceAttributes"
+  | "SecondaryDeviceAttributes"
+  | "WindowSizePixels"
+  | "WindowSizeChars"
+  | "ScreenSizeChars";
+
+export interface PtyTerminalQuery {
+  id: string;
+  query: TerminalQuery;
+}
+
 // Session commands
 export async function createSession(
   name: string,
@@ -49,7 +379,21 @@ export async function createSession(
   groupId?: string,
   startupCommand?: string,
   rows?: number,
-  cols?: number
+  cols?: number,
+  cpuLimit?: number,
+  memoryLimit?: number,
+  command?: string,
+  thenShell?: boolean,
+  term?: string,
+  envFile?: string,
+  envFileRequired?: boolean,
+  lazy?: boolean,
+  ephemeral?: boolean,
+  args?: string[],
+  env?: Record<string, string>,
+  startupCommands?: string[],
+  startupDelayMs?: number,
+  kind?: SessionKind
 ): Promise<SessionInfo> {
   return invoke("create_session", {
     name,
@@ -59,11 +403,58 @@ export async function createSession(
     startupCommand,
     rows,
     cols,
+    cpuLimit,
+    memoryLimit,
+    command,
+    thenShell,
+    term,
+    envFile,
+    envFileRequired,
+    lazy,
+    ephemeral,
+    args,
+    env,
+    startupCommands,
+    startupDelayMs,
+    kind,
   });
 }
 
-export async function deleteSession(id: string): Promise<void> {
-  return invoke("delete_session", { id });
+export async function duplicateSession(
+  id: string,
+  rows?: number,
+  cols?: number,
+  rerunStartupCommand?: boolean
+): Promise<SessionInfo> {
+  return invoke("duplicate_session", { id, rows, cols, rerunStartupCommand });
+}
+
+export async function restartSession(id: string): Promise<SessionInfo> {
+  return invoke("restart_session", { id });
+}
+
+export async function spawnPendingSession(
+  id: string,
+  rows?: number,
+  cols?: number
+): Promise<SessionInfo> {
+  return invoke("spawn_pending_session", { id, rows, cols });
+}
+
+export async function restoreSessions(): Promise<RestoreReport> {
+  return invoke("restore_sessions");
+}
+
+export async function deleteSession(id: string, force?: boolean): Promise<DeleteOutcome> {
+  return invoke("delete_session", { id, force });
+}
+
+export async function deleteSessions(ids: string[]): Promise<SessionDeleteResult[]> {
+  return invoke("delete_sessions", { ids });
+}
+
+export async function setCloseBehavior(id: string, behavior: CloseBehavior): Promise<void> {
+  return invoke("set_close_behavior", { id, behavior });
 }
 
 export async function renameSession(id: string, name: string): Promise<void> {
@@ -74,10 +465,148 @@ export async function writeToSession(id: string, data: Uint8Array): Promise<void
   return invoke("write_to_session", { id, data: Array.from(data) });
 }
 
+// For large pastes: one call and one flush for every chunk
+export async function writeToSessionBulk(id: string, chunks: Uint8Array[]): Promise<void> {
+  return invoke("write_to_session_bulk", { id, chunks: chunks.map((chunk) => Array.from(chunk)) });
+}
+
+// Wrapped in bracketed-paste markers when the program enabled them; resolves to whether it was
+export async function pasteToSession(id: string, data: Uint8Array): Promise<boolean> {
+  return invoke("paste_to_session", { id, data: Array.from(data) });
+}
+
 export async function resizeSession(id: string, rows: number, cols: number): Promise<void> {
   return invoke("resize_session", { id, rows, cols });
 }
 
+export async function getForegroundProcess(id: string): Promise<ForegroundProcess | null> {
+  return invoke("get_foreground_process", { id });
+}
+
+export async function getSessionProcessTree(id: string): Promise<ProcessNode[]> {
+  return invoke("get_session_process_tree", { id });
+}
+
+export async function getSessionResourceUsage(id: string): Promise<ResourceUsage | null> {
+  return invoke("get_session_resource_usage", { id });
+}
+
+export async function getSessionMetrics(id: string): Promise<SessionMetrics> {
+  return invoke("get_session_metrics", { id });
+}
+
+export async function getAllMetrics(): Promise<SessionMetrics[]> {
+  return invoke("get_all_metrics");
+}
+
+export async function shellSupportsIntegration(shellPath: string): Promise<boolean> {
+  return invoke("shell_supports_integration", { shellPath });
+}
+
+export async function findSessionsByProcess(name: string): Promise<string[]> {
+  return invoke("find_sessions_by_process", { name });
+}
+
+export async function getIdleSessions(idleMs: number): Promise<string[]> {
+  return invoke("get_idle_sessions", { idleMs });
+}
+
+export async function killSessionsByProcess(name: string): Promise<string[]> {
+  return invoke("kill_sessions_by_process", { name });
+}
+
+export async function tapSession(id: string, target?: TapTarget): Promise<TapInfo> {
+  return invoke("tap_session", { id, target });
+}
+
+export async function untapSession(tapId: string): Promise<void> {
+  return invoke("untap_session", { tapId });
+}
+
+export async function startLogging(id: string, path: string): Promise<void> {
+  return invoke("start_logging", { id, path });
+}
+
+export async function stopLogging(id: string): Promise<void> {
+  return invoke("stop_logging", { id });
+}
+
+// The current log becomes <path>.1, older ones shift up, and logging continues in a fresh file
+export async function rotateSessionLog(id: string): Promise<void> {
+  return invoke("rotate_session_log", { id });
+}
+
+export async function startRecording(id: string): Promise<void> {
+  return invoke("start_recording", { id });
+}
+
+// Writes an asciinema v2 cast to `path`
+export async function stopRecording(id: string, path: string): Promise<void> {
+  return invoke("stop_recording", { id, path });
+}
+
+export type HtmlTheme = "Dark" | "Light";
+
+// Renders the session's scrollback with its colors to a standalone HTML page
+export async function exportSessionHtml(id: string, path: string, theme?: HtmlTheme): Promise<void> {
+  return invoke("export_session_html", { id, path, theme });
+}
+
+export async function attachSessionSocket(id: string, path: string): Promise<void> {
+  return invoke("attach_session_socket", { id, path });
+}
+
+export async function detachSessionSocket(id: string): Promise<void> {
+  return invoke("detach_session_socket", { id });
+}
+
+export async function sendSignal(id: string, signal: Signal): Promise<void> {
+  return invoke("send_signal", { id, signal });
+}
+
+export async function sendEof(id: string): Promise<void> {
+  return invoke("send_eof", { id });
+}
+
+export async function resetSession(id: string): Promise<boolean> {
+  return invoke("reset_session", { id });
+}
+
+export async function getSessionScrollback(id: string): Promise<number[]> {
+  return invoke("get_session_scrollback", { id });
+}
+
+// Only for sessions without a running shell; live output after a respawn follows it
+export async function setSessionOutput(id: string, data: Uint8Array): Promise<void> {
+  return invoke("set_session_output", { id, data: Array.from(data) });
+}
+
+export async function readSession(id: string, sinceOffset: number, maxWaitMs?: number): Promise<SessionRead> {
+  return invoke("read_session", { id, sinceOffset, maxWaitMs });
+}
+
+// Plain text, with lines redrawn by carriage returns showing their final state
+export async function getSessionLines(id: string, count: number): Promise<string[]> {
+  return invoke("get_session_lines", { id, count });
+}
+
+export async function searchSessions(query: string): Promise<SearchHit[]> {
+  return invoke("search_sessions", { query });
+}
+
+// Matches grouped by session; line counts from the oldest retained output line
+export async function searchAllSessions(pattern: string, regex = false): Promise<SessionMatches[]> {
+  return invoke("search_all_sessions", { pattern, regex });
+}
+
+export async function clearSession(id: string): Promise<void> {
+  return invoke("clear_session", { id });
+}
+
+export async function getSessionEcho(id: string): Promise<EchoState> {
+  return invoke("get_session_echo", { id });
+}
+
 export async function getSession(id: string): Promise<SessionInfo | null> {
   return invoke("get_session", { id });
 }
@@ -86,14 +615,123 @@ export async function getAllSessions(): Promise<SessionInfo[]> {
   return invoke("get_all_sessions");
 }
 
+export async function getSessionsSorted(by: SortKey, descending = false): Promise<SessionInfo[]> {
+  return invoke("get_sessions_sorted", { by, descending });
+}
+
+// Pinned sessions first, then group order
+export async function getAllSessionsSorted(): Promise<SessionInfo[]> {
+  return invoke("get_all_sessions_sorted");
+}
+
+// Resolves to whether the session is now pinned
+export async function toggleSessionPinned(id: string): Promise<boolean> {
+  return invoke("toggle_session_pinned", { id });
+}
+
 export async function setSessionGroup(id: string, groupId: string | null): Promise<void> {
   return invoke("set_session_group", { id, groupId });
 }
 
+export async function moveGroupSessions(fromGroupId: string, toGroupId: string): Promise<string[]> {
+  return invoke("move_group_sessions", { fromGroupId, toGroupId });
+}
+
+export async function reorderSession(id: string, newOrder: number): Promise<SessionInfo[]> {
+  return invoke("reorder_session", { id, newOrder });
+}
+
 export async function setStartupCommand(id: string, command: string | null): Promise<void> {
   return invoke("set_startup_command", { id, command });
 }
 
+export async function setStartupCommands(
+  id: string,
+  commands: string[],
+  delayMs?: number
+): Promise<void> {
+  return invoke("set_startup_commands", { id, commands, delayMs });
+}
+
+export async function setInputRateLimit(id: string, bytesPerSec: number | null): Promise<void> {
+  return invoke("set_input_rate_limit", { id, bytesPerSec });
+}
+
+export async function getInputPacing(id: string): Promise<InputPacingStats> {
+  return invoke("get_input_pacing", { id });
+}
+
+export async function discardPendingInput(id: string): Promise<number> {
+  return invoke("discard_pending_input", { id });
+}
+
+export async function setSessionHotkey(id: string, hotkey: string | null): Promise<void> {
+  return invoke("set_session_hotkey", { id, hotkey });
+}
+
+export async function setSessionColor(id: string, color: string | null): Promise<void> {
+  return invoke("set_session_color", { id, color });
+}
+
+export async function setSessionTags(id: string, tags: string[]): Promise<void> {
+  return invoke("set_session_tags", { id, tags });
+}
+
+// Rejected if it would create a dependency cycle
+export async function setSessionDependency(id: string, dependsOn: string | null): Promise<void> {
+  return invoke("set_session_dependency", { id, dependsOn });
+}
+
+export async function getSessionsByTag(tag: string): Promise<SessionInfo[]> {
+  return invoke("get_sessions_by_tag", { tag });
+}
+
+export async function deriveSessionColor(id: string): Promise<string> {
+  return invoke("derive_session_color", { id });
+}
+
+export async function setSessionTerm(id: string, term: string | null): Promise<void> {
+  return invoke("set_session_term", { id, term });
+}
+
+export async function getSessionTerm(id: string): Promise<string> {
+  return invoke("get_session_term", { id });
+}
+
+export async function setSessionScroll(id: string, position: unknown | null): Promise<void> {
+  return invoke("set_session_scroll", { id, position });
+}
+
+export async function setTerminalQueryDetection(enabled: boolean): Promise<void> {
+  return invoke("set_terminal_query_detection", { enabled });
+}
+
+export async function setOutputFlushInterval(ms: number): Promise<void> {
+  return invoke("set_output_flush_interval", { ms });
+}
+
+// Larger output batches are sent as several pty-output events; null restores the 64 KiB default
+export async function setMaxOutputEventSize(bytes: number | null): Promise<void> {
+  return invoke("set_max_output_event_size", { bytes });
+}
+
+export async function setOutputEncoding(encoding: OutputEncoding): Promise<void> {
+  return invoke("set_output_encoding", { encoding });
+}
+
+// Raw bytes of a pty-output event, whichever encoding it was sent in
+export function outputBytes(output: PtyOutput): Uint8Array {
+  if (typeof output.data !== "string") {
+    return new Uint8Array(output.data);
+  }
+  const binary = atob(output.data);
+  const bytes = new Uint8Array(binary.length);
+  for (let i = 0; i < binary.length; i++) {
+    bytes[i] = binary.charCodeAt(i);
+  }
+  return bytes;
+}
+
 // Group commands
 export async function createGroup(name: string): Promise<SessionGroup> {
   return invoke("create_group", { name });
@@ -103,6 +741,19 @@ export async function deleteGroup(id: string): Promise<void> {
   return invoke("delete_group", { id });
 }
 
+export async function deleteGroupWithSessions(id: string): Promise<string[]> {
+  return invoke("delete_group_with_sessions", { id });
+}
+
+export async function killGroupSessions(id: string): Promise<string[]> {
+  return invoke("kill_group_sessions", { id });
+}
+
+// Restarts sessions one by one; listen for "session-restarting" / "session-restarted" to show progress
+export async function restartGroup(id: string): Promise<GroupRestartReport> {
+  return invoke("restart_group", { id });
+}
+
 export async function renameGroup(id: string, name: string): Promise<void> {
   return invoke("rename_group", { id, name });
 }
@@ -115,6 +766,63 @@ export async function getAllGroups(): Promise<SessionGroup[]> {
   return invoke("get_all_groups");
 }
 
+export async function setGroupHotkey(id: string, hotkey: string | null): Promise<void> {
+  return invoke("set_group_hotkey", { id, hotkey });
+}
+
+export async function setGroupStartupCommand(id: string, command: string | null): Promise<void> {
+  return invoke("set_group_startup_command", { id, command });
+}
+
+export async function setGroupColor(id: string, color: string | null): Promise<void> {
+  return invoke("set_group_color", { id, color });
+}
+
+export async function setGroupTags(id: string, tags: string[]): Promise<void> {
+  return invoke("set_group_tags", { id, tags });
+}
+
+export async function setGroupLayout(id: string, hint: string | null): Promise<void> {
+  return invoke("set_group_layout", { id, hint });
+}
+
+export async function reorderGroup(id: string, newOrder: number): Promise<SessionGroup[]> {
+  return invoke("reorder_group", { id, newOrder });
+}
+
+export async function normalizeGroupOrders(): Promise<SessionGroup[]> {
+  return invoke("normalize_group_orders");
+}
+
+// Profile commands
+export async function createProfile(
+  name: string,
+  shell?: string,
+  args?: string[],
+  cwd?: string,
+  env?: Record<string, string>,
+  startupCommands?: string[]
+): Promise<Profile> {
+  return invoke("create_profile", { name, shell, args, cwd, env, startupCommands });
+}
+
+export async function deleteProfile(id: string): Promise<void> {
+  return invoke("delete_profile", { id });
+}
+
+export async function getAllProfiles(): Promise<Profile[]> {
+  return invoke("get_all_profiles");
+}
+
+export async function createSessionFromProfile(
+  profileId: string,
+  overrides?: ProfileOverrides,
+  rows?: number,
+  cols?: number
+): Promise<SessionInfo> {
+  return invoke("create_session_from_profile", { profileId, overrides, rows, cols });
+}
+
 // Active session
 export async function setActiveSession(id: string | null): Promise<void> {
   return invoke("set_active_session", { id });
@@ -124,20 +832,184 @@ export async function getActiveSession(): Promise<string | null> {
   return invoke("get_active_session");
 }
 
+export async function setActiveSessionFallback(fallback: ActiveSessionFallback): Promise<void> {
+  return invoke("set_active_session_fallback", { fallback });
+}
+
+// Settings
+export async function getSettings(): Promise<Settings> {
+  return invoke("get_settings");
+}
+
+export async function setPathOverride(dirs: string[], mode: PathMode): Promise<void> {
+  return invoke("set_path_override", { dirs, mode });
+}
+
+export async function setScrollbackLimit(bytes: number | null): Promise<void> {
+  return invoke("set_scrollback_limit", { bytes });
+}
+
+export async function setScrollbackLineLimit(lines: number | null): Promise<void> {
+  return invoke("set_scrollback_line_limit", { lines });
+}
+
+export async function setLogRotation(maxBytes: number | null, maxFiles: number | null): Promise<void> {
+  return invoke("set_log_rotation", { maxBytes, maxFiles });
+}
+
+export async function setMaxSessions(max: number | null): Promise<void> {
+  return invoke("set_max_sessions", { max });
+}
+
+// Scripts send one JSON request per line: {"id", "command", "args"}; returns the socket path while listening
+export async function setControlSocket(enabled: boolean): Promise<string | null> {
+  return invoke("set_control_socket", { enabled });
+}
+
+export async function getControlSocketPath(): Promise<string | null> {
+  return invoke("get_control_socket_path");
+}
+
+// Diagnostics
+export async function pingSession(id: string): Promise<PingResult> {
+  return invoke("ping_session", { id });
+}
+
+export async function selfTest(): Promise<SelfTestReport> {
+  return invoke("self_test");
+}
+
 // Persistence
 export async function saveLayout(): Promise<void> {
   return invoke("save_layout");
 }
 
+export async function checkPersistenceHealth(): Promise<PersistenceHealth> {
+  return invoke("check_persistence_health");
+}
+
+export async function snapshotState(): Promise<AppState> {
+  return invoke("snapshot_state");
+}
+
 export async function loadLayout(): Promise<AppState> {
   return invoke("load_layout");
 }
 
+export async function getStateHost(): Promise<StateHost> {
+  return invoke("get_state_host");
+}
+
+export async function getRestoreReport(): Promise<RestoreReport | null> {
+  return invoke("get_restore_report");
+}
+
+export async function importLayoutPreview(path: string): Promise<LayoutPreview> {
+  return invoke("import_layout_preview", { path });
+}
+
+export async function exportLayout(path: string): Promise<void> {
+  return invoke("export_layout", { path });
+}
+
+// Sessions come back stopped; spawnPendingSession starts them
+export async function importLayout(path: string, merge: boolean): Promise<AppState> {
+  return invoke("import_layout", { path, merge });
+}
+
+export async function listWorkspaces(): Promise<Workspace[]> {
+  return invoke("list_workspaces");
+}
+
+export async function saveWorkspace(name: string): Promise<void> {
+  return invoke("save_workspace", { name });
+}
+
+// Kills the current sessions and respawns the workspace's
+export async function loadWorkspace(name: string): Promise<RestoreReport> {
+  return invoke("load_workspace", { name });
+}
+
+export async function deleteWorkspace(name: string): Promise<void> {
+  return invoke("delete_workspace", { name });
+}
+
+export async function listStateBackups(): Promise<StateBackup[]> {
+  return invoke("list_state_backups");
+}
+
+// Kills the current sessions and respawns the backup's
+export async function restoreStateBackup(name: string): Promise<RestoreReport> {
+  return invoke("restore_state_backup", { name });
+}
+
+export async function isStateDirty(): Promise<StateDirtyReport> {
+  return invoke("is_state_dirty");
+}
+
 // Event listeners
 export function onPtyOutput(callback: (output: PtyOutput) => void): Promise<UnlistenFn> {
   return listen<PtyOutput>("pty-output", (event) => callback(event.payload));
 }
 
+export function onPtyBell(callback: (bell: PtyBell) => void): Promise<UnlistenFn> {
+  return listen<PtyBell>("pty-bell", (event) => callback(event.payload));
+}
+
 export function onPtyExit(callback: (exit: PtyExit) => void): Promise<UnlistenFn> {
   return listen<PtyExit>("pty-exit", (event) => callback(event.payload));
 }
+
+export function onTerminalQuery(callback: (query: PtyTerminalQuery) => void): Promise<UnlistenFn> {
+  return listen<PtyTerminalQuery>("terminal-query", (event) => callback(event.payload));
+}
+
+export function onSessionTap(callback: (output: PtyTapOutput) => void): Promise<UnlistenFn> {
+  return listen<PtyTapOutput>("session-tap", (event) => callback(event.payload));
+}
+
+export function onEchoChanged(callback: (change: PtyEchoChanged) => void): Promise<UnlistenFn> {
+  return listen<PtyEchoChanged>("echo-changed", (event) => callback(event.payload));
+}
+
+export function onSessionRenamed(callback: (renamed: SessionRenamed) => void): Promise<UnlistenFn> {
+  return listen<SessionRenamed>("session-renamed", (event) => callback(event.payload));
+}
+
+export function onRepoContextChanged(callback: (change: RepoContextChanged) => void): Promise<UnlistenFn> {
+  return listen<RepoContextChanged>("repo-context-changed", (event) => callback(event.payload));
+}
+
+// Fired whenever saving the layout fails, whoever asked for the save
+export function onPersistenceError(callback: (error: PersistenceError) => void): Promise<UnlistenFn> {
+  return listen<PersistenceError>("persistence-error", (event) => callback(event.payload));
+}
+
+export function onSessionLogError(callback: (error: SessionLogError) => void): Promise<UnlistenFn> {
+  return listen<SessionLogError>("session-log-error", (event) => callback(event.payload));
+}
+
+export function onSessionCleared(callback: (cleared: SessionCleared) => void): Promise<UnlistenFn> {
+  return listen<SessionCleared>("session-cleared", (event) => callback(event.payload));
+}
+
+// Fired by restartGroup as it gets to each session, before its shell is replaced
+export function onSessionRestarting(callback: (id: string) => void): Promise<UnlistenFn> {
+  return listen<{ id: string }>("session-restarting", (event) => callback(event.payload.id));
+}
+
+export function onSessionRestarted(callback: (info: SessionInfo) => void): Promise<UnlistenFn> {
+  return listen<SessionInfo>("session-restarted", (event) => callback(event.payload));
+}
+
+export function onActiveSessionChanged(callback: (id: string | null) => void): Promise<UnlistenFn> {
+  return listen<string | null>("active-session-changed", (event) => callback(event.payload));
+}
+
+export function onGroupsUpdated(callback: (groups: SessionGroup[]) => void): Promise<UnlistenFn> {
+  return listen<SessionGroup[]>("groups-updated", (event) => callback(event.payload));
+}
+
+export function onRestoreComplete(callback: (report: RestoreReport) => void): Promise<UnlistenFn> {
+  return listen<RestoreReport>("restore-complete", (event) => callback(event.payload));
+}
@@ -87,7 +87,7 @@ export const useSessionStore = create<SessionStore>((set, get) => ({
       shell: info.shell,
       status: "running",
       terminal: null,
-      startupCommand: info.startup_command,
+      startupCommand: info.startup_commands.join("; ") || null,
     };
 
     set((state) => {
@@ -100,7 +100,10 @@ export const useSessionStore = create<SessionStore>((set, get) => ({
   },
 
   deleteSession: async (id: string) => {
-    await tauri.deleteSession(id);
+    const outcome = await tauri.deleteSession(id);
+    if (outcome.type === "NeedsConfirmation") {
+      return;
+    }
 
     set((state) => {
       const sessions = new Map(state.sessions);
@@ -232,7 +235,7 @@ export const useSessionStore = create<SessionStore>((set, get) => ({
       const groups = new Map(state.groups);
       const group = groups.get(id);
       if (group) {
-        groups.set(id, { ...group, name });
+        groups.set(id, { ...group, name: name.trim() });
       }
       return { groups };
     });
@@ -377,48 +380,36 @@ export const useSessionStore = create<SessionStore>((set, get) => ({
 
       set({ groups });
 
-      // Restore sessions from saved state
-      const savedSessions = state.sessions || [];
-      let firstSessionId: string | null = null;
-
-      for (const savedSession of savedSessions) {
-        try {
-          // Recreate each session with its saved name, group, and startup command
-          const info = await tauri.createSession(
-            savedSession.name,
-            undefined, // use default shell
-            savedSession.cwd,
-            savedSession.group_id || undefined,
-            savedSession.startup_command || undefined // Auto-run startup command
-          );
-          
-          const session: Session = {
-            id: info.id,
-            name: info.name,
-            groupId: info.group_id,
-            shell: info.shell,
-            status: "running",
-            terminal: null,
-            startupCommand: info.startup_command,
-          };
-
-          set((s) => {
-            const sessions = new Map(s.sessions);
-            sessions.set(session.id, session);
-            return { sessions };
-          });
-
-          if (!firstSessionId) {
-            firstSessionId = session.id;
-          }
-        } catch (err) {
-          console.error("Failed to restore session:", savedSession.name, err);
-        }
+      // Respawn saved sessions under their saved ids (startup commands run backend-side)
+      const report = await tauri.restoreSessions();
+      for (const err of report.errors) {
+        console.error("Failed to restore session:", err.session_id, err.error);
       }
 
-      // Set active session
+      const restored = await tauri.getAllSessions();
+      restored.sort((a, b) => a.created_at - b.created_at);
+      const sessions = new Map(get().sessions);
+      for (const info of restored) {
+        sessions.set(info.id, {
+          id: info.id,
+          name: info.name,
+          groupId: info.group_id,
+          shell: info.shell,
+          status: info.status.type === "Running" ? "running" : info.status.type === "Stopped" ? "stopped" : "error",
+          terminal: null,
+          startupCommand: info.startup_commands.join("; ") || null,
+        });
+      }
+
+      // Prefer the saved active session, else the first one
+      const activeSessionId =
+        state.active_session_id && sessions.has(state.active_session_id)
+          ? state.active_session_id
+          : restored[0]?.id ?? null;
+
       set({
-        activeSessionId: firstSessionId,
+        sessions,
+        activeSessionId,
         isLoading: false,
       });
     } catch (e) {